@@ -7,6 +7,59 @@ macro_rules! custom_err {
     }
 }
 
+/// Define the errors a player gets for rejected events, with stable
+/// codes for clients to localize the messages.  Such an error
+/// converts into a `HandleError` with its name as the message.  A
+/// released code never changes.  Also used by the MTT crates, each
+/// with its own range of codes.
+#[macro_export]
+macro_rules! coded_err {
+    ($($fn:ident => $variant:ident = $code:literal,)*) => {
+        #[repr(u16)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ErrorCode {
+            $($variant = $code,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            pub fn code(self) -> u16 {
+                self as u16
+            }
+
+            pub fn message(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => stringify!($fn),)*
+                }
+            }
+
+            /// Find the code of an error, None for the internal ones
+            pub fn of(error: &race_api::prelude::HandleError) -> Option<ErrorCode> {
+                match error {
+                    race_api::prelude::HandleError::Custom(message) => Self::ALL
+                        .iter()
+                        .copied()
+                        .find(|c| c.message() == message.as_str()),
+                    _ => None,
+                }
+            }
+        }
+
+        impl From<ErrorCode> for race_api::prelude::HandleError {
+            fn from(code: ErrorCode) -> Self {
+                race_api::prelude::HandleError::Custom(String::from(code.message()))
+            }
+        }
+
+        $(
+            pub fn $fn() -> race_api::prelude::HandleError {
+                ErrorCode::$variant.into()
+            }
+        )*
+    }
+}
+
 custom_err!(internal_player_not_found);
 custom_err!(internal_pot_has_no_owner);
 custom_err!(internal_malformed_total_bet);
@@ -22,22 +75,10 @@ custom_err!(next_button_player_not_found);
 custom_err!(next_button_position_not_found);
 custom_err!(heads_up_missing_sb);
 custom_err!(heads_up_missing_bb);
+custom_err!(heads_up_btn_not_sb);
 custom_err!(mplayers_missing_sb);
 custom_err!(mplayers_missing_bb);
 custom_err!(pot_winner_missing);
-custom_err!(not_the_acting_player_to_bet);
-custom_err!(not_the_acting_player_to_raise);
-custom_err!(not_the_acting_player_to_check);
-custom_err!(not_the_acting_player_to_fold);
-custom_err!(not_the_acting_player_to_call);
-custom_err!(not_the_acting_player);
-custom_err!(player_cant_bet);
-custom_err!(bet_amonut_is_too_small);
-custom_err!(raise_amount_is_too_small);
-custom_err!(player_already_betted);
-custom_err!(player_cant_check);
-custom_err!(player_cant_raise);
-custom_err!(cannot_join_full_table);
 custom_err!(strongest_hand_not_found);
 custom_err!(invalid_hole_cards_number);
 custom_err!(first_hole_card_index_missing);
@@ -47,3 +88,74 @@ custom_err!(second_hole_card_error);
 custom_err!(flop_cards_error);
 custom_err!(turn_card_error);
 custom_err!(river_card_error);
+custom_err!(deposit_player_not_found);
+custom_err!(replay_blinds_mismatch);
+custom_err!(replay_chips_change_mismatch);
+
+coded_err! {
+    // Betting actions
+    not_the_acting_player_to_bet => NotTheActingPlayerToBet = 100,
+    not_the_acting_player_to_raise => NotTheActingPlayerToRaise = 101,
+    not_the_acting_player_to_check => NotTheActingPlayerToCheck = 102,
+    not_the_acting_player_to_fold => NotTheActingPlayerToFold = 103,
+    not_the_acting_player_to_call => NotTheActingPlayerToCall = 104,
+    not_the_acting_player => NotTheActingPlayer = 105,
+    player_cant_bet => PlayerCantBet = 110,
+    bet_amonut_is_too_small => BetAmountIsTooSmall = 111,
+    raise_amount_is_too_small => RaiseAmountIsTooSmall = 112,
+    player_already_betted => PlayerAlreadyBetted = 113,
+    player_cant_check => PlayerCantCheck = 114,
+    player_cant_raise => PlayerCantRaise = 115,
+    raise_exceeds_pot_limit => RaiseExceedsPotLimit = 116,
+    betting_capped => BettingCapped = 117,
+    max_raises_per_street_reached => MaxRaisesPerStreetReached = 118,
+    action_not_reopened => ActionNotReopened = 119,
+    action_already_held => ActionAlreadyHeld = 120,
+
+    // Joining and deposits
+    cannot_join_full_table => CannotJoinFullTable = 200,
+    deposit_below_min_buyin => DepositBelowMinBuyin = 201,
+    deposit_exceeds_max_buyin => DepositExceedsMaxBuyin = 202,
+    deposit_not_multiple_of_increment => DepositNotMultipleOfIncrement = 203,
+
+    // Table options
+    run_it_twice_not_available => RunItTwiceNotAvailable = 300,
+    player_cant_agree_run_it_twice => PlayerCantAgreeRunItTwice = 301,
+    rabbit_hunt_not_available => RabbitHuntNotAvailable = 302,
+    show_cards_not_available => ShowCardsNotAvailable = 303,
+    auto_rebuy_not_available => AutoRebuyNotAvailable = 304,
+    sit_out_not_available => SitOutNotAvailable = 305,
+    chop_blinds_not_available => ChopBlindsNotAvailable = 306,
+    muck_not_available => MuckNotAvailable = 307,
+    insurance_not_available => InsuranceNotAvailable = 308,
+    insurance_premium_too_large => InsurancePremiumTooLarge = 309,
+    observe_not_available => ObserveNotAvailable = 310,
+    sit_in_not_available => SitInNotAvailable = 311,
+    dead_blind_not_available => DeadBlindNotAvailable = 312,
+    allin_protection_not_sendable => AllinProtectionNotSendable = 313,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_are_unique_and_stable() {
+        let mut codes: Vec<u16> = ErrorCode::ALL.iter().map(|c| c.code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+
+        assert_eq!(ErrorCode::NotTheActingPlayer.code(), 105);
+        assert_eq!(ErrorCode::BetAmountIsTooSmall.code(), 111);
+        assert_eq!(ErrorCode::PlayerCantCheck.code(), 114);
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::of(&(*code).into()), Some(*code));
+        }
+        assert_eq!(
+            player_cant_check(),
+            race_api::prelude::HandleError::Custom("player_cant_check".to_string())
+        );
+        assert_eq!(ErrorCode::of(&internal_player_not_found()), None);
+    }
+}
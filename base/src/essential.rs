@@ -1,10 +1,13 @@
 //! Holdem essentials such as bet, player, pot, street and so on.
 
+use crate::evaluator::{joker_deck_cards, short_deck_cards, standard_deck_cards};
 use borsh::{BorshDeserialize, BorshSerialize};
 use race_api::prelude::{CustomEvent, HandleError};
 use std::collections::BTreeMap;
 
 pub const MAX_ACTION_TIMEOUT_COUNT: u8 = 2;
+pub const FIXED_LIMIT_MAX_RAISES: u8 = 3; // after the opening bet
+pub const INTEGRITY_WINDOW: usize = 20; // the recent confrontations checked for chip dumping
 pub const ACTION_TIMEOUT_PREFLOP: u64 = 12_000;
 pub const ACTION_TIMEOUT_POSTFLOP: u64 = 15_000;
 pub const ACTION_TIMEOUT_TURN: u64 = 20_000;
@@ -14,8 +17,11 @@ pub const WAIT_TIMEOUT_DEFAULT: u64 = 5_000;
 pub const WAIT_TIMEOUT_LAST_PLAYER: u64 = 5_000;
 pub const WAIT_TIMEOUT_SHOWDOWN: u64 = 10_000;
 pub const WAIT_TIMEOUT_RUNNER: u64 = 13_000;
+pub const WAIT_TIMEOUT_RUN_IT_TWICE: u64 = 5_000;
+pub const WAIT_TIMEOUT_INSURANCE: u64 = 10_000;
 
 pub const RAKE_SLOT_ID: u8 = 0;
+pub const JACKPOT_SLOT_ID: u8 = 1;
 
 /// Holdem Modes in which a specific table type is defined
 #[derive(BorshSerialize, BorshDeserialize, Default, PartialEq, Debug, Clone, Copy)]
@@ -38,6 +44,8 @@ pub enum PlayerStatus {
     Init, // Indicating new players ready for the next hand
     Leave,
     Out,
+    SitOut,   // Keeping the seat but dealt out for some hands
+    Observer, // Watching the table without being dealt in
 }
 
 
@@ -56,6 +64,11 @@ pub struct Player {
     pub status: PlayerStatus,
     pub timeout: u8,          // count the times of action timeout
     pub deposit: u64,         // The deposited amount
+    pub sitout_hands: u8,     // count the hands sitting out in a row
+    pub protection_used: bool, // the all-in protection is used in this session
+    pub auto_rebuy_to: Option<u64>, // the stack to top up to between hands
+    pub sitout_hands_remaining: u8, // the hands to sit out voluntarily
+    pub rake_exempt: bool,    // the chips put in pots are never raked
 }
 
 impl Player {
@@ -67,6 +80,11 @@ impl Player {
             status: PlayerStatus::default(),
             timeout,
             deposit: 0,
+            sitout_hands: 0,
+            protection_used: false,
+            auto_rebuy_to: None,
+            sitout_hands_remaining: 0,
+            rake_exempt: false,
         }
     }
 
@@ -83,6 +101,11 @@ impl Player {
             status,
             timeout: 0,
             deposit: 0,
+            sitout_hands: 0,
+            protection_used: false,
+            auto_rebuy_to: None,
+            sitout_hands_remaining: 0,
+            rake_exempt: false,
         }
     }
 
@@ -94,6 +117,11 @@ impl Player {
             status: PlayerStatus::Init,
             timeout: 0,
             deposit: 0,
+            sitout_hands: 0,
+            protection_used: false,
+            auto_rebuy_to: None,
+            sitout_hands_remaining: 0,
+            rake_exempt: false,
         }
     }
 
@@ -101,9 +129,17 @@ impl Player {
         self.id
     }
 
+    /// Whether the player is not dealt in current hand
+    pub fn is_dealt_out(&self) -> bool {
+        matches!(
+            self.status,
+            PlayerStatus::Init | PlayerStatus::SitOut | PlayerStatus::Observer
+        )
+    }
+
     pub fn next_to_act(&self) -> bool {
         match self.status {
-            PlayerStatus::Allin | PlayerStatus::Fold | PlayerStatus::Init | PlayerStatus::Leave | PlayerStatus::Out => false,
+            PlayerStatus::Allin | PlayerStatus::Fold | PlayerStatus::Init | PlayerStatus::Leave | PlayerStatus::Out | PlayerStatus::SitOut | PlayerStatus::Observer => false,
             _ => true,
         }
     }
@@ -135,6 +171,18 @@ pub struct ActingPlayer {
     pub clock: u64, // action clock
 }
 
+/// The decision context of the acting player, for clients to render
+/// decision aids.  The raise amounts are the chips to put in, as in
+/// [`GameEvent::Raise`].
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ActingContext {
+    pub player_id: u64,
+    pub to_call: u64,
+    pub pot: u64, // pots and bets of current street
+    pub min_raise: u64,
+    pub max_raise: u64,
+}
+
 /// Representation of Holdem pot
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct Pot {
@@ -152,12 +200,24 @@ impl Pot {
         }
     }
 
+    pub fn has_same_owners(&self, other: &Pot) -> bool {
+        self.owners.len() == other.owners.len()
+            && self.owners.iter().all(|o| other.owners.contains(o))
+    }
+
     pub fn merge(&mut self, other: &Pot) -> Result<(), HandleError> {
         self.amount += other.amount;
         Ok(())
     }
 }
 
+// A pot with the players eligible to win it, the main pot comes first
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PotView {
+    pub owners: Vec<u64>,
+    pub amount: u64,
+}
+
 /// Holdem Streets
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Default, Copy, Clone)]
 pub enum Street {
@@ -182,6 +242,87 @@ pub enum HoldemStage {
     Showdown,
 }
 
+/// Who posts the ante: everyone in the hand, or only the BB or the
+/// button posts a single ante for the table
+#[derive(Default, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum AnteType {
+    #[default]
+    EachPlayer,
+    BigBlind,
+    Button,
+}
+
+/// Betting structures that limit the bet and raise amounts
+#[derive(Default, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BettingMode {
+    #[default]
+    NoLimit,
+    PotLimit,
+    FixedLimit,
+}
+
+/// The cards shuffled into the deck
+#[derive(Default, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum DeckSpec {
+    #[default]
+    Standard,
+    // The 36 cards from 6 to A, with short deck rankings
+    ShortDeck36,
+    // The 52 cards and two jokers, a joker stands for any card
+    WithJokers,
+}
+
+impl DeckSpec {
+    pub fn cards(&self) -> Vec<String> {
+        match self {
+            DeckSpec::Standard => standard_deck_cards(),
+            DeckSpec::ShortDeck36 => short_deck_cards(),
+            DeckSpec::WithJokers => joker_deck_cards(),
+        }
+    }
+}
+
+/// The poker game played at the table
+#[derive(Default, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum GameVariant {
+    #[default]
+    Holdem,
+    // Four hole cards, the high hand takes exactly two of them, and
+    // each pot is split with the best 8-or-better low
+    OmahaHiLo,
+}
+
+impl GameVariant {
+    pub fn hole_cards_count(&self) -> usize {
+        match self {
+            GameVariant::Holdem => 2,
+            GameVariant::OmahaHiLo => 4,
+        }
+    }
+}
+
+/// Action timeouts (in msecs) of a table, replacing the defaults
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ActionTimeouts {
+    pub preflop: u64,
+    pub postflop: u64,
+    pub turn: u64,
+    pub river: u64,
+    pub afk: u64, // for players who timed out last time, 0 to use the street's
+}
+
+impl Default for ActionTimeouts {
+    fn default() -> Self {
+        Self {
+            preflop: ACTION_TIMEOUT_PREFLOP,
+            postflop: ACTION_TIMEOUT_POSTFLOP,
+            turn: ACTION_TIMEOUT_TURN,
+            river: ACTION_TIMEOUT_RIVER,
+            afk: 0,
+        }
+    }
+}
+
 /// Representation of a specific on-chain Holdem game account
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug)]
 pub struct HoldemAccount {
@@ -192,6 +333,35 @@ pub struct HoldemAccount {
     pub rake_cap: u8,          // the maximum rake in BB
     pub max_deposit: u64,      // the maximum deposit in chips, usually 100BB
     pub theme: Option<String>, // an optional theme identifier
+    pub min_think_time: u64,   // the minimum msecs before an action takes effect, 0 to disable
+    pub max_sitout_hands: u8,  // the hands a player can sit out before removed, 0 to disable
+    pub deposit_increment: u64, // deposits must be multiples of it, 0 to disable
+    pub max_ante_ratio: u16,   // the max ante to post (per thousand of the stack), 0 to disable
+    pub headsup_btn_posts_blinds: bool, // the button posts both blinds in heads-up
+    pub dealt_in_when_posted: bool, // only players who can post the ante are dealt in
+    pub run_it_twice: bool,    // allow running the board twice in an all-in
+    pub deck_spec: DeckSpec,   // the cards in the deck, also for the hand rankings
+    pub action_timeouts: Option<ActionTimeouts>, // None to use the defaults
+    pub betting_mode: BettingMode,
+    pub ante_type: AnteType,
+    pub min_deposit: u64, // the minimum buy-in in chips, 0 to disable
+    pub kill_game: bool,  // a player scooping two pots in a row posts a kill blind
+    pub kill_threshold: u64, // a scooped pot of at least this size also triggers a kill, 0 to disable
+    pub max_raises_per_street: Option<u8>, // None for unlimited raises
+    pub allin_protection: bool, // a timed out player facing a bet goes all-in, once per session
+    pub dead_button: bool, // the BB always moves to the next player, the button may be dead
+    pub no_flop_no_drop: bool, // no rake unless the flop is dealt
+    pub no_showdown_no_rake: bool, // no rake unless the hand goes to showdown
+    pub insurance: bool, // offer insurance to the favorite of an all-in confrontation
+    pub insurance_fund: u64, // chips the house puts in the game account to pay the insurance
+    pub bad_beat_drop: u64, // chips dropped from each flop hand into the bad beat jackpot, 0 to disable
+    pub bomb_pot_interval: u16, // every Nth hand is a bomb pot, 0 to disable
+    pub bomb_pot_ante: u64, // the ante everyone posts in a bomb pot
+    pub action_warning: u64, // msecs before the action timeout to warn the acting player, 0 to disable
+    pub min_players_to_start: u8, // the players seated to start a hand, at least 2
+    pub blinds_enabled: bool, // false for ante-only hands without blinds
+    pub integrity_threshold: u8, // the losses to one opponent to flag chip dumping, 0 to disable
+    pub rake_cap_chips: Option<u64>, // the maximum rake in chips, replacing `rake_cap`
 }
 
 impl Default for HoldemAccount {
@@ -203,7 +373,35 @@ impl Default for HoldemAccount {
             rake: 3,
             rake_cap: 1,
             max_deposit: 2000,
-            theme: None
+            theme: None,
+            min_think_time: 0,
+            max_sitout_hands: 0,
+            deposit_increment: 0,
+            max_ante_ratio: 0,
+            headsup_btn_posts_blinds: false,
+            dealt_in_when_posted: false,
+            run_it_twice: false,
+            deck_spec: DeckSpec::Standard,
+            action_timeouts: None,
+            betting_mode: BettingMode::NoLimit,
+            ante_type: AnteType::EachPlayer,
+            min_deposit: 0,
+            kill_game: false,
+            kill_threshold: 0,
+            max_raises_per_street: None,
+            allin_protection: false,
+            dead_button: false,
+            no_flop_no_drop: false,
+            no_showdown_no_rake: false,
+            insurance: false,
+            insurance_fund: 0,
+            bad_beat_drop: 0,
+            bomb_pot_interval: 0,
+            bomb_pot_ante: 0,
+            action_warning: 0,
+            min_players_to_start: 2,
+            blinds_enabled: true,
+            integrity_threshold: 0,
         }
     }
 }
@@ -217,16 +415,61 @@ pub enum GameEvent {
     Call,
     Fold,
     Raise(u64),
+    AgreeRunItTwice,
+    RabbitHunt,
+    ShowCards,
+    // Top up to the stack between hands, 0 to disable
+    SetAutoRebuy(u64),
+    // Sit out for the number of hands from the next one, 0 to return
+    SitOut(u8),
+    // Agree to take back the blinds in heads-up preflop
+    ChopBlinds,
+    // Decline to show the hole cards at showdown, forfeiting the pot
+    Muck,
+    // Show one of the hole cards by its index, 0 or 1
+    ShowOne(u8),
+    // Pay the premium to insure the hand against the outs
+    BuyInsurance(u64),
+    // Watch the table without being dealt in, until sitting in
+    Observe,
+    SitIn,
+    // Return from sitting out in the next hand by posting SB and BB
+    PostDeadBlind,
+    // Recorded in the hand history for a timed out player protected
+    // as all-in, never sent by players
+    AllinProtected,
 }
 
 impl CustomEvent for GameEvent {}
 
 /// The following structs are used for the front-end to display animations.
+// The part of a pot awarded, a pot in hi-lo games is split into
+// halves unless no low qualifies
+#[derive(Default, BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PotShare {
+    #[default]
+    Whole,
+    High,
+    Low,
+}
+
 // A pot used for awarding winners
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct AwardPot {
     pub winners: Vec<u64>,
     pub amount: u64,
+    pub share: PotShare,
+}
+
+// The insurance offered to the favorite of an all-in confrontation
+// on the turn against the outs on the river.  An outdrawn buyer is
+// paid the premium * (cards_left - outs) / outs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InsuranceOffer {
+    pub player_id: u64,
+    pub outs: u8,
+    pub cards_left: u8,
+    pub max_premium: u64,
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
@@ -249,10 +492,61 @@ pub enum Display {
         old_pots: Vec<Pot>,
         bet_map: BTreeMap<u64, u64>,
     },
+    // The main and side pots after collecting bets
+    Pots {
+        pots: Vec<PotView>,
+    },
     AwardPots {
         pots: Vec<AwardPot>,
     },
     GameResult {
         player_map: BTreeMap<u64, PlayerResult>,
+        // The board, or both boards when run it twice
+        boards: Vec<Vec<String>>,
+    },
+    // Equities of players in an all-in confrontation
+    Equities {
+        equities: BTreeMap<u64, f64>,
+    },
+    // The board with the undealt cards of a hand ended early
+    RabbitHunt {
+        board: Vec<String>,
+    },
+    // The blinds are returned as both players agree
+    ChopBlinds,
+    InsuranceOffer {
+        offer: InsuranceOffer,
+    },
+    // The insured player is outdrawn and compensated from the insurance pool
+    InsurancePayout {
+        player_id: u64,
+        amount: u64,
+    },
+    // A hand of quads or better is beaten at showdown
+    BadBeat {
+        winner: u64,
+        loser: u64,
+    },
+    // The part of a bet nobody has called is back to the stack
+    ReturnUncalledBet {
+        player_id: u64,
+        amount: u64,
+    },
+    // The acting player is running out of time
+    ActionWarning {
+        player_id: u64,
+    },
+    // A deposit made in the middle of a hand, to be added to the
+    // stack before the next one
+    DepositPending {
+        player_id: u64,
+        amount: u64,
+    },
+    // A player keeps losing to the same opponent, for an off-chain
+    // review of chip dumping
+    IntegrityFlag {
+        player_id: u64,
+        opponent_id: u64,
+        count: u8,
     },
 }
@@ -2,6 +2,8 @@
 
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 
@@ -124,10 +126,51 @@ pub enum Category {
     HighCard,
 }
 
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Category::RoyalFlush => "RoyalFlush",
+            Category::StraightFlush => "StraightFlush",
+            Category::FourOfAKind => "FourOfAKind",
+            Category::FullHouse => "FullHouse",
+            Category::Flush => "Flush",
+            Category::Straight => "Straight",
+            Category::ThreeOfAKind => "ThreeOfAKind",
+            Category::TwoPairs => "TwoPairs",
+            Category::Pair => "Pair",
+            Category::HighCard => "HighCard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parse the text form of a category, for readers that still keep
+/// the category as a string.
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "RoyalFlush" => Ok(Category::RoyalFlush),
+            "StraightFlush" => Ok(Category::StraightFlush),
+            "FourOfAKind" => Ok(Category::FourOfAKind),
+            "FullHouse" => Ok(Category::FullHouse),
+            "Flush" => Ok(Category::Flush),
+            "Straight" => Ok(Category::Straight),
+            "ThreeOfAKind" => Ok(Category::ThreeOfAKind),
+            "TwoPairs" => Ok(Category::TwoPairs),
+            "Pair" => Ok(Category::Pair),
+            "HighCard" => Ok(Category::HighCard),
+            _ => Err(format!("Invalid hand category: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PlayerHand<'a> {
     pub category: Category,  // rankings
     pub picks: Vec<&'a str>, // Best 5 out of 7
+    pub kickers: Vec<u8>,    // Ranks of picks, in comparison order
     pub value: Vec<u8>,      // [value, category_order ...]
 }
 
@@ -193,7 +236,24 @@ const POSSIBLE_STRAIGHTS_ORDERS: [[u8; 5]; 10] = [
     [5, 4, 3, 2, 14],
 ];
 
+/// In short deck, A-6-7-8-9 is the lowest straight
+const SHORT_DECK_STRAIGHTS_ORDERS: [[u8; 5]; 6] = [
+    [14, 13, 12, 11, 10],
+    [13, 12, 11, 10, 9],
+    [12, 11, 10, 9, 8],
+    [11, 10, 9, 8, 7],
+    [10, 9, 8, 7, 6],
+    [9, 8, 7, 6, 14],
+];
+
 fn find_straights<'a>(cards: &Vec<&'a str>) -> (bool, Vec<Vec<&'a str>>) {
+    find_straights_by_orders(cards, &POSSIBLE_STRAIGHTS_ORDERS)
+}
+
+fn find_straights_by_orders<'a>(
+    cards: &Vec<&'a str>,
+    straights_orders: &[[u8; 5]],
+) -> (bool, Vec<Vec<&'a str>>) {
     let order_to_cards = |o: u8| {
         cards
             .iter()
@@ -203,7 +263,7 @@ fn find_straights<'a>(cards: &Vec<&'a str>) -> (bool, Vec<Vec<&'a str>>) {
     };
 
     let mut results = Vec::new();
-    for orders in POSSIBLE_STRAIGHTS_ORDERS {
+    for orders in straights_orders {
         let cards_vec = orders
             .iter()
             .map(|o| order_to_cards(*o))
@@ -312,6 +372,16 @@ pub fn compare_hands(handv1: &Vec<u8>, handv2: &Vec<u8>) -> Ordering {
 
 /// This fn accpets unsorted cards.
 pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
+    evaluate(cards, false)
+}
+
+/// Evaluate cards with short deck (6+) rankings: a flush beats a full
+/// house and A-6-7-8-9 is the lowest straight.
+pub fn evaluate_short_deck_cards(cards: Vec<&str>) -> PlayerHand {
+    evaluate(cards, true)
+}
+
+fn evaluate(cards: Vec<&str>, short_deck: bool) -> PlayerHand {
     let sorted_by_group: Vec<&str> = sort_suited_cards(&cards);
     let sorted_kinds: Vec<&str> = sorted_by_group
         .iter()
@@ -325,7 +395,11 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
 
     let mut sorted_cards: Vec<&str> = cards.iter().map(|c| *c).collect();
     sorted_cards.sort_by(|&c1, &c2| compare_kinds(c1, c2));
-    let (has_straights, straights) = find_straights(&sorted_cards);
+    let (has_straights, straights) = if short_deck {
+        find_straights_by_orders(&sorted_cards, &SHORT_DECK_STRAIGHTS_ORDERS)
+    } else {
+        find_straights(&sorted_cards)
+    };
     let sflush = find_straight_flush(&flush_cards, &straights);
 
     // royal flush
@@ -334,6 +408,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::RoyalFlush,
             picks: rflush,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -344,6 +419,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::StraightFlush,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -354,26 +430,29 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::FourOfAKind,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
-    // full house
-    else if check_same_kinds(&sorted_kinds, Category::FullHouse) {
+    // full house, which is below flush in short deck
+    else if check_same_kinds(&sorted_kinds, Category::FullHouse) && !(short_deck && has_flush) {
         let picks = sorted_by_group[0..5].to_vec();
-        let value = tag_value(&picks, 6);
+        let value = tag_value(&picks, if short_deck { 5 } else { 6 });
         PlayerHand {
             category: Category::FullHouse,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
     // flush
     else if has_flush {
         let picks = flush_cards[0..5].to_vec();
-        let value = tag_value(&picks, 5);
+        let value = tag_value(&picks, if short_deck { 6 } else { 5 });
         PlayerHand {
             category: Category::Flush,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -384,6 +463,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::Straight,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -394,6 +474,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::ThreeOfAKind,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -404,6 +485,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::TwoPairs,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -414,6 +496,7 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::Pair,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
@@ -424,16 +507,832 @@ pub fn evaluate_cards(cards: Vec<&str>) -> PlayerHand {
         PlayerHand {
             category: Category::HighCard,
             picks,
+            kickers: value[1..].to_vec(),
             value,
         }
     }
 }
 
+/// Make the hand of picks, tagged with the order of category
+fn make_hand(category: Category, picks: Vec<&str>) -> PlayerHand {
+    let value = tag_value(&picks, category_order(category));
+    PlayerHand {
+        category,
+        picks,
+        kickers: value[1..].to_vec(),
+        value,
+    }
+}
+
+/// A faster path of `evaluate_cards` for exactly seven cards, which
+/// counts the kinds and suits in place of the searches.  The result is
+/// identical to `evaluate_cards`, other numbers of cards are passed
+/// to it.
+pub fn evaluate_seven(cards: Vec<&str>) -> PlayerHand {
+    if cards.len() != 7 {
+        return evaluate_cards(cards);
+    }
+    // The first card of the kind (and the suit), in the input order
+    let find = |suit: Option<&str>, order: u8| {
+        cards
+            .iter()
+            .find(|c| kind_to_order(c) == order && suit.map_or(true, |s| c.split_at(1).0 == s))
+            .copied()
+    };
+    let straight_of = |suit: Option<&str>| {
+        POSSIBLE_STRAIGHTS_ORDERS.iter().find_map(|orders| {
+            orders
+                .iter()
+                .map(|o| find(suit, *o))
+                .collect::<Option<Vec<&str>>>()
+        })
+    };
+    // At most one suit can have five of the seven cards
+    let flush_suit = SUITS
+        .iter()
+        .copied()
+        .find(|s| cards.iter().filter(|c| c.split_at(1).0 == *s).count() >= 5);
+    if let Some(picks) = flush_suit.and_then(|s| straight_of(Some(s))) {
+        if kind_to_order(picks[0]) == 14 {
+            return make_hand(Category::RoyalFlush, picks);
+        } else {
+            return make_hand(Category::StraightFlush, picks);
+        }
+    }
+
+    // Groups of (count, kind), larger groups and higher kinds first
+    let mut kind_counts = [0usize; 15];
+    for card in cards.iter() {
+        kind_counts[kind_to_order(card) as usize] += 1;
+    }
+    let mut groups: Vec<(usize, u8)> = (0..15u8)
+        .filter(|k| kind_counts[*k as usize] > 0)
+        .map(|k| (kind_counts[k as usize], k))
+        .collect();
+    groups.sort_by(|(n1, k1), (n2, k2)| n2.cmp(n1).then(k2.cmp(k1)));
+    let by_group: Vec<&str> = groups
+        .iter()
+        .flat_map(|(_, k)| cards.iter().filter(move |c| kind_to_order(c) == *k))
+        .take(5)
+        .copied()
+        .collect();
+    let top = groups[0].0;
+    let second = groups.get(1).map_or(0, |(n, _)| *n);
+
+    if top == 4 {
+        make_hand(Category::FourOfAKind, by_group)
+    } else if top == 3 && second >= 2 {
+        make_hand(Category::FullHouse, by_group)
+    } else if let Some(suit) = flush_suit {
+        let mut picks: Vec<&str> = cards
+            .iter()
+            .filter(|c| c.split_at(1).0 == suit)
+            .copied()
+            .collect();
+        picks.sort_by(|c1, c2| compare_kinds(c1, c2));
+        picks.truncate(5);
+        make_hand(Category::Flush, picks)
+    } else if let Some(picks) = straight_of(None) {
+        make_hand(Category::Straight, picks)
+    } else if top == 3 {
+        make_hand(Category::ThreeOfAKind, by_group)
+    } else if top == 2 && second == 2 {
+        make_hand(Category::TwoPairs, by_group)
+    } else if top == 2 {
+        make_hand(Category::Pair, by_group)
+    } else {
+        make_hand(Category::HighCard, by_group)
+    }
+}
+
+const SUITS: [&str; 4] = ["s", "h", "d", "c"];
+const KINDS: [&str; 13] = ["a", "k", "q", "j", "t", "9", "8", "7", "6", "5", "4", "3", "2"];
+
+/// The 36 cards of a short deck, with 2s through 5s removed
+pub fn short_deck_cards() -> Vec<String> {
+    SUITS
+        .iter()
+        .flat_map(|s| KINDS[..9].iter().map(move |k| format!("{}{}", s, k)))
+        .collect()
+}
+
+/// The red and the black joker
+pub const JOKERS: [&str; 2] = ["jr", "jb"];
+
+const STANDARD_DECK: [&str; 52] = [
+    "sa", "sk", "sq", "sj", "st", "s9", "s8", "s7", "s6", "s5", "s4", "s3", "s2", "ha", "hk", "hq",
+    "hj", "ht", "h9", "h8", "h7", "h6", "h5", "h4", "h3", "h2", "da", "dk", "dq", "dj", "dt", "d9",
+    "d8", "d7", "d6", "d5", "d4", "d3", "d2", "ca", "ck", "cq", "cj", "ct", "c9", "c8", "c7", "c6",
+    "c5", "c4", "c3", "c2",
+];
+
+/// The 52 cards of a standard deck
+pub fn standard_deck_cards() -> Vec<String> {
+    STANDARD_DECK.iter().map(|c| c.to_string()).collect()
+}
+
+/// The 54 cards of a standard deck with the two jokers
+pub fn joker_deck_cards() -> Vec<String> {
+    STANDARD_DECK
+        .iter()
+        .chain(JOKERS.iter())
+        .map(|c| c.to_string())
+        .collect()
+}
+
+/// Evaluate cards where a joker stands for any card not among them,
+/// the best hand is taken.  The picks show the cards the jokers stand
+/// for.
+pub fn evaluate_with_jokers(cards: Vec<&str>) -> PlayerHand {
+    let (jokers, mut cards): (Vec<&str>, Vec<&str>) =
+        cards.into_iter().partition(|c| JOKERS.contains(c));
+    if jokers.is_empty() {
+        return evaluate_seven(cards);
+    }
+    let candidates: Vec<&str> = STANDARD_DECK
+        .iter()
+        .filter(|c| !cards.contains(c))
+        .copied()
+        .collect();
+    let mut best = None;
+    substitute_jokers(&mut cards, jokers.len(), &candidates, &mut best);
+    best.unwrap_or_else(|| evaluate_seven(cards))
+}
+
+// Try the candidates for the jokers left, in combinations as the
+// jokers are interchangeable
+fn substitute_jokers<'a>(
+    cards: &mut Vec<&'a str>,
+    jokers: usize,
+    candidates: &[&'a str],
+    best: &mut Option<PlayerHand<'a>>,
+) {
+    if jokers == 0 {
+        let hand = evaluate_seven(cards.clone());
+        let better = match best {
+            Some(b) => compare_hands(&hand.value, &b.value) == Ordering::Greater,
+            None => true,
+        };
+        if better {
+            *best = Some(hand);
+        }
+        return;
+    }
+    for (i, card) in candidates.iter().enumerate() {
+        cards.push(card);
+        substitute_jokers(cards, jokers - 1, &candidates[(i + 1)..], best);
+        cards.pop();
+    }
+}
+
+/// Find the best qualifying low (8-or-better) in Omaha Hi-Lo, made
+/// of exactly two hole cards and three community cards.  A counts as
+/// the lowest card, straights and flushes don't count against a low.
+/// The result lists the five ranks from high to low, thus a smaller
+/// value is a better low, e.g. [5, 4, 3, 2, 1] is the nuts.  Return
+/// None when no low qualifies.
+pub fn evaluate_low(hole_cards: &[&str], board: &[&str]) -> Option<Vec<u8>> {
+    let low_order = |c: &str| match kind_to_order(c) {
+        14 => 1,
+        o => o,
+    };
+    let mut best: Option<Vec<u8>> = None;
+    for cards in omaha_combinations(hole_cards, board) {
+        let mut ranks: Vec<u8> = cards.iter().map(|c| low_order(c)).collect();
+        ranks.sort_by(|r1, r2| r2.cmp(r1));
+        ranks.dedup();
+        if ranks.len() == 5 && ranks[0] <= 8 && best.as_ref().map_or(true, |b| ranks < *b) {
+            best = Some(ranks);
+        }
+    }
+    best
+}
+
+/// Find the best high hand in Omaha, made of exactly two hole cards
+/// and three community cards.  Fewer cards are evaluated as a whole.
+pub fn evaluate_omaha_high<'a>(hole_cards: &[&'a str], board: &[&'a str]) -> PlayerHand<'a> {
+    let mut best: Option<PlayerHand> = None;
+    for cards in omaha_combinations(hole_cards, board) {
+        let hand = evaluate_cards(cards);
+        let better = match &best {
+            Some(b) => compare_hands(&hand.value, &b.value) == Ordering::Greater,
+            None => true,
+        };
+        if better {
+            best = Some(hand);
+        }
+    }
+    best.unwrap_or_else(|| evaluate_cards(create_cards(board, hole_cards)))
+}
+
+// All the five cards made of two hole cards and three community cards
+fn omaha_combinations<'a>(hole_cards: &[&'a str], board: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut combinations = Vec::new();
+    if hole_cards.len() < 2 || board.len() < 3 {
+        return combinations;
+    }
+    let mut hole_combo: Vec<usize> = (0..2).collect();
+    loop {
+        let mut board_combo: Vec<usize> = (0..3).collect();
+        loop {
+            let cards = hole_combo
+                .iter()
+                .map(|i| hole_cards[*i])
+                .chain(board_combo.iter().map(|i| board[*i]))
+                .collect();
+            combinations.push(cards);
+            if !next_combination(&mut board_combo, board.len()) {
+                break;
+            }
+        }
+        if !next_combination(&mut hole_combo, hole_cards.len()) {
+            break;
+        }
+    }
+    combinations
+}
+
+/// Sort the cards by their groups of kinds, larger groups and then
+/// higher ranks first.  `rank` gives the rank of a card.  Return the
+/// category of the groups, along with the sorted cards.
+fn group_by_ranks<'a>(cards: &[&'a str], rank: impl Fn(&str) -> u8) -> (Category, Vec<&'a str>) {
+    let count = |c: &str| cards.iter().filter(|c2| rank(c2) == rank(c)).count();
+    let mut sorted = cards.to_vec();
+    sorted.sort_by(|c1, c2| count(c2).cmp(&count(c1)).then(rank(c2).cmp(&rank(c1))));
+    let mut counts: Vec<usize> = sorted.iter().map(|c| count(c)).collect();
+    counts.dedup();
+    let pairs = sorted.iter().filter(|c| count(c) == 2).count() / 2;
+    let category = match (counts.first(), pairs) {
+        (Some(4), _) => Category::FourOfAKind,
+        (Some(3), 1) => Category::FullHouse,
+        (Some(3), _) => Category::ThreeOfAKind,
+        (Some(2), 2) => Category::TwoPairs,
+        (Some(2), _) => Category::Pair,
+        _ => Category::HighCard,
+    };
+    (category, sorted)
+}
+
+/// The order of a category value in the high hand rankings
+fn category_order(category: Category) -> u8 {
+    match category {
+        Category::RoyalFlush => 9,
+        Category::StraightFlush => 8,
+        Category::FourOfAKind => 7,
+        Category::FullHouse => 6,
+        Category::Flush => 5,
+        Category::Straight => 4,
+        Category::ThreeOfAKind => 3,
+        Category::TwoPairs => 2,
+        Category::Pair => 1,
+        Category::HighCard => 0,
+    }
+}
+
+/// Make the value of a low hand from its category and ranks.  Each
+/// byte is inverted, so that the lower hand compares greater with
+/// `compare_hands`.
+fn low_value(category: Category, ranks: impl Iterator<Item = u8>) -> Vec<u8> {
+    std::iter::once(category_order(category))
+        .chain(ranks)
+        .map(|v| u8::MAX - v)
+        .collect()
+}
+
+/// Evaluate a 2-7 lowball hand, where A is always high, straights and
+/// flushes count against the hand and 7-5-4-3-2 unsuited is the best.
+/// The value is ordered by `compare_hands` like the high hands: the
+/// better (lower) hand is greater.
+pub fn evaluate_deuce_to_seven<'a>(cards: [&'a str; 5]) -> PlayerHand<'a> {
+    let (category, picks) = group_by_ranks(&cards, kind_to_order);
+    let ranks: Vec<u8> = picks.iter().map(|c| kind_to_order(c)).collect();
+    let is_flush = cards
+        .iter()
+        .all(|c| c.split_at(1).0 == cards[0].split_at(1).0);
+    let is_straight = category == Category::HighCard && ranks[0] - ranks[4] == 4;
+    let category = match (is_straight, is_flush) {
+        (true, true) => Category::StraightFlush,
+        (false, true) => Category::Flush,
+        (true, false) => Category::Straight,
+        _ => category,
+    };
+    let value = low_value(category, ranks.iter().copied());
+    PlayerHand {
+        category,
+        picks,
+        kickers: ranks,
+        value,
+    }
+}
+
+/// Evaluate a Razz (A-5 lowball) hand, picking the best five low cards
+/// out of the seven.  A counts as the lowest card, straights and
+/// flushes don't count, thus 5-4-3-2-A is the best.  The value is
+/// ordered by `compare_hands` like the high hands: the better (lower)
+/// hand is greater.
+pub fn evaluate_razz<'a>(cards: &[&'a str]) -> PlayerHand<'a> {
+    let low_order = |c: &str| match kind_to_order(c) {
+        14 => 1,
+        o => o,
+    };
+    let mut best: Option<PlayerHand> = None;
+    let mut combo: Vec<usize> = (0..usize::min(5, cards.len())).collect();
+    loop {
+        let picked: Vec<&str> = combo.iter().map(|i| cards[*i]).collect();
+        let (category, picks) = group_by_ranks(&picked, low_order);
+        let kickers: Vec<u8> = picks.iter().map(|c| low_order(c)).collect();
+        let value = low_value(category, kickers.iter().copied());
+        if best.as_ref().map_or(true, |b| {
+            compare_hands(&value, &b.value) == Ordering::Greater
+        }) {
+            best = Some(PlayerHand {
+                category,
+                picks,
+                kickers,
+                value,
+            });
+        }
+        if !next_combination(&mut combo, cards.len()) {
+            break;
+        }
+    }
+    best.unwrap()
+}
+
+/// The number of run-outs simulated for the equities shown in game.
+pub const EQUITY_ITERATIONS: usize = 2000;
+
+fn n_choose_k(n: usize, k: usize) -> usize {
+    (1..=k).fold(1, |acc, i| acc * (n - k + i) / i)
+}
+
+/// Advance `combo` to the next combination of indexes out of `n`, in
+/// lexicographic order.  Return false if it's the last one.
+fn next_combination(combo: &mut [usize], n: usize) -> bool {
+    let k = combo.len();
+    for i in (0..k).rev() {
+        if combo[i] < n - k + i {
+            combo[i] += 1;
+            let start = combo[i];
+            for (offset, c) in combo[i + 1..].iter_mut().enumerate() {
+                *c = start + offset + 1;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// The cards of a full deck not in the hole cards or the board
+fn remaining_deck(hole_cards: &[[&str; 2]], board: &[&str]) -> Vec<String> {
+    let used: HashSet<&str> = hole_cards
+        .iter()
+        .flatten()
+        .chain(board.iter())
+        .copied()
+        .collect();
+    SUITS
+        .iter()
+        .flat_map(|s| KINDS.iter().map(move |k| format!("{}{}", s, k)))
+        .filter(|c| !used.contains(c.as_str()))
+        .collect()
+}
+
+/// Add the win + tie shares of a complete board to `equities`
+fn award_board(hole_cards: &[[&str; 2]], full_board: &[&str], equities: &mut [f64]) {
+    let values: Vec<Vec<u8>> = hole_cards
+        .iter()
+        .map(|hole| evaluate_cards(create_cards(full_board, hole)).value)
+        .collect();
+    let mut best = &values[0];
+    for value in values.iter() {
+        if compare_hands(value, best) == Ordering::Greater {
+            best = value;
+        }
+    }
+    let winners: Vec<usize> = (0..values.len())
+        .filter(|i| compare_hands(&values[*i], best) == Ordering::Equal)
+        .collect();
+    let share = 1.0 / winners.len() as f64;
+    for w in winners {
+        equities[w] += share;
+    }
+}
+
+/// Calculate the equity (win + tie share) of each hand, given the
+/// known community cards, by simulating `iterations` random run-outs.
+/// The result is in the same order as `hole_cards` and sums to 1.0.
+/// The run-outs are drawn by a splitmix64 generator with `seed`, so
+/// the same seed gives the same result.  When no more than
+/// `iterations` run-outs are possible, all of them are enumerated
+/// instead.
+pub fn equity(hole_cards: &[[&str; 2]], board: &[&str], iterations: usize, seed: u64) -> Vec<f64> {
+    let mut equities = vec![0.0; hole_cards.len()];
+    if hole_cards.is_empty() || board.len() > 5 || iterations == 0 {
+        return equities;
+    }
+
+    let mut deck = remaining_deck(hole_cards, board);
+    let missing = 5 - board.len();
+    if n_choose_k(deck.len(), missing) <= iterations {
+        let mut combo: Vec<usize> = (0..missing).collect();
+        let mut count = 0;
+        loop {
+            let mut full_board: Vec<&str> = board.to_vec();
+            full_board.extend(combo.iter().map(|i| deck[*i].as_str()));
+            award_board(hole_cards, &full_board, &mut equities);
+            count += 1;
+            if !next_combination(&mut combo, deck.len()) {
+                break;
+            }
+        }
+        equities.iter_mut().for_each(|e| *e /= count as f64);
+        return equities;
+    }
+
+    let mut state = seed;
+    let mut next_random = || {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+    for _ in 0..iterations {
+        // Partially shuffle the deck, the first `missing` cards are drawn
+        for i in 0..missing {
+            let j = i + (next_random() % (deck.len() - i) as u64) as usize;
+            deck.swap(i, j);
+        }
+        let mut full_board: Vec<&str> = board.to_vec();
+        full_board.extend(deck[..missing].iter().map(|c| c.as_str()));
+        award_board(hole_cards, &full_board, &mut equities);
+    }
+    equities.iter_mut().for_each(|e| *e /= iterations as f64);
+    equities
+}
+
+fn hand_values(hole_cards: &[[&str; 2]], board: &[&str]) -> Vec<Vec<u8>> {
+    hole_cards
+        .iter()
+        .map(|hole| evaluate_cards(create_cards(board, hole)).value)
+        .collect()
+}
+
+/// Find the sole leader of an all-in confrontation on a board of 3 or
+/// 4 cards, and list the outs against it: the undealt cards that put
+/// another hand ahead on the next card.  A card making a tie is not an
+/// out.  Return None when nobody is ahead alone.
+pub fn count_outs(hole_cards: &[[&str; 2]], board: &[&str]) -> Option<(usize, Vec<String>)> {
+    if hole_cards.len() < 2 || board.len() < 3 || board.len() > 4 {
+        return None;
+    }
+    let values = hand_values(hole_cards, board);
+    let leader = (0..values.len()).max_by(|a, b| compare_hands(&values[*a], &values[*b]))?;
+    let is_behind = |values: &[Vec<u8>], ordering: Ordering| {
+        values
+            .iter()
+            .enumerate()
+            .any(|(i, v)| i != leader && compare_hands(v, &values[leader]) == ordering)
+    };
+    if is_behind(&values, Ordering::Equal) {
+        return None;
+    }
+
+    let outs = remaining_deck(hole_cards, board)
+        .into_iter()
+        .filter(|card| {
+            let mut next_board: Vec<&str> = board.to_vec();
+            next_board.push(card.as_str());
+            is_behind(&hand_values(hole_cards, &next_board), Ordering::Greater)
+        })
+        .collect();
+    Some((leader, outs))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::cmp::Ordering;
 
+    #[test]
+    fn test_count_outs_of_underpair_on_flop() {
+        let hole_cards = [["sa", "ha"], ["sk", "hk"]];
+        let (leader, mut outs) = count_outs(&hole_cards, &["s2", "d7", "c9"]).unwrap();
+        outs.sort();
+        assert_eq!(leader, 0);
+        assert_eq!(outs, vec!["ck", "dk"]);
+    }
+
+    #[test]
+    fn test_count_outs_of_flush_draw_on_flop() {
+        let hole_cards = [["hk", "hq"], ["sa", "da"]];
+        let (leader, outs) = count_outs(&hole_cards, &["h2", "h7", "c9"]).unwrap();
+        assert_eq!(leader, 1);
+        assert_eq!(outs.len(), 9);
+        assert!(outs.iter().all(|c| c.starts_with('h')));
+
+        // Nobody is ahead alone
+        assert_eq!(
+            count_outs(&[["sa", "ha"], ["da", "ca"]], &["s2", "d7", "c9"]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_equity_aa_vs_kk_preflop_in_budget() {
+        let hole_cards = [["sa", "ha"], ["sk", "hk"]];
+        let equities = equity(&hole_cards, &[], EQUITY_ITERATIONS, 0);
+        assert_eq!(equities.len(), 2);
+        assert!((equities[0] + equities[1] - 1.0).abs() < 1e-9);
+        assert!((equities[0] - 0.82).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_equity_aa_vs_kk_preflop() {
+        let hole_cards = [["sa", "ha"], ["sk", "hk"]];
+        let equities = equity(&hole_cards, &[], 5000, 42);
+        assert!((equities[0] + equities[1] - 1.0).abs() < 1e-9);
+        assert!((equities[0] - 0.82).abs() < 0.03);
+        // Same seed, same result
+        assert_eq!(equity(&hole_cards, &[], 5000, 42), equities);
+    }
+
+    #[test]
+    fn test_equity_enumerates_few_run_outs() {
+        let hole_cards = [["sa", "ha"], ["sk", "hk"]];
+        // 44 rivers, each is evaluated once regardless of the seed
+        let board = ["dk", "c7", "h9", "s3"];
+        let equities = equity(&hole_cards, &board, 1000, 1);
+        assert_eq!(equities, equity(&hole_cards, &board, 1000, 2));
+        assert!((equities[0] - 2.0 / 44.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_equity_on_river_is_exact() {
+        let hole_cards = [["sa", "ha"], ["sk", "hk"], ["d2", "c2"]];
+        let board = ["dk", "c7", "h9", "s3", "st"];
+        assert_eq!(
+            equity(&hole_cards, &board, EQUITY_ITERATIONS, 0),
+            vec![0.0, 1.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_category_round_trips_text() {
+        let categories = [
+            Category::RoyalFlush,
+            Category::StraightFlush,
+            Category::FourOfAKind,
+            Category::FullHouse,
+            Category::Flush,
+            Category::Straight,
+            Category::ThreeOfAKind,
+            Category::TwoPairs,
+            Category::Pair,
+            Category::HighCard,
+        ];
+        for category in categories {
+            let text = category.to_string();
+            assert_eq!(text.parse::<Category>(), Ok(category));
+        }
+        assert!("Trips".parse::<Category>().is_err());
+    }
+
+    fn assert_same_as_evaluate_cards(cards: Vec<&str>) {
+        let fast = evaluate_seven(cards.clone());
+        let hand = evaluate_cards(cards.clone());
+        assert_eq!(fast.category, hand.category, "{:?}", cards);
+        assert_eq!(fast.picks, hand.picks, "{:?}", cards);
+        assert_eq!(fast.kickers, hand.kickers, "{:?}", cards);
+        assert_eq!(fast.value, hand.value, "{:?}", cards);
+    }
+
+    #[test]
+    fn test_evaluate_seven_same_as_evaluate_cards() {
+        let deck: Vec<String> = SUITS
+            .iter()
+            .flat_map(|s| KINDS.iter().map(move |k| format!("{}{}", s, k)))
+            .collect();
+        let mut state = 7u64;
+        let mut next_random = || {
+            state = state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        for _ in 0..5000 {
+            let mut idxs: Vec<usize> = (0..deck.len()).collect();
+            for i in 0..7 {
+                let j = i + (next_random() % (deck.len() - i) as u64) as usize;
+                idxs.swap(i, j);
+            }
+            assert_same_as_evaluate_cards(idxs[..7].iter().map(|i| deck[*i].as_str()).collect());
+        }
+
+        // The rare ones
+        for cards in [
+            vec!["st", "sk", "h2", "sq", "sa", "d3", "sj"],
+            vec!["s9", "s8", "s7", "s6", "s5", "s4", "h3"],
+            vec!["s2", "sa", "s3", "d4", "s4", "s5", "h5"],
+            vec!["s9", "h9", "d9", "c9", "sa", "ha", "dk"],
+            vec!["s9", "h9", "d9", "c2", "s2", "h2", "dk"],
+            vec!["s9", "h9", "dk", "c2", "s2", "hk", "da"],
+            vec!["sa", "h2", "d3", "c4", "s5", "h5", "d5"],
+        ] {
+            assert_same_as_evaluate_cards(cards);
+        }
+    }
+
+    #[test]
+    fn test_kickers_break_two_pairs_tie() {
+        let board = ["sk", "hk", "d9", "c9", "s2"];
+        let hand1 = evaluate_cards(create_cards(&board, &["ha", "c3"]));
+        let hand2 = evaluate_cards(create_cards(&board, &["hq", "c3"]));
+        assert_eq!(hand1.category, Category::TwoPairs);
+        assert_eq!(hand2.category, Category::TwoPairs);
+        assert_eq!(hand1.kickers, vec![13, 13, 9, 9, 14]);
+        assert_eq!(hand2.kickers, vec![13, 13, 9, 9, 12]);
+        // The kickers are ordered as the values
+        assert_eq!(hand1.kickers, hand1.value[1..]);
+        assert_eq!(
+            hand1.kickers.cmp(&hand2.kickers),
+            compare_hands(&hand1.value, &hand2.value)
+        );
+    }
+
+    #[test]
+    fn test_deuce_to_seven_best_hands() {
+        let nuts = evaluate_deuce_to_seven(["s7", "h5", "d4", "c3", "s2"]);
+        let second = evaluate_deuce_to_seven(["s7", "h6", "d4", "c3", "s2"]);
+        assert_eq!(nuts.category, Category::HighCard);
+        assert_eq!(compare_hands(&nuts.value, &second.value), Ordering::Greater);
+
+        // A is high, the wheel is no straight but an ace high
+        let wheel = evaluate_deuce_to_seven(["sa", "h5", "d4", "c3", "s2"]);
+        assert_eq!(wheel.category, Category::HighCard);
+        assert_eq!(
+            compare_hands(&second.value, &wheel.value),
+            Ordering::Greater
+        );
+
+        // A straight loses to a pair
+        let straight = evaluate_deuce_to_seven(["s6", "h5", "d4", "c3", "s2"]);
+        let pair = evaluate_deuce_to_seven(["s7", "h7", "d4", "c3", "s2"]);
+        assert_eq!(straight.category, Category::Straight);
+        assert_eq!(
+            compare_hands(&pair.value, &straight.value),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_deuce_to_seven_flush_loses_to_non_flush() {
+        let flush = evaluate_deuce_to_seven(["s7", "s5", "s4", "s3", "s2"]);
+        assert_eq!(flush.category, Category::Flush);
+        for cards in [
+            ["sk", "hq", "dj", "c9", "s8"],
+            ["sk", "hk", "dq", "cq", "sj"],
+            ["sa", "ha", "da", "cq", "sj"],
+        ] {
+            let hand = evaluate_deuce_to_seven(cards);
+            assert_eq!(compare_hands(&hand.value, &flush.value), Ordering::Greater);
+        }
+    }
+
+    #[test]
+    fn test_razz_wheel_is_the_nuts() {
+        let wheel = evaluate_razz(&["sa", "h2", "d3", "c4", "s5", "hk", "dk"]);
+        assert_eq!(wheel.category, Category::HighCard);
+        assert_eq!(wheel.picks, vec!["s5", "c4", "d3", "h2", "sa"]);
+        // Straights and flushes don't count
+        let suited = evaluate_razz(&["sa", "s2", "s3", "s4", "s5", "s6", "s7"]);
+        assert_eq!(compare_hands(&wheel.value, &suited.value), Ordering::Equal);
+        let six = evaluate_razz(&["sa", "h2", "d3", "c4", "s6", "hk", "dk"]);
+        assert_eq!(compare_hands(&wheel.value, &six.value), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_razz_pairs_are_penalized() {
+        // The pair is avoided when five different ranks are available
+        let hand = evaluate_razz(&["sa", "ha", "d3", "c4", "s5", "hk", "dq"]);
+        assert_eq!(hand.category, Category::HighCard);
+        assert_eq!(kind_to_order(hand.picks[0]), 12);
+
+        // Any no pair beats a pair, a lower pair beats a higher one
+        let king_high = evaluate_razz(&["sk", "hq", "dj", "c9", "s8"]);
+        let pair_of_twos = evaluate_razz(&["s2", "h2", "d3", "c3", "s4", "h4", "d5"]);
+        assert_eq!(pair_of_twos.category, Category::Pair);
+        let pair_of_aces = evaluate_razz(&["sa", "ha", "d3", "c4", "s5", "h3", "d5"]);
+        assert_eq!(pair_of_aces.category, Category::Pair);
+        assert_eq!(
+            compare_hands(&king_high.value, &pair_of_aces.value),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_hands(&pair_of_aces.value, &pair_of_twos.value),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_evaluate_low_uses_two_hole_cards() {
+        let board = ["s2", "d3", "h8", "ck", "sk"];
+        // Only two of A-4-5 can be used: 8-4-3-2-A
+        let low = evaluate_low(&["ha", "c5", "d4", "sq"], &board);
+        assert_eq!(low, Some(vec![8, 4, 3, 2, 1]));
+        // A-4 beats 4-5
+        let worse = evaluate_low(&["c4", "c5", "hq", "sq"], &board);
+        assert_eq!(worse, Some(vec![8, 5, 4, 3, 2]));
+        assert!(low < worse);
+    }
+
+    #[test]
+    fn test_evaluate_low_not_qualified() {
+        // Only two low cards on board
+        let board = ["s2", "d3", "h9", "ck", "sk"];
+        assert_eq!(evaluate_low(&["ha", "c5", "d4", "s6"], &board), None);
+        // Paired hole cards can't make five different ranks
+        let board = ["s2", "d3", "h8", "ck", "sk"];
+        assert_eq!(evaluate_low(&["ha", "da", "dq", "sq"], &board), None);
+    }
+
+    #[test]
+    fn test_evaluate_omaha_high_uses_two_hole_cards() {
+        // Four hearts on board, but a single heart in hand makes no flush
+        let board = ["h2", "h7", "h9", "hk", "s3"];
+        let hand = evaluate_omaha_high(&["ha", "sa", "d4", "c5"], &board);
+        assert_eq!(hand.category, Category::Pair);
+        assert_eq!(hand.picks.len(), 5);
+        assert_eq!(hand.picks.iter().filter(|c| c.ends_with('a')).count(), 2);
+        // Two hearts in hand make the flush
+        let flush = evaluate_omaha_high(&["ha", "hq", "d4", "c5"], &board);
+        assert_eq!(flush.category, Category::Flush);
+        assert_eq!(compare_hands(&flush.value, &hand.value), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_short_deck_has_36_cards() {
+        let deck = short_deck_cards();
+        assert_eq!(deck.len(), 36);
+        assert!(deck.iter().all(|c| kind_to_order(c) >= 6));
+    }
+
+    #[test]
+    fn test_joker_makes_the_best_hand() {
+        let board = ["sa", "ha", "da", "c7", "d9"];
+        let hand = evaluate_with_jokers(create_cards(&board, &["jr", "s3"]));
+        assert_eq!(hand.category, Category::FourOfAKind);
+        assert!(hand.picks.contains(&"ca"));
+
+        // Two jokers complete a royal flush
+        let board = ["sa", "sk", "sq", "c7", "d2"];
+        let hand = evaluate_with_jokers(create_cards(&board, &["jr", "jb"]));
+        assert_eq!(hand.category, Category::RoyalFlush);
+        assert_eq!(joker_deck_cards().len(), 54);
+    }
+
+    #[test]
+    fn test_short_deck_a6789_straight() {
+        let board = ["sa", "h6", "c7", "d8", "s9"];
+        let hand = evaluate_short_deck_cards(create_cards(&board, &["hk", "dk"]));
+        assert_eq!(hand.category, Category::Straight);
+        assert_eq!(hand.picks, vec!["s9", "d8", "c7", "h6", "sa"]);
+
+        // The lowest straight loses to 6-T
+        let higher = evaluate_short_deck_cards(create_cards(&board, &["ht", "dk"]));
+        assert_eq!(higher.category, Category::Straight);
+        assert_eq!(compare_hands(&higher.value, &hand.value), Ordering::Greater);
+
+        // Not a straight in a full deck
+        let hand = evaluate_cards(create_cards(&board, &["hk", "dk"]));
+        assert_eq!(hand.category, Category::Pair);
+    }
+
+    #[test]
+    fn test_short_deck_flush_beats_full_house() {
+        let board = ["ha", "h9", "h7", "s7", "d9"];
+        let flush_hole = ["hk", "h6"];
+        let full_house_hole = ["c9", "sq"];
+
+        let flush = evaluate_short_deck_cards(create_cards(&board, &flush_hole));
+        let full_house = evaluate_short_deck_cards(create_cards(&board, &full_house_hole));
+        assert_eq!(flush.category, Category::Flush);
+        assert_eq!(full_house.category, Category::FullHouse);
+        assert_eq!(
+            compare_hands(&flush.value, &full_house.value),
+            Ordering::Greater
+        );
+
+        let flush = evaluate_cards(create_cards(&board, &flush_hole));
+        let full_house = evaluate_cards(create_cards(&board, &full_house_hole));
+        assert_eq!(
+            compare_hands(&flush.value, &full_house.value),
+            Ordering::Less
+        );
+    }
+
     #[test]
     fn sorting_cards() {
         // A single card is a 2-char string literal: Suit-Kind
@@ -1,17 +1,38 @@
 //! Game state machine (or handler) of Holdem: the core of this lib.
 use race_api::prelude::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::mem::take;
 
 use crate::errors;
 use crate::essential::{
-    ActingPlayer, AwardPot, Display, GameEvent, GameMode, HoldemAccount, HoldemStage,
-    InternalPlayerJoin, Player, PlayerResult, PlayerStatus, Pot, Street, ACTION_TIMEOUT_POSTFLOP,
-    ACTION_TIMEOUT_PREFLOP, ACTION_TIMEOUT_RIVER, ACTION_TIMEOUT_TURN, MAX_ACTION_TIMEOUT_COUNT,
-    WAIT_TIMEOUT_DEFAULT, WAIT_TIMEOUT_LAST_PLAYER, WAIT_TIMEOUT_RUNNER, WAIT_TIMEOUT_SHOWDOWN,
+    ActingContext, ActingPlayer, ActionTimeouts, AnteType, AwardPot, BettingMode, DeckSpec,
+    Display, GameEvent, GameMode, GameVariant, HoldemAccount, HoldemStage, InsuranceOffer,
+    InternalPlayerJoin, Player, PlayerResult, PlayerStatus, Pot, PotShare, PotView, Street,
+    FIXED_LIMIT_MAX_RAISES, INTEGRITY_WINDOW, JACKPOT_SLOT_ID, MAX_ACTION_TIMEOUT_COUNT,
+    WAIT_TIMEOUT_DEFAULT, WAIT_TIMEOUT_INSURANCE, WAIT_TIMEOUT_LAST_PLAYER, WAIT_TIMEOUT_RUNNER,
+    WAIT_TIMEOUT_RUN_IT_TWICE, WAIT_TIMEOUT_SHOWDOWN,
 };
-use crate::evaluator::{compare_hands, create_cards, evaluate_cards, PlayerHand};
-use crate::hand_history::{BlindBet, BlindType, HandHistory, PlayerAction, Showdown};
+use crate::evaluator::{
+    compare_hands, count_outs, create_cards, equity, evaluate_low, evaluate_omaha_high,
+    evaluate_seven, evaluate_short_deck_cards, evaluate_with_jokers, Category, PlayerHand,
+    EQUITY_ITERATIONS,
+};
+use crate::hand_history::{BlindBet, BlindType, HandHistory, PlayerAction, Showdown, MASKED_CARD};
+
+/// Read the revealed hole cards at `idxs`
+fn revealed_hole_cards<'a>(
+    idxs: &[usize],
+    decryption: &'a HashMap<usize, String>,
+) -> Result<Vec<&'a str>, HandleError> {
+    idxs.iter()
+        .enumerate()
+        .map(|(i, idx)| match decryption.get(idx) {
+            Some(card) => Ok(card.as_str()),
+            None if i == 0 => Err(errors::first_hole_card_error()),
+            None => Err(errors::second_hole_card_error()),
+        })
+        .collect()
+}
 
 // Holdem: the game state
 #[derive(BorshSerialize, BorshDeserialize, Default, Debug, PartialEq, Clone)]
@@ -42,15 +63,97 @@ pub struct Holdem {
     pub table_size: u8, // The size of table
     pub hand_history: HandHistory,
     pub next_game_start: u64,
+    pub min_think_time: u64,
+    pub action_asked_at: u64,
+    // An early action waiting for the minimum think time to pass
+    pub pending_action: Option<PlayerAction>,
+    pub max_sitout_hands: u8,
+    pub deposit_increment: u64,
+    pub ante: u64,
+    pub max_ante_ratio: u16,
+    pub headsup_btn_posts_blinds: bool,
+    pub dealt_in_when_posted: bool,
+    pub run_it_twice: bool,
+    // The number of runouts to deal in Runner, 0 before it's decided
+    pub runouts: u8,
+    pub run_it_twice_agreed: Vec<u64>,
+    pub second_board: Vec<String>,
+    pub deck_spec: DeckSpec,
+    pub action_timeouts: Option<ActionTimeouts>,
+    pub betting_mode: BettingMode,
+    // The number of raises in current street
+    pub street_raises: u8,
+    // Waiting for the undealt board cards to be revealed
+    pub rabbit_hunt: bool,
+    pub ante_type: AnteType,
+    pub min_deposit: u64,
+    pub kill_game: bool,
+    pub kill_threshold: u64,
+    // The sole winners of the last two hands, cleared by a split pot
+    pub last_winners: Vec<u64>,
+    // The pot scooped in the last hand
+    pub last_pot: u64,
+    // The player to post the kill blind in current hand
+    pub killer: Option<u64>,
+    pub max_raises_per_street: Option<u8>,
+    // The uncontested winner waiting for the hole cards to show
+    pub show_cards: Option<u64>,
+    // The only hole card to show, None to show both
+    pub show_card_index: Option<u8>,
+    pub allin_protection: bool,
+    pub dead_button: bool,
+    // The positions of SB and BB in the last hand, for the dead button rule
+    pub blind_positions: Option<(usize, usize)>,
+    pub chop_blinds_agreed: Vec<u64>,
+    pub no_flop_no_drop: bool,
+    pub no_showdown_no_rake: bool,
+    // Players declined to show their hole cards at showdown
+    pub mucked: Vec<u64>,
+    pub insurance: bool,
+    // The insurance offered in current hand and the premium paid
+    pub insurance_offer: Option<InsuranceOffer>,
+    pub insurance_premium: u64,
+    // The chips held to pay the insurance: the house fund and the
+    // premiums taken
+    pub insurance_pool: u64,
+    pub bad_beat_drop: u64,
+    // The total chips dropped into the bad beat jackpot
+    pub bad_beat_collected: u64,
+    pub bomb_pot_interval: u16,
+    pub bomb_pot_ante: u64,
+    // The number of hands dealt, for the bomb pot interval
+    pub hand_count: u64,
+    // Current hand is a bomb pot, dealt straight to the flop
+    pub bomb_pot: bool,
+    // Players who can only call or fold, as a short all-in raised
+    // the bet after they acted
+    pub raise_closed: Vec<u64>,
+    pub action_warning: u64,
+    pub min_players_to_start: u8,
+    // No blinds are posted, the antes are the only forced bets
+    pub ante_only: bool,
+    // Players returning from sitting out by posting a dead blind
+    pub dead_blind_posters: Vec<u64>,
+    pub integrity_threshold: u8,
+    // The recent heads-up confrontations lost, as (loser, winner)
+    pub integrity_pairs: Vec<(u64, u64)>,
+    // The maximum rake in chips, replacing the cap in BB
+    pub rake_cap_chips: Option<u64>,
+    pub variant: GameVariant,
+    // The low winners of each pot in hi-lo games, empty for a pot
+    // without a qualifying low
+    pub low_winners: Vec<Vec<u64>>,
 }
 
 // Methods that mutate or query the game state
 impl Holdem {
     // Mark out players.
-    // An out player is one with zero chips.
+    // An out player is one with zero chips, observers don't need chips.
     fn mark_out_players(&mut self) {
         for (_, v) in self.player_map.iter_mut() {
-            if v.status != PlayerStatus::Leave && v.chips + v.deposit == 0 {
+            if !matches!(v.status, PlayerStatus::Leave | PlayerStatus::Observer)
+                && v.chips + v.deposit == 0
+            {
                 // Set player status to Out to indicates this player has no chips
                 v.status = PlayerStatus::Out;
                 // Here we use timeout for rebuy timeout.
@@ -59,6 +162,36 @@ impl Holdem {
         }
     }
 
+    // Mark players sitting out beyond the limit with `Leave` status.
+    // A player is sitting out when the latest action is a timeout.
+    // Those sitting out voluntarily are not counted.
+    fn mark_sitout_players(&mut self) {
+        if self.mode != GameMode::Cash || self.max_sitout_hands == 0 {
+            return;
+        }
+        for player in self.player_map.values_mut() {
+            if matches!(
+                player.status,
+                PlayerStatus::Init
+                    | PlayerStatus::Leave
+                    | PlayerStatus::Out
+                    | PlayerStatus::SitOut
+                    | PlayerStatus::Observer
+            ) {
+                continue;
+            }
+            if player.timeout > 0 {
+                player.sitout_hands += 1;
+            } else {
+                player.sitout_hands = 0;
+            }
+            if player.sitout_hands > self.max_sitout_hands {
+                println!("Player {} sits out for too long", player.id);
+                player.status = PlayerStatus::Leave;
+            }
+        }
+    }
+
     // Remove players with `Leave` or `Out` status.
     fn remove_leave_and_out_players(&mut self) -> Vec<Player> {
         let player_map = take(&mut self.player_map);
@@ -93,7 +226,7 @@ impl Holdem {
         for player in self.player_map.values_mut() {
             if player.status == PlayerStatus::Out {
                 player.timeout += 1;
-            } else {
+            } else if player.status != PlayerStatus::Observer {
                 player.status = PlayerStatus::Wait;
             }
         }
@@ -106,6 +239,7 @@ impl Holdem {
         self.street_bet = 0;
         self.min_raise = 0;
         self.acting_player = None;
+        self.pending_action = None;
         effect.cancel_dispatch();
         Ok(())
     }
@@ -123,6 +257,44 @@ impl Holdem {
         None
     }
 
+    /// Whether nobody can act any more in current hand: all players
+    /// are all-in except at most one, and that one has already
+    /// matched the bets of all others.
+    pub fn is_betting_closed(&self) -> bool {
+        let max_bet = self.bet_map.values().copied().max().unwrap_or(0);
+        let mut allin_cnt = 0;
+        let mut active_players = Vec::<u64>::new();
+        for id in self.player_order.iter() {
+            if let Some(player) = self.player_map.get(id) {
+                match player.status {
+                    PlayerStatus::Allin => allin_cnt += 1,
+                    PlayerStatus::Wait | PlayerStatus::Acted | PlayerStatus::Acting => {
+                        active_players.push(*id)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        allin_cnt + active_players.len() > 1
+            && active_players.len() <= 1
+            && active_players
+                .iter()
+                .all(|id| self.get_player_bet(*id) >= max_bet)
+    }
+
+    /// Whether a timed out player can be protected as all-in instead
+    /// of folding.  It's available once per session in cash games, to
+    /// a player who has committed chips in current hand.
+    pub fn can_protect_allin(&self, player_id: u64) -> bool {
+        self.allin_protection
+            && self.mode == GameMode::Cash
+            && self
+                .player_map
+                .get(&player_id)
+                .is_some_and(|p| !p.protection_used)
+            && self.total_bet_map.get(&player_id).is_some_and(|b| *b > 0)
+    }
+
     pub fn is_acting_player(&self, player_id: u64) -> bool {
         match &self.acting_player {
             Some(ActingPlayer { id, .. }) => *id == player_id,
@@ -130,34 +302,16 @@ impl Holdem {
         }
     }
 
-    fn get_remainder_player(&mut self) -> Option<u64> {
-        let eligible_candidates = {
-            let mut players = self
-                .player_map
-                .values()
-                .filter(|p| {
-                    self.prize_map.contains_key(&p.id())
-                        && matches!(
-                            p.status,
-                            PlayerStatus::Acted | PlayerStatus::Allin | PlayerStatus::Wait
-                        )
-                })
-                .map(|p| (p.id(), p.position))
-                .collect::<Vec<(u64, usize)>>();
-            players.sort_by(|(_, pos1), (_, pos2)| pos1.cmp(pos2));
-            players.into_iter().map(|(id, _)| id).collect::<Vec<u64>>()
-        };
-
-        let remainder_player = if eligible_candidates.is_empty() {
-            // When no remainder player, use the the first in player map
-            self.player_map
-                .first_key_value()
-                .and_then(|(id, _)| Some(*id))
-        } else {
-            eligible_candidates.first().and_then(|id| Some(*id))
-        };
-
-        remainder_player
+    /// Return the winner in the worst position to get the odd chips,
+    /// that is the first one to the left of BTN.
+    fn get_remainder_player(&self, winners: &[u64]) -> Option<u64> {
+        let btn = self.btn;
+        self.player_map
+            .values()
+            .filter(|p| winners.contains(&p.id()))
+            .min_by_key(|p| (p.position <= btn, p.position))
+            .map(|p| p.id())
+            .or(winners.first().copied())
     }
 
     /// Return either acting player position or btn for reference
@@ -169,12 +323,28 @@ impl Holdem {
         }
     }
 
-    // BTN moves clockwise.  The next BTN is calculated base on the current one
+    // BTN moves clockwise.  The next BTN is calculated base on the current one.
+    // With the dead button rule, BTN moves to the seat of last SB, which can
+    // be empty, thus the BB always moves to the next player.  Players sitting
+    // out are skipped, as the BTN must be the SB in heads-up.
     pub fn get_next_btn(&mut self) -> Result<usize, HandleError> {
-        let mut player_positions: Vec<usize> =
-            self.player_map.values().map(|p| p.position).collect();
+        let mut player_positions: Vec<usize> = self
+            .player_map
+            .values()
+            .filter(|p| !matches!(p.status, PlayerStatus::SitOut | PlayerStatus::Observer))
+            .map(|p| p.position)
+            .collect();
+        if player_positions.is_empty() {
+            player_positions = self.player_map.values().map(|p| p.position).collect();
+        }
         player_positions.sort();
 
+        if self.dead_button && player_positions.len() > 2 {
+            if let Some((sb_pos, _)) = self.blind_positions {
+                return Ok(sb_pos);
+            }
+        }
+
         let next_positions: Vec<usize> = player_positions
             .iter()
             .filter(|pos| **pos > self.btn)
@@ -195,16 +365,24 @@ impl Holdem {
         }
     }
 
-    fn get_action_time(&self) -> u64 {
+    fn get_action_time(&self, player_id: u64) -> u64 {
+        let timeouts = self.action_timeouts.clone().unwrap_or_default();
+        let is_afk = self
+            .player_map
+            .get(&player_id)
+            .map_or(false, |p| p.timeout > 0);
+        if is_afk && timeouts.afk > 0 {
+            return timeouts.afk;
+        }
         match self.street {
-            Street::Turn => ACTION_TIMEOUT_TURN,
-            Street::River => ACTION_TIMEOUT_RIVER,
-            Street::Flop => ACTION_TIMEOUT_POSTFLOP,
+            Street::Turn => timeouts.turn,
+            Street::River => timeouts.river,
+            Street::Flop => timeouts.postflop,
             Street::Preflop => {
                 if self.street_bet == self.bb {
-                    ACTION_TIMEOUT_PREFLOP
+                    timeouts.preflop
                 } else {
-                    ACTION_TIMEOUT_POSTFLOP
+                    timeouts.postflop
                 }
             }
             _ => 0,
@@ -216,7 +394,7 @@ impl Holdem {
         player_id: u64,
         effect: &mut Effect,
     ) -> Result<(), HandleError> {
-        let timeout = self.get_action_time();
+        let timeout = self.get_action_time(player_id);
         if let Some(player) = self.player_map.get_mut(&player_id) {
             println!("Asking {} to act", player.id);
             player.status = PlayerStatus::Acting;
@@ -225,7 +403,13 @@ impl Holdem {
                 position: player.position,
                 clock: effect.timestamp() + timeout,
             });
-            effect.action_timeout(player_id, timeout)?; // in msecs
+            self.action_asked_at = effect.timestamp();
+            // Dispatch the warning first, the action timeout follows it
+            if self.action_warning > 0 && timeout > self.action_warning {
+                effect.wait_timeout(timeout - self.action_warning);
+            } else {
+                effect.action_timeout(player_id, timeout)?; // in msecs
+            }
             Ok(())
         } else {
             return Err(errors::next_action_player_missing());
@@ -238,7 +422,7 @@ impl Holdem {
         let mut player_pos: Vec<(u64, usize)> = self
             .player_map
             .values()
-            .filter(|p| p.status != PlayerStatus::Init)
+            .filter(|p| !p.is_dealt_out())
             .map(|p| {
                 if p.position > last_pos {
                     (p.id, p.position - last_pos)
@@ -254,8 +438,33 @@ impl Holdem {
         Ok(())
     }
 
-    pub fn blind_bets(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
-        let (sb_id, bb_id) = if self.player_order.len() == 2 {
+    /// Whether the SB is dead by the dead button rule: the player who
+    /// posted the BB in last hand is gone, so the first player in
+    /// order is the next BB.
+    fn is_sb_dead(&self) -> bool {
+        let Some((_, last_bb_pos)) = self.blind_positions else {
+            return false;
+        };
+        self.dead_button
+            && self.player_order.len() > 2
+            && self
+                .player_order
+                .first()
+                .and_then(|id| self.player_map.get(id))
+                .is_some_and(|p| p.position != last_bb_pos)
+    }
+
+    /// Return the ids of SB and BB, according to the player order.
+    /// When the SB is dead, both are the BB.
+    fn get_blind_ids(&self) -> Result<(u64, u64), HandleError> {
+        if self.is_sb_dead() {
+            let bb_id = self
+                .player_order
+                .first()
+                .cloned()
+                .ok_or(errors::mplayers_missing_bb())?;
+            Ok((bb_id, bb_id))
+        } else if self.player_order.len() == 2 {
             let bb_id = self
                 .player_order
                 .first()
@@ -266,7 +475,7 @@ impl Holdem {
                 .last()
                 .cloned()
                 .ok_or(errors::heads_up_missing_bb())?;
-            (sb_id, bb_id)
+            Ok((sb_id, bb_id))
         } else {
             let sb_id = self
                 .player_order
@@ -278,32 +487,221 @@ impl Holdem {
                 .get(1)
                 .cloned()
                 .ok_or(errors::mplayers_missing_bb())?;
-            (sb_id, bb_id)
+            Ok((sb_id, bb_id))
+        }
+    }
+
+    /// Take antes from all players in the hand and collect them into
+    /// pots.  When `max_ante_ratio` is set, a player posts at most such
+    /// fraction of the stack, the rest is not owed.  With `ante_type`
+    /// of BB or button, only that player posts the ante.
+    pub fn ante_bets(&mut self) -> Result<Vec<BlindBet>, HandleError> {
+        if self.ante == 0 {
+            return Ok(vec![]);
+        }
+
+        let posters = match self.ante_type {
+            AnteType::EachPlayer => self.player_order.clone(),
+            AnteType::BigBlind => vec![self.get_blind_ids()?.1],
+            AnteType::Button => self.player_order.last().cloned().into_iter().collect(),
+        };
+        let mut ante_infos = Vec::new();
+        for id in posters {
+            let Some(player) = self.player_map.get(&id) else {
+                return Err(errors::internal_player_not_found());
+            };
+            let ante = if self.max_ante_ratio > 0 {
+                let ante_cap = player.chips * self.max_ante_ratio as u64 / 1000;
+                u64::min(self.ante, ante_cap)
+            } else {
+                self.ante
+            };
+            let (allin, real_ante) = self.take_bet(id, ante)?;
+            if allin {
+                self.set_player_status(id, PlayerStatus::Allin)?;
+            }
+            ante_infos.push(BlindBet::new(id, BlindType::Ante, real_ante));
+        }
+
+        if self.ante_type == AnteType::EachPlayer {
+            self.collect_bets()?;
+        } else {
+            // A single ante is dead money for all players in the hand
+            let amount = self.bet_map.values().sum();
+            self.bet_map.clear();
+            self.pots.push(Pot {
+                owners: self.player_order.clone(),
+                winners: vec![],
+                amount,
+            });
+        }
+        Ok(ante_infos)
+    }
+
+    /// Whether current hand is a bomb pot, i.e. every
+    /// `bomb_pot_interval`th hand in cash games.
+    pub fn is_bomb_pot_hand(&self) -> bool {
+        self.mode == GameMode::Cash
+            && self.bomb_pot_interval > 0
+            && self.bomb_pot_ante > 0
+            && self.hand_count % self.bomb_pot_interval as u64 == 0
+    }
+
+    /// Take the bomb pot ante from every player dealt in and collect
+    /// them into pots.  No blinds are posted in a bomb pot.
+    pub fn bomb_pot_bets(&mut self) -> Result<(), HandleError> {
+        // Everyone is in with the ante, nothing to post
+        self.dead_blind_posters.clear();
+        let posters: Vec<u64> = self
+            .player_map
+            .values()
+            .filter(|p| p.status == PlayerStatus::Wait)
+            .map(|p| p.id)
+            .collect();
+        let mut ante_infos = Vec::new();
+        for id in posters {
+            let (allin, real_ante) = self.take_bet(id, self.bomb_pot_ante)?;
+            if allin {
+                self.set_player_status(id, PlayerStatus::Allin)?;
+            }
+            ante_infos.push(BlindBet::new(id, BlindType::Ante, real_ante));
+        }
+        self.collect_bets()?;
+
+        let total_ante = self.pots.iter().map(|p| p.amount).sum();
+        let hh = &mut self.hand_history;
+        hh.set_blinds_infos(ante_infos);
+        hh.set_pot(Street::Preflop, total_ante);
+        Ok(())
+    }
+
+    pub fn blind_bets(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+        let (sb_id, bb_id) = self.get_blind_ids()?;
+        let dead_sb = self.is_sb_dead();
+
+        if self.ante_only {
+            return self.ante_only_bets(effect);
+        }
+
+        // In heads-up, the BTN posts the SB and acts first preflop
+        if self.player_order.len() == 2
+            && self.player_map.get(&sb_id).map(|p| p.position) != Some(self.btn)
+        {
+            return Err(errors::heads_up_btn_not_sb());
+        }
+
+        // In the heads-up variant, the button (SB) posts both blinds
+        let btn_posts_blinds = self.headsup_btn_posts_blinds && self.player_order.len() == 2;
+
+        let mut blinds_infos = self.ante_bets()?;
+        let total_ante: u64 = self.pots.iter().map(|p| p.amount).sum();
+
+        let (real_sb, real_bb) = if dead_sb {
+            let (allin, real_bb) = self.take_bet(bb_id, self.bb)?;
+            if allin {
+                self.set_player_status(bb_id, PlayerStatus::Allin)?;
+            }
+            blinds_infos.push(BlindBet::new(bb_id, BlindType::Bb, real_bb));
+            (0, real_bb)
+        } else if btn_posts_blinds {
+            let (allin, real_bet) = self.take_bet(sb_id, self.sb + self.bb)?;
+            if allin {
+                self.set_player_status(sb_id, PlayerStatus::Allin)?;
+            }
+            let real_sb = u64::min(real_bet, self.sb);
+            blinds_infos.push(BlindBet::new(sb_id, BlindType::Sb, real_sb));
+            blinds_infos.push(BlindBet::new(sb_id, BlindType::Bb, real_bet - real_sb));
+            (real_sb, real_bet - real_sb)
+        } else {
+            let (allin, real_sb) = self.take_bet(sb_id, self.sb)?;
+            if allin {
+                self.set_player_status(sb_id, PlayerStatus::Allin)?;
+            }
+            let (allin, real_bb) = self.take_bet(bb_id, self.bb)?;
+            if allin {
+                self.set_player_status(bb_id, PlayerStatus::Allin)?;
+            }
+            blinds_infos.push(BlindBet::new(sb_id, BlindType::Sb, real_sb));
+            blinds_infos.push(BlindBet::new(bb_id, BlindType::Bb, real_bb));
+            (real_sb, real_bb)
         };
 
-        let (allin, real_sb) = self.take_bet(sb_id, self.sb)?;
-        if allin {
-            self.set_player_status(sb_id, PlayerStatus::Allin)?;
+        // The killer tops up the bets to the kill blind, which is
+        // twice the BB.  A killer in the blinds has them count towards
+        // the kill.
+        let kill_blind = 2 * self.bb;
+        let mut real_kill = 0;
+        let mut killer_bet = 0;
+        if let Some(killer) = self.killer.filter(|id| self.player_order.contains(id)) {
+            let posted = self.bet_map.get(&killer).copied().unwrap_or(0);
+            let chips = self.player_map.get(&killer).map(|p| p.chips).unwrap_or(0);
+            if posted < kill_blind && chips > 0 {
+                let (allin, real_bet) = self.take_bet(killer, kill_blind - posted)?;
+                if allin {
+                    self.set_player_status(killer, PlayerStatus::Allin)?;
+                }
+                blinds_infos.push(BlindBet::new(killer, BlindType::Kill, real_bet));
+                real_kill = real_bet;
+            }
+            killer_bet = posted + real_kill;
         }
-        let (allin, real_bb) = self.take_bet(bb_id, self.bb)?;
-        if allin {
-            self.set_player_status(bb_id, PlayerStatus::Allin)?;
+
+        // The returning players post unless they are in the blinds
+        let mut real_dead = 0;
+        for id in std::mem::take(&mut self.dead_blind_posters) {
+            if id == sb_id || id == bb_id || !self.player_order.contains(&id) {
+                continue;
+            }
+            let (allin, real_bet) = self.take_bet(id, self.sb + self.bb)?;
+            if allin {
+                self.set_player_status(id, PlayerStatus::Allin)?;
+            }
+            let dead = u64::min(real_bet, self.sb);
+            self.bet_map.entry(id).and_modify(|b| *b -= dead);
+            self.bet_map.retain(|_, b| *b > 0);
+            match self.pots.last_mut() {
+                Some(pot) if pot.owners == self.player_order => pot.amount += dead,
+                _ => self.pots.push(Pot {
+                    owners: self.player_order.clone(),
+                    winners: vec![],
+                    amount: dead,
+                }),
+            }
+            blinds_infos.push(BlindBet::new(id, BlindType::DeadSb, dead));
+            blinds_infos.push(BlindBet::new(id, BlindType::Bb, real_bet - dead));
+            real_dead += real_bet;
         }
 
         let hh = &mut self.hand_history;
-        hh.set_blinds_infos(vec![
-            BlindBet::new(sb_id, BlindType::Sb, real_sb),
-            BlindBet::new(bb_id, BlindType::Bb, real_bb),
-        ]);
-        hh.set_pot(Street::Preflop, real_sb + real_bb);
+        hh.set_blinds_infos(blinds_infos);
+        hh.set_pot(
+            Street::Preflop,
+            total_ante + real_sb + real_bb + real_kill + real_dead,
+        );
 
-        // Select next to act
+        // Select next to act, the opponent of the button acts first
+        // when the button posts both blinds
         if self.player_order.len() == 2 {
+            if !btn_posts_blinds {
+                self.player_order.rotate_left(1);
+            }
+        } else if dead_sb {
             self.player_order.rotate_left(1);
         } else {
             self.player_order.rotate_left(2);
         }
 
+        // Save the blind positions for the next button, a dead SB
+        // stays at the seat of last BB
+        if self.dead_button {
+            let position_of = |id| self.player_map.get(&id).map(|p| p.position);
+            let sb_pos = match self.blind_positions {
+                Some((_, last_bb_pos)) if dead_sb => Some(last_bb_pos),
+                _ => position_of(sb_id),
+            };
+            self.blind_positions = sb_pos.zip(position_of(bb_id));
+        }
+
         let mut action_addr = None;
         for addr in self.player_order.iter() {
             if let Some(player) = self.player_map.get_mut(addr) {
@@ -315,12 +713,58 @@ impl Holdem {
         }
 
         match action_addr {
-            Some(addr) => self.ask_for_action(addr.to_owned(), effect)?,
-            None => self.next_state(effect)?, // players all go all in
+            Some(addr) if !self.is_betting_closed() => {
+                self.ask_for_action(addr.to_owned(), effect)?
+            }
+            _ => self.next_state(effect)?, // players all go all in
         }
 
         self.min_raise = self.bb;
-        self.street_bet = self.bb;
+        self.street_bet = if btn_posts_blinds {
+            self.sb + self.bb
+        } else {
+            self.bb
+        };
+        if killer_bet > self.street_bet {
+            self.street_bet = killer_bet;
+        }
+        if killer_bet == kill_blind {
+            self.min_raise = kill_blind;
+        }
+        self.display.push(Display::DealCards);
+        Ok(())
+    }
+
+    /// Whether anyone has acted in an ante-only hand, as the bets
+    /// are empty before and after the antes.
+    fn ante_only_acted(&self) -> bool {
+        self.ante_only && self.hand_history.current_hand_actions().next().is_some()
+    }
+
+    /// Take the antes of a hand without blinds.  Nobody has a bet to
+    /// call, so the player next to the button acts first.
+    fn ante_only_bets(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+        let ante_infos = self.ante_bets()?;
+        let total_ante: u64 = self.pots.iter().map(|p| p.amount).sum();
+        // Returning players have no blinds to post
+        self.dead_blind_posters.clear();
+
+        let hh = &mut self.hand_history;
+        hh.set_blinds_infos(ante_infos);
+        hh.set_pot(Street::Preflop, total_ante);
+
+        self.street_bet = 0;
+        self.min_raise = if self.bb > 0 { self.bb } else { self.ante };
+
+        let action_addr = self
+            .player_order
+            .iter()
+            .find(|id| self.player_map.get(id).is_some_and(|p| p.next_to_act()))
+            .cloned();
+        match action_addr {
+            Some(addr) if !self.is_betting_closed() => self.ask_for_action(addr, effect)?,
+            _ => self.next_state(effect)?,
+        }
         self.display.push(Display::DealCards);
         Ok(())
     }
@@ -398,10 +842,10 @@ impl Holdem {
             }
         }
 
-        // Merge pots with same (num of) owners
+        // Merge pots with same owners
         for new_pot in new_pots.iter() {
             if let Some(last_pot) = self.pots.last_mut() {
-                if new_pot.owners.len() == last_pot.owners.len() {
+                if new_pot.has_same_owners(last_pot) {
                     last_pot.merge(new_pot)?;
                 } else {
                     self.pots.push(new_pot.clone());
@@ -416,10 +860,53 @@ impl Holdem {
             old_pots,
             bet_map: self.bet_map.clone(),
         });
+        self.display.push(Display::Pots {
+            pots: self
+                .pots
+                .iter()
+                .map(|p| PotView {
+                    owners: p.owners.clone(),
+                    amount: p.amount,
+                })
+                .collect(),
+        });
         self.bet_map.clear();
         Ok(())
     }
 
+    /// Return the part of the highest bet that nobody has called, as
+    /// soon as the action closes.  It is otherwise returned by
+    /// collecting the bets, so the pots stay the same.
+    pub fn return_uncalled_bet(&mut self) -> Result<(), HandleError> {
+        let mut bets: Vec<(u64, u64)> = self.bet_map.iter().map(|(id, b)| (*id, *b)).collect();
+        bets.sort_by(|(_, b1), (_, b2)| b2.cmp(b1));
+        let Some((player_id, top_bet)) = bets.first().copied() else {
+            return Ok(());
+        };
+        let called = bets.get(1).map(|(_, b)| *b).unwrap_or(0);
+        let amount = top_bet - called;
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let player = self
+            .player_map
+            .get_mut(&player_id)
+            .ok_or(errors::internal_player_not_found())?;
+        let total_bet = self
+            .total_bet_map
+            .get_mut(&player_id)
+            .ok_or(errors::internal_malformed_total_bet())?;
+        player.chips += amount;
+        *total_bet -= amount;
+        self.bet_map.insert(player_id, called);
+        self.bet_map.retain(|_, b| *b > 0);
+        println!("Uncalled bet {} returned to {}", amount, player_id);
+        self.display
+            .push(Display::ReturnUncalledBet { player_id, amount });
+        Ok(())
+    }
+
     pub fn change_street(
         &mut self,
         effect: &mut Effect,
@@ -435,6 +922,8 @@ impl Holdem {
         println!("Street changes to {:?}", self.street);
         self.min_raise = self.bb;
         self.street_bet = 0;
+        self.street_raises = 0;
+        self.raise_closed.clear();
         self.acting_player = None;
         self.update_board(effect)?;
 
@@ -467,49 +956,81 @@ impl Holdem {
             .count()
     }
 
-    /// Count players whose status is not `Init`
+    /// Count players dealt in, i.e. not `Init`, `SitOut` or `Observer`
     pub fn count_ingame_players(&self) -> usize {
         self.player_map
             .values()
-            .filter(|p| p.status != PlayerStatus::Init)
+            .filter(|p| !p.is_dealt_out())
             .count()
     }
 
+    /// The deck indexes of the next `count` board cards, which come
+    /// after the hole cards and the board dealt so far.
+    pub fn next_board_card_indexes(&self, count: usize) -> Vec<usize> {
+        let start = self.hand_index_map.len() * self.variant.hole_cards_count() + self.board.len();
+        (start..(start + count)).collect()
+    }
+
+    /// Ask to reveal the next `count` board cards and return their
+    /// indexes.  The cards are read with `next_board_cards` once the
+    /// secrets are ready.
+    pub fn reveal_next_board_cards(
+        &self,
+        effect: &mut Effect,
+        count: usize,
+    ) -> Result<Vec<usize>, HandleError> {
+        let idxs = self.next_board_card_indexes(count);
+        effect.reveal(self.deck_random_id, idxs.clone());
+        Ok(idxs)
+    }
+
+    /// Return the next `count` board cards revealed.
+    pub fn next_board_cards(
+        &self,
+        effect: &Effect,
+        count: usize,
+    ) -> Result<Vec<String>, HandleError> {
+        let decryption = effect.get_revealed(self.deck_random_id)?;
+        self.next_board_card_indexes(count)
+            .into_iter()
+            .map(|i| {
+                decryption
+                    .get(&i)
+                    .cloned()
+                    .ok_or(errors::internal_failed_to_reveal_board())
+            })
+            .collect()
+    }
+
     /// Reveal community cards according to current street
     pub fn update_board(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
-        let players_cnt = self.count_ingame_players() * 2;
         match self.street {
             Street::Flop => {
-                effect.reveal(
-                    self.deck_random_id,
-                    (players_cnt..(players_cnt + 3)).collect::<Vec<usize>>(),
-                );
-                self.stage = HoldemStage::ShareKey;
-                println!("Board is {:?}", self.board);
-            }
-
-            Street::Turn => {
-                effect.reveal(self.deck_random_id, vec![players_cnt + 3]);
+                self.reveal_next_board_cards(effect, 3)?;
                 self.stage = HoldemStage::ShareKey;
                 println!("Board is {:?}", self.board);
             }
 
-            Street::River => {
-                effect.reveal(self.deck_random_id, vec![players_cnt + 4]);
+            Street::Turn | Street::River => {
+                self.reveal_next_board_cards(effect, 1)?;
                 self.stage = HoldemStage::ShareKey;
                 println!("Board is {:?}", self.board);
             }
 
-            // For Runner, update 5 community cards at once
+            // For Runner, update 5 community cards at once.  When run
+            // it twice, the second board shares the cards dealt before
+            // and takes the rest next to the first board.
             Street::Showdown => {
-                self.board.clear();
-                let decryption = effect.get_revealed(self.deck_random_id)?;
-                for i in players_cnt..(players_cnt + 5) {
-                    if let Some(card) = decryption.get(&i) {
-                        self.board.push(card.clone());
-                    } else {
-                        return Err(errors::internal_failed_to_reveal_board());
-                    }
+                let dealt = self.board.len();
+                let rest = 5 - dealt;
+                let runouts = if self.runouts == 2 { 2 } else { 1 };
+                let mut cards = self.next_board_cards(effect, rest * runouts)?;
+                let second_rest = cards.split_off(rest);
+                self.board.extend(cards);
+                if self.runouts == 2 {
+                    self.second_board = self.board[..dealt].to_vec();
+                    self.second_board.extend(second_rest);
+                    println!("Second board is {:?}", self.second_board);
                 }
                 let board = self.board.clone();
                 self.hand_history.set_board(board);
@@ -520,8 +1041,12 @@ impl Holdem {
         Ok(())
     }
 
-    /// Take the rake from winners' pot and update prize map.
-    pub fn take_rake_from_prize(&mut self) -> Result<u64, HandleError> {
+    /// Take the rake from pots before they are split among winners.
+    /// The rake is taken once per hand and capped at `rake_cap` BBs in total,
+    /// or at `rake_cap_chips` when it's set, thus the odd chips are
+    /// calculated on the amount after rake.  The chips
+    /// put in by the rake exempt players are not raked.
+    pub fn take_rake_from_pots(&mut self) -> Result<u64, HandleError> {
         // Only take rakes in Cash game
         if self.mode != GameMode::Cash {
             return Ok(0);
@@ -531,40 +1056,129 @@ impl Holdem {
         if self.street == Street::Preflop {
             return Ok(0);
         }
+
+        // No flop, no drop: the flop must be seen
+        if self.no_flop_no_drop && self.board.len() < 3 {
+            return Ok(0);
+        }
         let mut total_rake = 0;
 
-        let rake_cap: u64 = self.bb * self.rake_cap as u64;
+        let rake_cap: u64 = self
+            .rake_cap_chips
+            .unwrap_or(self.bb * self.rake_cap as u64);
 
-        for (_, prize) in self.prize_map.iter_mut() {
-            if *prize > 0 {
-                let r = u64::min(self.rake as u64 * *prize / 1000u64, rake_cap);
-                total_rake += r;
-                *prize = prize
-                    .checked_sub(r)
-                    .ok_or(errors::internal_amount_overflow())?;
+        // A pot covers the total bets from the level of the previous
+        // pot to the smallest total bet of its owners
+        let mut level = 0;
+        for pot in self.pots.iter_mut() {
+            let top = pot
+                .owners
+                .iter()
+                .filter_map(|id| self.total_bet_map.get(id).copied())
+                .min()
+                .unwrap_or(level);
+            let mut contributed = 0;
+            let mut exempt = 0;
+            for (id, total_bet) in self.total_bet_map.iter() {
+                let c = u64::clamp(*total_bet, level, u64::max(level, top)) - level;
+                contributed += c;
+                if self.player_map.get(id).is_some_and(|p| p.rake_exempt) {
+                    exempt += c;
+                }
             }
+            level = u64::max(level, top);
+
+            // Only the chips from players not exempt are raked
+            let rakeable = if contributed > 0 {
+                pot.amount - pot.amount * exempt / contributed
+            } else {
+                pot.amount
+            };
+            let r = u64::min(self.rake as u64 * rakeable / 1000u64, rake_cap - total_rake);
+            total_rake += r;
+            pot.amount = pot
+                .amount
+                .checked_sub(r)
+                .ok_or(errors::internal_amount_overflow())?;
         }
 
         return Ok(total_rake);
     }
 
+    /// Take the bad beat drop from the main pot, after the rake.  It's
+    /// dropped once per hand in Cash game when the flop is seen.
+    pub fn take_bad_beat_drop(&mut self) -> u64 {
+        if self.mode != GameMode::Cash || self.bad_beat_drop == 0 || self.board.len() < 3 {
+            return 0;
+        }
+        let Some(pot) = self.pots.first_mut() else {
+            return 0;
+        };
+        let drop = u64::min(self.bad_beat_drop, pot.amount);
+        pot.amount -= drop;
+        self.bad_beat_collected += drop;
+        drop
+    }
+
+    /// Find the bad beat in the hand ranking: the best losing hand is
+    /// quads or better.  Return the winner and the loser.
+    pub fn find_bad_beat(
+        &self,
+        winners: &[Vec<u64>],
+        showdowns: &[(u64, Showdown)],
+    ) -> Option<(u64, u64)> {
+        let winner = winners.first().and_then(|w| w.first()).copied()?;
+        let loser = winners.get(1).and_then(|w| w.first()).copied()?;
+        let (_, showdown) = showdowns.iter().find(|(id, _)| *id == loser)?;
+        matches!(
+            showdown.category,
+            Category::FourOfAKind | Category::StraightFlush | Category::RoyalFlush
+        )
+        .then_some((winner, loser))
+    }
+
+    /// Split the pots by the shares awarded.  In hi-lo games, a pot
+    /// with a qualifying low goes half to the high winners and half
+    /// to the low winners, the odd chip goes to the high half.  Any
+    /// other pot is awarded as a whole.
+    fn pot_shares(&self) -> Vec<(PotShare, &Vec<u64>, u64)> {
+        let mut shares = Vec::with_capacity(self.pots.len());
+        for (i, pot) in self.pots.iter().enumerate() {
+            match self.low_winners.get(i).filter(|w| !w.is_empty()) {
+                Some(low_winners) => {
+                    let low_amount = pot.amount / 2;
+                    shares.push((PotShare::High, &pot.winners, pot.amount - low_amount));
+                    shares.push((PotShare::Low, low_winners, low_amount));
+                }
+                None => shares.push((PotShare::Whole, &pot.winners, pot.amount)),
+            }
+        }
+        shares
+    }
+
     /// Build the prize map for awarding chips
     pub fn calc_prize(&mut self) -> Result<(), HandleError> {
-        let pots = &mut self.pots;
+        let shares = self.pot_shares();
         let mut prize_map = BTreeMap::<u64, u64>::new();
         // TODO: discuss the smallest unit
         let smallest_bet = 1u64;
-        let mut odd_chips = 0u64;
-        for pot in pots {
-            let cnt: u64 = pot.winners.len() as u64;
-            let remainder = pot.amount % (smallest_bet * cnt);
-            odd_chips += remainder;
-            let prize: u64 = (pot.amount - remainder) / cnt;
-            println!("Pot amount = {}", pot.amount);
+        let mut odd_chips = BTreeMap::<u64, u64>::new();
+        for (_, winners, amount) in shares.into_iter() {
+            let cnt: u64 = winners.len() as u64;
+            let remainder = amount % (smallest_bet * cnt);
+            if remainder > 0 {
+                // Giving odd chips to remainder player of the pot
+                let remainder_player = self
+                    .get_remainder_player(winners)
+                    .ok_or(errors::internal_player_not_found())?;
+                *odd_chips.entry(remainder_player).or_insert(0) += remainder;
+            }
+            let prize: u64 = (amount - remainder) / cnt;
+            println!("Pot amount = {}", amount);
             println!("Pot winner number = {}", cnt);
             println!("Pot remainder = {}", remainder);
             println!("Pot prize = {}", prize);
-            for winner in pot.winners.iter() {
+            for winner in winners.iter() {
                 prize_map
                     .entry(*winner)
                     .and_modify(|p| *p += prize)
@@ -572,19 +1186,16 @@ impl Holdem {
             }
         }
 
-        // Giving odd chips to remainder player
-        let remainder_player = self
-            .get_remainder_player()
-            .ok_or(errors::internal_player_not_found())?;
-
-        println!(
-            "Player {} to get the {} odd chips",
-            remainder_player, odd_chips
-        );
-        prize_map
-            .entry(remainder_player)
-            .and_modify(|prize| *prize += odd_chips)
-            .or_insert(odd_chips);
+        for (remainder_player, chips) in odd_chips {
+            println!(
+                "Player {} to get the {} odd chips",
+                remainder_player, chips
+            );
+            prize_map
+                .entry(remainder_player)
+                .and_modify(|prize| *prize += chips)
+                .or_insert(chips);
+        }
 
         self.prize_map = prize_map;
         Ok(())
@@ -629,18 +1240,54 @@ impl Holdem {
             }
         }
 
-        let award_pots = self
+        let award_pots = self.award_pots();
+        self.display.push(Display::AwardPots { pots: award_pots });
+
+        Ok(())
+    }
+
+    /// Assign the winners in hi-lo games, where `low_winner_sets` are
+    /// the qualifying lows from strong to weak.  Each pot is split
+    /// between the high and the low winners among its owners, or
+    /// scooped by the high winners when none of the owners has a low.
+    pub fn assign_hi_lo_winners(
+        &mut self,
+        winner_sets: Vec<Vec<u64>>,
+        low_winner_sets: Vec<Vec<u64>>,
+    ) -> Result<(), HandleError> {
+        self.low_winners = self
             .pots
             .iter()
             .map(|pot| {
-                let winners = pot.winners.clone();
-                let amount = pot.amount;
-                AwardPot { winners, amount }
+                low_winner_sets
+                    .iter()
+                    .map(|set| {
+                        set.iter()
+                            .filter(|w| pot.owners.contains(w))
+                            .copied()
+                            .collect::<Vec<u64>>()
+                    })
+                    .find(|w| !w.is_empty())
+                    .unwrap_or_default()
             })
             .collect();
-        self.display.push(Display::AwardPots { pots: award_pots });
+        self.assign_winners(winner_sets)
+    }
 
-        Ok(())
+    /// List the pots that have their winners resolved, in the order of
+    /// main pot followed by each side pot.  Each pot is a separate
+    /// entry even if it goes to the same winners, and so is each half
+    /// of a pot split between high and low.
+    pub fn award_pots(&self) -> Vec<AwardPot> {
+        self.pot_shares()
+            .into_iter()
+            .filter(|(_, winners, _)| !winners.is_empty())
+            .map(|(share, winners, amount)| AwardPot {
+                winners: winners.clone(),
+                amount,
+                share,
+            })
+            .collect()
     }
 
     /// Update the map that records players chips change (increased or decreased)
@@ -702,14 +1349,106 @@ impl Holdem {
             result_player_map.insert(*id, result);
         }
 
+        let mut boards = vec![self.board.clone()];
+        if !self.second_board.is_empty() {
+            boards.push(self.second_board.clone());
+        }
         self.display.push(Display::GameResult {
             player_map: result_player_map,
+            boards,
         });
 
+        // Track the scoopers for kill pots
+        if self.winners.len() == 1 {
+            self.last_winners.push(self.winners[0]);
+            if self.last_winners.len() > 2 {
+                self.last_winners.remove(0);
+            }
+            self.last_pot = self.prize_map.values().sum();
+        } else {
+            self.last_winners.clear();
+            self.last_pot = 0;
+        }
+
+        self.track_integrity(&chips_change_map);
         self.hand_history.set_chips_change(&chips_change_map);
         Ok(chips_change_map)
     }
 
+    /// Flag a player who loses to the same opponent again and again
+    /// in heads-up confrontations, by open-shoving or by folding
+    /// after putting chips in.  It's only observational, the flags
+    /// feed an off-chain review of chip dumping.
+    fn track_integrity(&mut self, chips_change_map: &BTreeMap<u64, i64>) {
+        if self.integrity_threshold == 0 || self.winners.len() != 1 {
+            return;
+        }
+        let winner = self.winners[0];
+        let history = &self.hand_history;
+        let is_voluntary = |action: &PlayerAction| {
+            matches!(
+                action.event,
+                GameEvent::Bet(_) | GameEvent::Raise(_) | GameEvent::Call
+            )
+        };
+
+        // Only one player put chips in and lost them
+        let losers: Vec<u64> = chips_change_map
+            .iter()
+            .filter(|(id, change)| {
+                **change < 0
+                    && history
+                        .current_hand_actions()
+                        .any(|(_, a)| a.id == **id && is_voluntary(a))
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        let &[loser] = losers.as_slice() else {
+            return;
+        };
+
+        let open_shove = actions
+            .iter()
+            .find(|(street, a)| {
+                *street == Street::Preflop
+                    && matches!(a.event, GameEvent::Bet(_) | GameEvent::Raise(_))
+            })
+            .is_some_and(|(_, a)| a.id == loser)
+            && self
+                .player_map
+                .get(&loser)
+                .is_some_and(|p| p.status == PlayerStatus::Allin);
+        let folded = actions
+            .iter()
+            .rev()
+            .find(|(_, a)| a.id == loser)
+            .is_some_and(|(_, a)| a.event == GameEvent::Fold);
+        if !open_shove && !folded {
+            return;
+        }
+
+        self.integrity_pairs.push((loser, winner));
+        if self.integrity_pairs.len() > INTEGRITY_WINDOW {
+            self.integrity_pairs.remove(0);
+        }
+        let count = self
+            .integrity_pairs
+            .iter()
+            .filter(|pair| **pair == (loser, winner))
+            .count();
+        if count >= self.integrity_threshold as usize {
+            println!(
+                "Integrity flag: {} lost to {} for {} times",
+                loser, winner, count
+            );
+            self.display.push(Display::IntegrityFlag {
+                player_id: loser,
+                opponent_id: winner,
+                count: count as u8,
+            });
+        }
+    }
+
     pub fn single_player_win(
         &mut self,
         effect: &mut Effect,
@@ -717,12 +1456,19 @@ impl Holdem {
     ) -> Result<(), HandleError> {
         self.collect_bets()?;
         self.assign_winners(vec![vec![winner]])?;
+        // The hand ends without showdown
+        let rake = if self.no_showdown_no_rake {
+            0
+        } else {
+            self.take_rake_from_pots()?
+        };
+        let drop = self.take_bad_beat_drop();
         self.calc_prize()?;
-        let rake = self.take_rake_from_prize()?;
         let _ = self.update_chips_map()?;
         self.apply_prize()?;
 
         self.mark_out_players();
+        self.mark_sitout_players();
 
         let removed_players = self.remove_leave_and_out_players();
         for player in removed_players {
@@ -733,41 +1479,241 @@ impl Holdem {
             effect.transfer(0, rake);
         }
 
+        if drop > 0 {
+            effect.transfer(JACKPOT_SLOT_ID, drop);
+        }
+
         self.wait_timeout(effect, WAIT_TIMEOUT_LAST_PLAYER);
         effect.checkpoint();
         Ok(())
     }
 
-    pub fn wait_timeout(&mut self, effect: &mut Effect, timeout: u64) {
-        self.next_game_start = effect.timestamp() + timeout;
-        if self.mode != GameMode::Mtt {
-            effect.wait_timeout(timeout);
+    /// Check if a deposit can be accepted: the player must be in game
+    /// and the amount must be a multiple of `deposit_increment`.  A
+    /// player without chips is making the initial buy-in, which must
+    /// be between `min_deposit` and `max_deposit`.  Otherwise it's a
+    /// top-up, which can't bring the stack above `max_deposit`.
+    pub fn check_deposit(&self, player_id: u64, amount: u64) -> Result<(), HandleError> {
+        let Some(p) = self.player_map.get(&player_id) else {
+            return Err(errors::deposit_player_not_found());
+        };
+        if self.deposit_increment > 0 && amount % self.deposit_increment != 0 {
+            return Err(errors::deposit_not_multiple_of_increment());
         }
+        let stack = p.chips + p.deposit;
+        if stack == 0 && amount < self.min_deposit {
+            return Err(errors::deposit_below_min_buyin());
+        }
+        if stack + amount > self.max_deposit {
+            return Err(errors::deposit_exceeds_max_buyin());
+        }
+        Ok(())
     }
 
-    pub fn fill_player_chips_with_deposits(&mut self) {
+    pub fn is_valid_deposit(&self, player_id: u64, amount: u64) -> bool {
+        self.check_deposit(player_id, amount).is_ok()
+    }
+
+    /// Add a deposit to the player, up to `max_deposit` in total with
+    /// the stack.  Return the excess to refund.  The deposit is kept
+    /// apart from the stack until the next hand starts, thus a deposit
+    /// in the middle of a hand never plays in its pots.
+    pub fn add_deposit(&mut self, player_id: u64, amount: u64) -> Result<u64, HandleError> {
+        let Some(p) = self.player_map.get(&player_id) else {
+            return Err(errors::deposit_player_not_found());
+        };
+        let room = self.max_deposit.saturating_sub(p.chips + p.deposit);
+        let accepted = if amount > room && self.deposit_increment > 0 {
+            room - room % self.deposit_increment
+        } else {
+            u64::min(amount, room)
+        };
+        if accepted == 0 && amount > 0 {
+            return Err(errors::deposit_exceeds_max_buyin());
+        }
+        self.check_deposit(player_id, accepted)?;
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::deposit_player_not_found());
+        };
+        player.deposit += accepted;
+        if !matches!(self.stage, HoldemStage::Init | HoldemStage::Settle) {
+            self.display.push(Display::DepositPending {
+                player_id,
+                amount: accepted,
+            });
+        }
+        Ok(amount - accepted)
+    }
+
+    pub fn wait_timeout(&mut self, effect: &mut Effect, timeout: u64) {
+        self.next_game_start = effect.timestamp() + timeout;
+        if self.mode != GameMode::Mtt {
+            effect.wait_timeout(timeout);
+        }
+    }
+
+    /// Move the deposits to the stacks before a hand, up to the
+    /// player's auto-rebuy stack if it's set, otherwise up to
+    /// `max_deposit`.  The rest of the deposit is kept for later.
+    pub fn fill_player_chips_with_deposits(&mut self) {
         for player in self.player_map.values_mut() {
-            if player.chips < self.max_deposit && player.deposit > 0 {
+            let target = player
+                .auto_rebuy_to
+                .map_or(self.max_deposit, |t| u64::min(t, self.max_deposit));
+            if player.chips < target && player.deposit > 0 {
                 let old_player_chips = player.chips;
-                player.chips = u64::min(player.chips + player.deposit, self.max_deposit);
+                player.chips = u64::min(player.chips + player.deposit, target);
                 player.deposit = player.deposit - player.chips + old_player_chips;
             }
         }
     }
 
-    pub fn settle(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+    /// Set the stack a player tops up to between hands, it takes
+    /// effect from the next hand.
+    pub fn set_auto_rebuy(&mut self, player_id: u64, amount: u64) -> Result<(), HandleError> {
+        if self.mode != GameMode::Cash || amount > self.max_deposit {
+            return Err(errors::auto_rebuy_not_available());
+        }
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        player.auto_rebuy_to = if amount == 0 { None } else { Some(amount) };
+        Ok(())
+    }
+
+    /// Emit the equities of the players who are still in the hand,
+    /// when two or more of them are all in.  The hole cards must have
+    /// been revealed.
+    pub fn emit_equities(&mut self, effect: &Effect) -> Result<(), HandleError> {
+        let allin_cnt = self
+            .player_map
+            .values()
+            .filter(|p| p.status == PlayerStatus::Allin)
+            .count();
+        // The equities are calculated for Hold'em with a standard deck
+        if allin_cnt < 2
+            || self.deck_spec != DeckSpec::Standard
+            || self.variant != GameVariant::Holdem
+        {
+            return Ok(());
+        }
+
         let decryption = effect.get_revealed(self.deck_random_id)?;
-        // Board
+        let showing = self.showing_players();
+        let mut ids = Vec::new();
+        let mut hole_cards = Vec::new();
+        for (id, idxs) in self.hand_index_map.iter() {
+            let Some(player) = self.player_map.get(id) else {
+                return Err(errors::internal_player_not_in_game_but_assigned_cards());
+            };
+            if !matches!(
+                player.status,
+                PlayerStatus::Wait | PlayerStatus::Acted | PlayerStatus::Allin
+            ) || self.is_forfeited(*id, &showing)
+            {
+                continue;
+            }
+            let Some(first_card) = idxs.first().and_then(|i| decryption.get(i)) else {
+                return Err(errors::first_hole_card_error());
+            };
+            let Some(second_card) = idxs.last().and_then(|i| decryption.get(i)) else {
+                return Err(errors::second_hole_card_error());
+            };
+            ids.push(*id);
+            hole_cards.push([first_card.as_str(), second_card.as_str()]);
+        }
+
         let board: Vec<&str> = self.board.iter().map(|c| c.as_str()).collect();
+        // Seeded by the hand, so every node shows the same equities
+        let equities = equity(&hole_cards, &board, EQUITY_ITERATIONS, self.hand_count);
+        let equities = ids.into_iter().zip(equities).collect();
+        self.display.push(Display::Equities { equities });
+        Ok(())
+    }
+
+    /// Return the players going to showdown in the order they show
+    /// their cards: the last aggressor goes first, or the first player
+    /// after BTN when nobody bet in the last round, then clockwise.
+    pub fn show_order(&self) -> Vec<u64> {
+        let first_pos = self
+            .hand_history
+            .last_aggressor()
+            .and_then(|id| self.player_map.get(&id))
+            .map(|p| p.position)
+            .unwrap_or(self.btn + 1);
+        let mut shown: Vec<(u64, usize)> = self
+            .player_map
+            .values()
+            .filter(|p| {
+                self.hand_index_map.contains_key(&p.id)
+                    && matches!(
+                        p.status,
+                        PlayerStatus::Wait | PlayerStatus::Acted | PlayerStatus::Allin
+                    )
+            })
+            .map(|p| {
+                if p.position >= first_pos {
+                    (p.id, p.position - first_pos)
+                } else {
+                    (p.id, p.position + 100)
+                }
+            })
+            .collect();
+        shown.sort_by(|(_, pos1), (_, pos2)| pos1.cmp(pos2));
+        shown.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Return the players to reveal the hole cards at showdown, in
+    /// the show order.  A mucked player still has to show when a pot
+    /// is left without anyone else to win it.
+    pub fn showing_players(&self) -> Vec<u64> {
+        let order = self.show_order();
+        let mut showing: Vec<u64> = order
+            .iter()
+            .filter(|id| !self.mucked.contains(id))
+            .copied()
+            .collect();
+        for pot in self.pots.iter() {
+            if pot.owners.iter().any(|id| showing.contains(id)) {
+                continue;
+            }
+            if let Some(id) = order.iter().find(|id| pot.owners.contains(id)) {
+                showing.push(*id);
+            }
+        }
+        order
+            .into_iter()
+            .filter(|id| showing.contains(id))
+            .collect()
+    }
+
+    /// Whether a player mucked the hole cards and isn't required to
+    /// show them, thus the cards are never revealed.
+    fn is_forfeited(&self, player_id: u64, showing: &[u64]) -> bool {
+        self.mucked.contains(&player_id) && !showing.contains(&player_id)
+    }
+
+    /// Evaluate the hands of unfolded players on the given board.
+    /// Return the winner sets from strong to weak, and the showdowns.
+    fn rank_hands(
+        &self,
+        board: &[&str],
+        decryption: &HashMap<usize, String>,
+    ) -> Result<(Vec<Vec<u64>>, Vec<(u64, Showdown)>), HandleError> {
         // Player hands
         let mut player_hands: Vec<(u64, PlayerHand)> = Vec::with_capacity(self.player_order.len());
 
         let mut showdowns = Vec::<(u64, Showdown)>::new();
+        // Mucked hands are not revealed, they can't win any pot
+        let showing = self.showing_players();
 
         for (id, idxs) in self.hand_index_map.iter() {
-            if idxs.len() != 2 {
+            if idxs.len() != self.variant.hole_cards_count() {
                 return Err(errors::invalid_hole_cards_number());
             }
+            if self.is_forfeited(*id, &showing) {
+                continue;
+            }
 
             let Some(player) = self.player_map.get(id) else {
                 return Err(errors::internal_player_not_found());
@@ -777,21 +1723,8 @@ impl Holdem {
                 && player.status != PlayerStatus::Init
                 && player.status != PlayerStatus::Leave
             {
-                let Some(first_card_idx) = idxs.first() else {
-                    return Err(errors::first_hole_card_index_missing());
-                };
-                let Some(first_card) = decryption.get(first_card_idx) else {
-                    return Err(errors::first_hole_card_error());
-                };
-                let Some(second_card_idx) = idxs.last() else {
-                    return Err(errors::second_hole_card_index_missing());
-                };
-                let Some(second_card) = decryption.get(second_card_idx) else {
-                    return Err(errors::second_hole_card_error());
-                };
-                let hole_cards = [first_card.as_str(), second_card.as_str()];
-                let cards = create_cards(board.as_slice(), &hole_cards);
-                let hand = evaluate_cards(cards);
+                let hole_cards = revealed_hole_cards(idxs, decryption)?;
+                let hand = self.evaluate_hand(board, &hole_cards);
                 let hole_cards = hole_cards.iter().map(|c| c.to_string()).collect();
                 let category = hand.category.clone();
                 let picks = hand.picks.iter().map(|c| c.to_string()).collect();
@@ -802,6 +1735,7 @@ impl Holdem {
                         hole_cards,
                         category,
                         picks,
+                        voluntary: false,
                     },
                 ));
             }
@@ -841,13 +1775,120 @@ impl Holdem {
 
         println!("Player rankings in order: {:?}", winners);
 
-        self.assign_winners(winners)?;
+        Ok((winners, showdowns))
+    }
+
+    /// Evaluate the high hand of the hole cards on the board, by the
+    /// rules of the variant and the deck.
+    fn evaluate_hand<'a>(&self, board: &[&'a str], hole_cards: &[&'a str]) -> PlayerHand<'a> {
+        if self.variant == GameVariant::OmahaHiLo {
+            return evaluate_omaha_high(hole_cards, board);
+        }
+        let cards = create_cards(board, hole_cards);
+        match self.deck_spec {
+            DeckSpec::Standard => evaluate_seven(cards),
+            DeckSpec::ShortDeck36 => evaluate_short_deck_cards(cards),
+            DeckSpec::WithJokers => evaluate_with_jokers(cards),
+        }
+    }
+
+    /// Rank the qualifying lows of the hands shown in hi-lo games.
+    /// Return the low winner sets from strong to weak, which is empty
+    /// when no low qualifies or the variant has no low.
+    fn rank_lows(&self, board: &[&str], showdowns: &[(u64, Showdown)]) -> Vec<Vec<u64>> {
+        if self.variant != GameVariant::OmahaHiLo {
+            return Vec::new();
+        }
+        let mut lows: Vec<(u64, Vec<u8>)> = showdowns
+            .iter()
+            .filter_map(|(id, showdown)| {
+                let hole_cards: Vec<&str> =
+                    showdown.hole_cards.iter().map(|c| c.as_str()).collect();
+                evaluate_low(&hole_cards, board).map(|low| (*id, low))
+            })
+            .collect();
+        // A smaller low is a better one
+        lows.sort_by(|(_, l1), (_, l2)| l1.cmp(l2));
+
+        let mut low_winners: Vec<Vec<u64>> = Vec::new();
+        let mut last_low: Option<Vec<u8>> = None;
+        for (id, low) in lows {
+            match low_winners.last_mut() {
+                Some(draws) if last_low.as_ref() == Some(&low) => draws.push(id),
+                _ => low_winners.push(vec![id]),
+            }
+            last_low = Some(low);
+        }
+        println!("Low rankings in order: {:?}", low_winners);
+        low_winners
+    }
+
+    /// Record the players who folded after seeing the board or mucked
+    /// at showdown, with their hole cards if they have been revealed.
+    pub fn record_mucked(&mut self, decryption: &HashMap<usize, String>) {
+        let showing = self.showing_players();
+        for (id, idxs) in self.hand_index_map.iter() {
+            let folded = self
+                .player_map
+                .get(id)
+                .is_some_and(|p| p.status == PlayerStatus::Fold);
+            let saw_board = self
+                .hand_history
+                .current_hand_actions()
+                .any(|(street, action)| action.id == *id && *street != Street::Preflop);
+            if (folded && saw_board) || self.is_forfeited(*id, &showing) {
+                let hole_cards = idxs.iter().map(|i| decryption.get(i).cloned()).collect();
+                self.hand_history.add_muck(*id, hole_cards);
+            }
+        }
+    }
+
+    pub fn settle(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+        let decryption = effect.get_revealed(self.deck_random_id)?;
+        // Board
+        let board: Vec<&str> = self.board.iter().map(|c| c.as_str()).collect();
+        let (winners, showdowns) = self.rank_hands(&board, decryption)?;
+        let lows = self.rank_lows(&board, &showdowns);
+        self.record_mucked(decryption);
+        if let Some((winner, loser)) = self.find_bad_beat(&winners, &showdowns) {
+            println!("Bad beat: {} beats {}", winner, loser);
+            self.display.push(Display::BadBeat { winner, loser });
+        }
+
+        if self.second_board.is_empty() {
+            self.assign_hi_lo_winners(winners, lows)?;
+        } else {
+            // Each board wins a half of every pot, and the odd chip
+            // of a pot stays with the first board
+            let board: Vec<&str> = self.second_board.iter().map(|c| c.as_str()).collect();
+            let (second_winners, second_showdowns) = self.rank_hands(&board, decryption)?;
+            let second_lows = self.rank_lows(&board, &second_showdowns);
+            let mut second_pots = self.pots.clone();
+            for (pot, second_pot) in self.pots.iter_mut().zip(second_pots.iter_mut()) {
+                second_pot.amount = pot.amount / 2;
+                pot.amount -= second_pot.amount;
+            }
+            self.assign_hi_lo_winners(winners, lows)?;
+            let first_pots = take(&mut self.pots);
+            let first_low_winners = take(&mut self.low_winners);
+            self.pots = second_pots;
+            self.assign_hi_lo_winners(second_winners, second_lows)?;
+            let second_pots = take(&mut self.pots);
+            let second_low_winners = take(&mut self.low_winners);
+            self.pots = first_pots;
+            self.pots.extend(second_pots);
+            self.low_winners = first_low_winners;
+            self.low_winners.extend(second_low_winners);
+        }
+        let rake = self.take_rake_from_pots()?;
+        let drop = self.take_bad_beat_drop();
         self.calc_prize()?;
-        let rake = self.take_rake_from_prize()?;
+        self.settle_insurance();
         let _ = self.update_chips_map()?;
         self.apply_prize()?;
 
         self.mark_out_players();
+        self.mark_sitout_players();
         let removed_players = self.remove_leave_and_out_players();
 
         for player in removed_players {
@@ -858,6 +1899,10 @@ impl Holdem {
             effect.transfer(0, rake);
         }
 
+        if drop > 0 {
+            effect.transfer(JACKPOT_SLOT_ID, drop);
+        }
+
         effect.checkpoint();
 
         // Save to hand history
@@ -875,7 +1920,6 @@ impl Holdem {
         let ingame_players = self.player_order.clone();
         let mut players_to_stay = Vec::<u64>::new();
         let mut players_to_act = Vec::<u64>::new();
-        let mut players_allin = Vec::<u64>::new();
 
         for id in ingame_players.iter() {
             if let Some(player) = self.player_map.get(id) {
@@ -889,7 +1933,6 @@ impl Holdem {
                     }
                     PlayerStatus::Allin => {
                         players_to_stay.push(*id);
-                        players_allin.push(*id);
                     }
                     _ => {}
                 }
@@ -926,53 +1969,66 @@ impl Holdem {
             Ok(())
         }
         // Blind bets
-        else if self.street == Street::Preflop && self.bet_map.is_empty() {
+        else if self.street == Street::Preflop
+            && self.bet_map.is_empty()
+            && !self.ante_only_acted()
+        {
             println!("[Next State]: Blind bets");
             self.blind_bets(effect)?;
             Ok(())
         }
-        // Ask next player to act
-        else if next_player.is_some() {
-            let Some(next_action_player) = next_player else {
-                return Err(errors::next_action_player_missing());
-            };
-            println!(
-                "[Next State]: Next-to-act player is: {}",
-                next_action_player
-            );
-            self.ask_for_action(next_action_player, effect)?;
-            Ok(())
-        }
-        // Runner
-        else if self.stage != HoldemStage::Runner
-            && players_allin.len() + 1 >= players_to_stay.len()
-        {
+        // Runner: deal it out as nobody can act any more
+        else if self.stage != HoldemStage::Runner && self.is_betting_closed() {
             println!("[Next State]: Runner");
             self.street = Street::Showdown;
             self.stage = HoldemStage::Runner;
             self.signal_game_end(effect)?;
+            self.return_uncalled_bet()?;
             self.collect_bets()?;
 
-            // Reveal all cards for eligible players: not folded and without init status
+            // Reveal all cards for eligible players: not folded, without
+            // init status and not mucked
+            let showing = self.showing_players();
             for (id, idxs) in self.hand_index_map.iter() {
                 let Some(player) = self.player_map.get(id) else {
                     return Err(errors::internal_player_not_in_game_but_assigned_cards());
                 };
-                if matches!(player.status, PlayerStatus::Acted | PlayerStatus::Allin) {
+                if matches!(
+                    player.status,
+                    PlayerStatus::Wait | PlayerStatus::Acted | PlayerStatus::Allin
+                ) && !self.is_forfeited(*id, &showing)
+                {
                     effect.reveal(self.deck_random_id, idxs.clone());
                 }
             }
 
-            let board_start = self.hand_index_map.len() * 2;
-            effect.reveal(
-                self.deck_random_id,
-                (board_start..(board_start + 5)).collect(),
+            // Give players a chance to buy insurance or run it twice
+            // when there are cards to come
+            if self.is_insurance_available() {
+                effect.wait_timeout(WAIT_TIMEOUT_INSURANCE);
+            } else if self.run_it_twice && self.board.len() < 5 {
+                effect.wait_timeout(WAIT_TIMEOUT_RUN_IT_TWICE);
+            } else {
+                self.deal_runner_boards(effect, 1);
+            }
+            Ok(())
+        }
+        // Ask next player to act
+        else if next_player.is_some() {
+            let Some(next_action_player) = next_player else {
+                return Err(errors::next_action_player_missing());
+            };
+            println!(
+                "[Next State]: Next-to-act player is: {}",
+                next_action_player
             );
+            self.ask_for_action(next_action_player, effect)?;
             Ok(())
         }
         // Next Street
         else if next_street != Street::Showdown {
             println!("[Next State]: Move to next street: {:?}", next_street);
+            self.return_uncalled_bet()?;
             self.change_street(effect, next_street)?;
             let street = self.street;
             let total_pot = self.pots.iter().map(|p| p.amount).sum();
@@ -985,15 +2041,19 @@ impl Holdem {
             self.stage = HoldemStage::Showdown;
             self.street = Street::Showdown;
             self.signal_game_end(effect)?;
+            self.return_uncalled_bet()?;
             self.collect_bets()?;
 
-            // Reveal players' hole cards
-            for (addr, idxs) in self.hand_index_map.iter() {
-                let Some(player) = self.player_map.get(addr) else {
+            // Reveal players' hole cards in the show order, the mucked
+            // ones are skipped
+            for addr in self.showing_players() {
+                let Some(player) = self.player_map.get(&addr) else {
                     return Err(errors::internal_player_not_in_game_but_assigned_cards());
                 };
                 if matches!(player.status, PlayerStatus::Acted | PlayerStatus::Allin) {
-                    effect.reveal(self.deck_random_id, idxs.clone());
+                    if let Some(idxs) = self.hand_index_map.get(&addr) {
+                        effect.reveal(self.deck_random_id, idxs.clone());
+                    }
                 }
             }
 
@@ -1009,6 +2069,18 @@ impl Holdem {
     ) -> Result<(), HandleError> {
         self.display.clear();
 
+        // In fixed-limit, bets and raises always take the structured size
+        let event = match event {
+            GameEvent::Bet(_) if self.betting_mode == BettingMode::FixedLimit => {
+                GameEvent::Bet(self.fixed_limit_bet_size())
+            }
+            GameEvent::Raise(_) if self.betting_mode == BettingMode::FixedLimit => {
+                let call_amount = self.street_bet - self.get_player_bet(sender);
+                GameEvent::Raise(call_amount + self.fixed_limit_bet_size())
+            }
+            _ => event,
+        };
+
         let Some(player) = self.player_map.get(&sender) else {
             return Err(HandleError::InvalidPlayer);
         };
@@ -1031,6 +2103,11 @@ impl Holdem {
                     return Err(errors::bet_amonut_is_too_small());
                 }
 
+                if self.betting_mode == BettingMode::PotLimit && amount > self.pot_limit_max(sender)
+                {
+                    return Err(errors::raise_exceeds_pot_limit());
+                }
+
                 let (allin, _) = self.take_bet(sender.clone(), amount)?;
                 self.set_player_acted(sender, allin)?;
                 self.min_raise = amount;
@@ -1068,6 +2145,59 @@ impl Holdem {
                 self.set_player_status(sender, PlayerStatus::Fold)?;
             }
 
+            GameEvent::AgreeRunItTwice => {
+                // Not a betting action, thus no next state
+                return self.agree_run_it_twice(effect, sender);
+            }
+
+            GameEvent::RabbitHunt => {
+                return self.rabbit_hunt(effect, sender);
+            }
+
+            GameEvent::ShowCards => {
+                return self.show_cards(effect, sender);
+            }
+
+            GameEvent::SetAutoRebuy(amount) => {
+                return self.set_auto_rebuy(sender, amount);
+            }
+
+            GameEvent::SitOut(hands) => {
+                return self.sit_out(sender, hands);
+            }
+
+            GameEvent::ChopBlinds => {
+                return self.agree_chop_blinds(effect, sender);
+            }
+
+            GameEvent::Muck => {
+                return self.muck(sender);
+            }
+
+            GameEvent::ShowOne(card_index) => {
+                return self.show_one(effect, sender, card_index);
+            }
+
+            GameEvent::BuyInsurance(premium) => {
+                return self.buy_insurance(effect, sender, premium);
+            }
+
+            GameEvent::Observe => {
+                return self.observe(sender);
+            }
+
+            GameEvent::SitIn => {
+                return self.sit_in(sender);
+            }
+
+            GameEvent::PostDeadBlind => {
+                return self.post_dead_blind(sender);
+            }
+
+            GameEvent::AllinProtected => {
+                return Err(errors::allin_protection_not_sendable());
+            }
+
             GameEvent::Raise(amount) => {
                 if !self.is_acting_player(sender) {
                     return Err(errors::not_the_acting_player_to_raise());
@@ -1077,16 +2207,48 @@ impl Holdem {
                     return Err(errors::player_cant_raise());
                 }
 
+                if self.betting_mode == BettingMode::FixedLimit
+                    && self.street_raises >= FIXED_LIMIT_MAX_RAISES
+                {
+                    return Err(errors::betting_capped());
+                }
+
+                if let Some(max_raises) = self.max_raises_per_street {
+                    if self.street_raises >= max_raises {
+                        return Err(errors::max_raises_per_street_reached());
+                    }
+                }
+
+                if self.raise_closed.contains(&sender) {
+                    return Err(errors::action_not_reopened());
+                }
+
                 let betted = self.get_player_bet(sender);
                 if amount + betted < self.street_bet + self.min_raise && amount != player.chips {
                     return Err(errors::raise_amount_is_too_small());
                 }
+                if self.betting_mode == BettingMode::PotLimit && amount > self.pot_limit_max(sender)
+                {
+                    return Err(errors::raise_exceeds_pot_limit());
+                }
                 let (allin, real_bet) = self.take_bet(sender.clone(), amount)?;
                 self.set_player_acted(sender, allin)?;
                 let new_street_bet = betted + real_bet;
                 let new_min_raise = new_street_bet - self.street_bet;
+                if new_min_raise >= self.min_raise {
+                    self.min_raise = new_min_raise;
+                    self.raise_closed.clear();
+                } else {
+                    // A short all-in doesn't reopen the action for
+                    // those who acted, the min raise stays
+                    for p in self.player_map.values() {
+                        if p.status == PlayerStatus::Acted && !self.raise_closed.contains(&p.id) {
+                            self.raise_closed.push(p.id);
+                        }
+                    }
+                }
                 self.street_bet = new_street_bet;
-                self.min_raise = new_min_raise;
+                self.street_raises += 1;
             }
         }
 
@@ -1098,6 +2260,414 @@ impl Holdem {
         Ok(())
     }
 
+    /// Reveal the board cards to come in Runner, for one or two
+    /// runouts.  The second runout takes the cards next to the first.
+    fn deal_runner_boards(&mut self, effect: &mut Effect, runouts: u8) {
+        let rest = 5 - self.board.len();
+        let idxs = self.next_board_card_indexes(rest * runouts as usize);
+        self.runouts = runouts;
+        effect.reveal(self.deck_random_id, idxs);
+    }
+
+    /// Record a player's agreement to chop the blinds.  It's only
+    /// available in a heads-up cash hand before any action preflop.
+    /// Once both agree, the blinds are returned and the hand ends
+    /// without a winner.
+    pub fn agree_chop_blinds(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+    ) -> Result<(), HandleError> {
+        let no_action = self.player_order.iter().all(|id| {
+            self.player_map
+                .get(id)
+                .is_some_and(|p| matches!(p.status, PlayerStatus::Wait | PlayerStatus::Acting))
+        });
+        if self.mode != GameMode::Cash
+            || self.stage != HoldemStage::Play
+            || self.street != Street::Preflop
+            || self.player_order.len() != 2
+            || self.street_raises > 0
+            || !no_action
+            || !self.player_order.contains(&player_id)
+        {
+            return Err(errors::chop_blinds_not_available());
+        }
+        if !self.chop_blinds_agreed.contains(&player_id) {
+            self.chop_blinds_agreed.push(player_id);
+        }
+        if self.chop_blinds_agreed.len() < 2 {
+            return Ok(());
+        }
+
+        println!("Both players agree to chop the blinds");
+        self.stage = HoldemStage::Settle;
+        self.signal_game_end(effect)?;
+        for (id, bet) in take(&mut self.total_bet_map) {
+            if let Some(player) = self.player_map.get_mut(&id) {
+                player.chips += bet;
+            }
+        }
+        self.bet_map.clear();
+        self.pots.clear();
+        self.display.push(Display::ChopBlinds);
+        let _ = self.update_chips_map()?;
+
+        self.mark_out_players();
+        self.mark_sitout_players();
+        let removed_players = self.remove_leave_and_out_players();
+        for player in removed_players {
+            effect.settle(player.id, player.chips + player.deposit, true)?;
+        }
+
+        self.wait_timeout(effect, WAIT_TIMEOUT_LAST_PLAYER);
+        effect.checkpoint();
+        Ok(())
+    }
+
+    /// Record a player's agreement to run it twice.  The board is run
+    /// twice once all the players in the hand agree.
+    pub fn agree_run_it_twice(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+    ) -> Result<(), HandleError> {
+        if !self.run_it_twice || self.stage != HoldemStage::Runner || self.runouts != 0 {
+            return Err(errors::run_it_twice_not_available());
+        }
+        let players_in_hand: Vec<u64> = self
+            .player_map
+            .values()
+            .filter(|p| {
+                self.hand_index_map.contains_key(&p.id)
+                    && matches!(
+                        p.status,
+                        PlayerStatus::Wait | PlayerStatus::Acted | PlayerStatus::Allin
+                    )
+            })
+            .map(|p| p.id)
+            .collect();
+        if !players_in_hand.contains(&player_id) {
+            return Err(errors::player_cant_agree_run_it_twice());
+        }
+        if !self.run_it_twice_agreed.contains(&player_id) {
+            self.run_it_twice_agreed.push(player_id);
+        }
+        if players_in_hand
+            .iter()
+            .all(|id| self.run_it_twice_agreed.contains(id))
+        {
+            println!("All players agree to run it twice");
+            self.deal_runner_boards(effect, 2);
+        }
+        Ok(())
+    }
+
+    /// Reveal the board cards that were not dealt in a hand ended
+    /// early, for players who were dealt in.  The result is untouched.
+    pub fn rabbit_hunt(&mut self, effect: &mut Effect, player_id: u64) -> Result<(), HandleError> {
+        if self.stage != HoldemStage::Settle
+            || self.deck_random_id == 0
+            || self.board.len() >= 5
+            || !self.hand_index_map.contains_key(&player_id)
+        {
+            return Err(errors::rabbit_hunt_not_available());
+        }
+        self.reveal_next_board_cards(effect, 5 - self.board.len())?;
+        self.rabbit_hunt = true;
+        Ok(())
+    }
+
+    /// Display the board completed with the hunted cards
+    pub fn show_rabbit_hunt(&mut self, effect: &Effect) -> Result<(), HandleError> {
+        self.rabbit_hunt = false;
+        let mut board = self.board.clone();
+        board.extend(self.next_board_cards(effect, 5 - self.board.len())?);
+        self.display.push(Display::RabbitHunt { board });
+        Ok(())
+    }
+
+    /// Whether the insurance can be offered when entering Runner: on
+    /// the turn, as the outs are counted on the river only.
+    fn is_insurance_available(&self) -> bool {
+        self.insurance
+            && self.deck_spec == DeckSpec::Standard
+            && self.variant == GameVariant::Holdem
+            && self.board.len() == 4
+    }
+
+    /// Offer the insurance to the sole leader of an all-in
+    /// confrontation, once the hole cards are revealed in Runner.
+    /// Nothing is offered without outs against the leader.  The
+    /// premium is limited so that the pool can pay the buyer when
+    /// outdrawn.
+    pub fn offer_insurance(&mut self, effect: &Effect) -> Result<(), HandleError> {
+        if !self.is_insurance_available() {
+            return Ok(());
+        }
+        let decryption = effect.get_revealed(self.deck_random_id)?;
+        let mut ids = Vec::new();
+        let mut hole_cards = Vec::new();
+        for id in self.showing_players() {
+            let Some(idxs) = self.hand_index_map.get(&id) else {
+                return Err(errors::internal_player_not_found());
+            };
+            let Some(first_card) = idxs.first().and_then(|i| decryption.get(i)) else {
+                return Err(errors::first_hole_card_error());
+            };
+            let Some(second_card) = idxs.last().and_then(|i| decryption.get(i)) else {
+                return Err(errors::second_hole_card_error());
+            };
+            ids.push(id);
+            hole_cards.push([first_card.as_str(), second_card.as_str()]);
+        }
+
+        let board: Vec<&str> = self.board.iter().map(|c| c.as_str()).collect();
+        let Some((leader, outs)) = count_outs(&hole_cards, &board) else {
+            return Ok(());
+        };
+        let player_id = ids[leader];
+        let cards_left = 52 - hole_cards.len() * 2 - board.len();
+        // Nothing to insure against, or nothing left to lose with
+        let outs_cnt = outs.len() as u64;
+        if outs_cnt == 0 || outs_cnt >= cards_left as u64 {
+            return Ok(());
+        }
+        // Full coverage of the pots to win
+        let pots: u64 = self
+            .pots
+            .iter()
+            .filter(|p| p.owners.contains(&player_id))
+            .map(|p| p.amount)
+            .sum();
+        let max_premium = u64::min(
+            pots * outs_cnt / cards_left as u64,
+            self.insurance_pool * outs_cnt / (cards_left as u64 - outs_cnt),
+        );
+        if max_premium == 0 {
+            return Ok(());
+        }
+        let offer = InsuranceOffer {
+            player_id,
+            outs: outs.len() as u8,
+            cards_left: cards_left as u8,
+            max_premium,
+        };
+        println!("Insurance offered: {:?}", offer);
+        self.display.push(Display::InsuranceOffer {
+            offer: offer.clone(),
+        });
+        self.insurance_offer = Some(offer);
+        Ok(())
+    }
+
+    /// Buy the insurance offered in current hand.  The board is dealt
+    /// once bought, unless waiting for running it twice.
+    pub fn buy_insurance(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+        premium: u64,
+    ) -> Result<(), HandleError> {
+        let Some(offer) = self.insurance_offer.as_ref() else {
+            return Err(errors::insurance_not_available());
+        };
+        if offer.player_id != player_id
+            || self.stage != HoldemStage::Runner
+            || self.runouts != 0
+            || self.insurance_premium > 0
+            || premium == 0
+        {
+            return Err(errors::insurance_not_available());
+        }
+        if premium > offer.max_premium {
+            return Err(errors::insurance_premium_too_large());
+        }
+        self.insurance_premium = premium;
+        if !self.run_it_twice {
+            self.deal_runner_boards(effect, 1);
+        }
+        Ok(())
+    }
+
+    /// Settle the insurance bought in current hand, after the prizes
+    /// are calculated.  The premium is shared by the insured pots in
+    /// proportion to their amounts.  The share of a pot the buyer wins
+    /// alone is paid out of the prize, the share of a pot lost is
+    /// compensated, and nothing is settled for a split pot.  The
+    /// premiums and the compensations go through the insurance pool.
+    fn settle_insurance(&mut self) {
+        let Some(offer) = self.insurance_offer.take() else {
+            return;
+        };
+        let premium = take(&mut self.insurance_premium);
+        if offer.outs == 0 || offer.outs >= offer.cards_left {
+            return;
+        }
+        let player_id = offer.player_id;
+        let insured: Vec<&Pot> = self
+            .pots
+            .iter()
+            .filter(|p| p.owners.contains(&player_id))
+            .collect();
+        let total: u64 = insured.iter().map(|p| p.amount).sum();
+        if premium == 0 || total == 0 {
+            return;
+        }
+
+        let mut paid = 0;
+        let mut lost = 0;
+        for pot in insured {
+            let share = premium * pot.amount / total;
+            if pot.winners == [player_id] {
+                paid += share;
+            } else if !pot.winners.contains(&player_id) {
+                lost += share;
+            }
+        }
+
+        let prize = self.prize_map.entry(player_id).or_insert(0);
+        let paid = u64::min(paid, *prize);
+        *prize -= paid;
+        self.insurance_pool += paid;
+
+        let amount = lost * (offer.cards_left - offer.outs) as u64 / offer.outs as u64;
+        let amount = u64::min(amount, self.insurance_pool);
+        if amount > 0 {
+            println!("Insured player {} is outdrawn", player_id);
+            *prize += amount;
+            self.insurance_pool -= amount;
+            self.display
+                .push(Display::InsurancePayout { player_id, amount });
+        }
+    }
+
+    /// Mark a player's hole cards as not to be revealed at showdown.
+    /// It's available before the showdown reveal to a player still in
+    /// the hand, who forfeits the pot unless required to show.
+    pub fn muck(&mut self, player_id: u64) -> Result<(), HandleError> {
+        let in_hand = self.player_map.get(&player_id).is_some_and(|p| {
+            matches!(
+                p.status,
+                PlayerStatus::Wait
+                    | PlayerStatus::Acting
+                    | PlayerStatus::Acted
+                    | PlayerStatus::Allin
+            )
+        });
+        if !matches!(self.stage, HoldemStage::Play | HoldemStage::ShareKey)
+            || !in_hand
+            || !self.hand_index_map.contains_key(&player_id)
+        {
+            return Err(errors::muck_not_available());
+        }
+        if !self.mucked.contains(&player_id) {
+            self.mucked.push(player_id);
+        }
+        Ok(())
+    }
+
+    /// Reveal the hole cards of the player who won uncontested, at
+    /// the player's own will.
+    pub fn show_cards(&mut self, effect: &mut Effect, player_id: u64) -> Result<(), HandleError> {
+        let idxs = self.voluntary_show_indexes(player_id)?.clone();
+        effect.reveal(self.deck_random_id, idxs);
+        self.show_cards = Some(player_id);
+        Ok(())
+    }
+
+    /// Reveal a single hole card of the player who won uncontested,
+    /// the others stay hidden.
+    pub fn show_one(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+        card_index: u8,
+    ) -> Result<(), HandleError> {
+        let idxs = self.voluntary_show_indexes(player_id)?;
+        let Some(idx) = idxs.get(card_index as usize).copied() else {
+            return Err(errors::show_cards_not_available());
+        };
+        effect.reveal(self.deck_random_id, vec![idx]);
+        self.show_cards = Some(player_id);
+        self.show_card_index = Some(card_index);
+        Ok(())
+    }
+
+    /// Return the hole card indexes of a player allowed to show them
+    /// voluntarily, i.e. the uncontested winner not required to show.
+    fn voluntary_show_indexes(&self, player_id: u64) -> Result<&Vec<usize>, HandleError> {
+        let Some(idxs) = self.hand_index_map.get(&player_id) else {
+            return Err(errors::show_cards_not_available());
+        };
+        if self.stage != HoldemStage::Settle
+            || self.deck_random_id == 0
+            || !self.hand_history.showdowns.is_empty()
+            || self.winners != vec![player_id]
+        {
+            return Err(errors::show_cards_not_available());
+        }
+        Ok(idxs)
+    }
+
+    /// Save the voluntarily shown hole cards to hand history.  The
+    /// hand is ranked only when there are enough cards on board.
+    pub fn add_voluntary_showdown(&mut self, effect: &Effect) -> Result<(), HandleError> {
+        let Some(player_id) = self.show_cards.take() else {
+            return Ok(());
+        };
+        let decryption = effect.get_revealed(self.deck_random_id)?;
+        let Some(idxs) = self.hand_index_map.get(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        // A single card shown, the others are masked
+        if let Some(card_index) = self.show_card_index.take() {
+            let Some(card) = idxs
+                .get(card_index as usize)
+                .and_then(|i| decryption.get(i))
+            else {
+                return Err(errors::first_hole_card_error());
+            };
+            let mut hole_cards = vec![MASKED_CARD.to_string(); idxs.len()];
+            hole_cards[card_index as usize] = card.clone();
+            let showdown = Showdown {
+                hole_cards,
+                category: Category::HighCard,
+                picks: vec![],
+                voluntary: true,
+            };
+            self.hand_history.add_showdown(player_id, showdown);
+            return Ok(());
+        }
+        let hole_cards = revealed_hole_cards(idxs, decryption)?;
+        let board: Vec<&str> = self.board.iter().map(|c| c.as_str()).collect();
+        let (category, picks) = if board.len() >= 3 {
+            let hand = self.evaluate_hand(&board, &hole_cards);
+            (
+                hand.category,
+                hand.picks.iter().map(|c| c.to_string()).collect(),
+            )
+        } else {
+            let mut kinds: Vec<&str> = hole_cards.iter().map(|c| c.split_at(1).1).collect();
+            kinds.sort();
+            kinds.dedup();
+            let category = if kinds.len() < hole_cards.len() {
+                Category::Pair
+            } else {
+                Category::HighCard
+            };
+            (category, vec![])
+        };
+        let showdown = Showdown {
+            hole_cards: hole_cards.iter().map(|c| c.to_string()).collect(),
+            category,
+            picks,
+            voluntary: true,
+        };
+        self.hand_history.add_showdown(player_id, showdown);
+        Ok(())
+    }
+
     pub fn set_player_acted(&mut self, player_id: u64, allin: bool) -> Result<(), HandleError> {
         self.set_player_status(
             player_id,
@@ -1129,6 +2699,81 @@ impl Holdem {
         Ok(())
     }
 
+    /// The size of a bet or raise in fixed-limit: small bets of 1BB on
+    /// preflop and flop, big bets of 2BB on turn and river.
+    pub fn fixed_limit_bet_size(&self) -> u64 {
+        match self.street {
+            Street::Turn | Street::River => 2 * self.bb,
+            _ => self.bb,
+        }
+    }
+
+    /// The maximum chips a player can put in with a bet or raise in
+    /// pot-limit: the call amount plus the pot after calling.
+    pub fn pot_limit_max(&self, player_id: u64) -> u64 {
+        let call_amount = self.street_bet - self.get_player_bet(player_id);
+        let pot: u64 =
+            self.pots.iter().map(|p| p.amount).sum::<u64>() + self.bet_map.values().sum::<u64>();
+        call_amount + pot + call_amount
+    }
+
+    /// Return the decision context of the acting player, None when
+    /// nobody is acting.  Both raise amounts are capped by the stack.
+    pub fn acting_player_context(&self) -> Option<ActingContext> {
+        let player_id = self.acting_player.as_ref()?.id;
+        let chips = self.player_map.get(&player_id)?.chips;
+        let betted = self.get_player_bet(player_id);
+        let to_call = self.street_bet.saturating_sub(betted);
+        let pot =
+            self.pots.iter().map(|p| p.amount).sum::<u64>() + self.bet_map.values().sum::<u64>();
+        // Only calling is left when the action is not reopened
+        let max_raise = if self.raise_closed.contains(&player_id) {
+            u64::min(to_call, chips)
+        } else if self.betting_mode == BettingMode::PotLimit {
+            u64::min(self.pot_limit_max(player_id), chips)
+        } else {
+            chips
+        };
+        let min_raise = u64::min(to_call + self.min_raise, max_raise);
+        Some(ActingContext {
+            player_id,
+            to_call,
+            pot,
+            min_raise,
+            max_raise,
+        })
+    }
+
+    /// The effective stack between two players: the chips of the
+    /// shorter one, 0 when either is not at the table.
+    pub fn effective_stack(&self, a: u64, b: u64) -> u64 {
+        let chips = |id| self.player_map.get(&id).map(|p| p.chips).unwrap_or(0);
+        u64::min(chips(a), chips(b))
+    }
+
+    /// The most chips a player can be called for: the stack, capped
+    /// by the largest stack of the other players still in the hand.
+    pub fn max_callable(&self, player_id: u64) -> u64 {
+        let others_max = self
+            .player_map
+            .values()
+            .filter(|p| {
+                p.id != player_id
+                    && matches!(
+                        p.status,
+                        PlayerStatus::Wait
+                            | PlayerStatus::Acted
+                            | PlayerStatus::Acting
+                            | PlayerStatus::Allin
+                    )
+            })
+            .map(|p| p.chips)
+            .max()
+            .unwrap_or(0);
+        let chips = self.player_map.get(&player_id).map(|p| p.chips).unwrap_or(0);
+        u64::min(chips, others_max)
+    }
+
     pub fn get_player_bet(&self, player_id: u64) -> u64 {
         self.bet_map.get(&player_id).cloned().unwrap_or(0)
     }
@@ -1155,6 +2800,12 @@ impl Holdem {
         self.stage = HoldemStage::Init;
         self.street = Street::Init;
         self.street_bet = 0;
+        self.street_raises = 0;
+        self.rabbit_hunt = false;
+        self.show_cards = None;
+        self.show_card_index = None;
+        self.insurance_offer = None;
+        self.insurance_premium = 0;
         self.board.clear();
         self.hand_index_map.clear();
         self.bet_map.clear();
@@ -1162,20 +2813,50 @@ impl Holdem {
         self.prize_map.clear();
         self.player_order.clear();
         self.pots.clear();
+        self.low_winners.clear();
         self.acting_player = None;
         self.winners.clear();
         self.display.clear();
         self.hand_history = HandHistory::default();
         self.next_game_start = 0;
+        self.pending_action = None;
+        self.runouts = 0;
+        self.run_it_twice_agreed.clear();
+        self.chop_blinds_agreed.clear();
+        self.mucked.clear();
+        self.second_board.clear();
+        self.bomb_pot = false;
+        self.raise_closed.clear();
         // Reset player status
         self.reset_player_map_status()?;
         Ok(())
     }
 
+    /// Whether enough players are seated to start a hand, never less
+    /// than two.
+    pub fn can_start_game(&self, effect: &Effect) -> bool {
+        let min_players = usize::max(self.min_players_to_start as usize, 2);
+        self.player_map.len() >= min_players && effect.count_nodes() >= 1
+    }
+
     pub fn position_occupied(&self, position: usize) -> bool {
         self.player_map.iter().find(|(_, ref p)| p.position == position).is_some()
     }
 
+    /// Find a seat for a joining player: the given position when it's
+    /// free, otherwise the first free one.  A leaving player keeps the
+    /// seat until removed from the table.  Return `None` when the
+    /// table is full.
+    pub fn find_position(&self, position: usize) -> Option<usize> {
+        if self.player_map.len() >= self.table_size as usize {
+            return None;
+        }
+        if position < self.table_size as usize && !self.position_occupied(position) {
+            return Some(position);
+        }
+        (0..self.table_size as usize).find(|i| !self.position_occupied(*i))
+    }
+
     pub fn internal_add_players(
         &mut self,
         add_players: Vec<InternalPlayerJoin>,
@@ -1200,17 +2881,158 @@ impl Holdem {
         Ok(())
     }
 
+    /// Deal hole cards to players, they remain invisible to others.
+    /// With `dealt_in_when_posted`, the players who can't post the
+    /// ante are dealt out with `Init` status, thus not in the pots.
+    pub fn deal_cards(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+        if self.dealt_in_when_posted && self.ante > 0 && !self.bomb_pot {
+            for player in self.player_map.values_mut() {
+                let dealt = !player.is_dealt_out();
+                if dealt && player.chips < self.ante {
+                    println!("Player {} is dealt out for not posting ante", player.id);
+                    player.status = PlayerStatus::Init;
+                }
+            }
+        }
+
+        let count = self.variant.hole_cards_count();
+        let dealt_players = self.player_map.values().filter(|p| !p.is_dealt_out());
+        for (idx, player) in dealt_players.enumerate() {
+            let idxs: Vec<usize> = (idx * count..(idx + 1) * count).collect();
+            effect.assign(self.deck_random_id, player.id, idxs.clone())?;
+            self.hand_index_map.insert(player.id, idxs);
+        }
+        Ok(())
+    }
+
+    /// Return the player who must post a kill blind in the next hand:
+    /// the one who scooped the last two pots, or the last pot of at
+    /// least `kill_threshold`.
+    pub fn find_killer(&self) -> Option<u64> {
+        if !self.kill_game {
+            return None;
+        }
+        let last_winner = self.last_winners.last().copied()?;
+        let two_in_a_row = self.last_winners.len() == 2 && self.last_winners[0] == last_winner;
+        let big_pot = self.kill_threshold > 0 && self.last_pot >= self.kill_threshold;
+        if !two_in_a_row && !big_pot {
+            return None;
+        }
+        self.player_map
+            .get(&last_winner)
+            .filter(|p| p.chips > 0)
+            .map(|p| p.id)
+    }
+
+    /// Deal out the players sitting out voluntarily for current hand.
+    /// They return once the hands to sit out are used up.
+    fn update_sitout_players(&mut self) {
+        for player in self.player_map.values_mut() {
+            if player.sitout_hands_remaining > 0 && player.status == PlayerStatus::Wait {
+                player.sitout_hands_remaining -= 1;
+                player.status = PlayerStatus::SitOut;
+            }
+        }
+    }
+
+    /// Comp a player rake free, e.g. for a promotion of the room.
+    pub fn set_rake_exempt(&mut self, player_id: u64, exempt: bool) -> Result<(), HandleError> {
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        player.rake_exempt = exempt;
+        Ok(())
+    }
+
+    /// Sit out for a number of hands from the next one, the seat and
+    /// the chips are kept.  0 to return in the next hand.
+    pub fn sit_out(&mut self, player_id: u64, hands: u8) -> Result<(), HandleError> {
+        if self.mode != GameMode::Cash {
+            return Err(errors::sit_out_not_available());
+        }
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        player.sitout_hands_remaining = hands;
+        Ok(())
+    }
+
+    /// Watch the table without being dealt in.  It's available to a
+    /// player who just joined, or between hands.
+    pub fn observe(&mut self, player_id: u64) -> Result<(), HandleError> {
+        if self.mode != GameMode::Cash {
+            return Err(errors::observe_not_available());
+        }
+        let between_hands = matches!(self.stage, HoldemStage::Init | HoldemStage::Settle);
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        if player.status != PlayerStatus::Init && !between_hands {
+            return Err(errors::observe_not_available());
+        }
+        player.status = PlayerStatus::Observer;
+        Ok(())
+    }
+
+    /// Turn an observer into a player, who is dealt in from the next
+    /// hand.
+    pub fn sit_in(&mut self, player_id: u64) -> Result<(), HandleError> {
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        if player.status != PlayerStatus::Observer {
+            return Err(errors::sit_in_not_available());
+        }
+        player.status = PlayerStatus::Init;
+        Ok(())
+    }
+
+    /// Cut the sitting out short and be dealt in from the next hand,
+    /// by posting a BB live and a SB dead to the pot.
+    pub fn post_dead_blind(&mut self, player_id: u64) -> Result<(), HandleError> {
+        let Some(player) = self.player_map.get_mut(&player_id) else {
+            return Err(errors::internal_player_not_found());
+        };
+        if self.mode != GameMode::Cash
+            || player.sitout_hands_remaining == 0
+            || player.chips < self.sb + self.bb
+        {
+            return Err(errors::dead_blind_not_available());
+        }
+        player.sitout_hands_remaining = 0;
+        if !self.dead_blind_posters.contains(&player_id) {
+            self.dead_blind_posters.push(player_id);
+        }
+        Ok(())
+    }
+
     pub fn internal_start_game(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
         self.reset_state()?;
         self.fill_player_chips_with_deposits();
+        self.update_sitout_players();
+        self.killer = self.find_killer();
 
         let next_btn = self.get_next_btn()?;
         println!("Game starts and next BTN: {}", next_btn);
         self.btn = next_btn;
 
-        if self.player_map.len() >= 2 {
+        let dealt_cnt = self
+            .player_map
+            .values()
+            .filter(|p| !matches!(p.status, PlayerStatus::SitOut | PlayerStatus::Observer))
+            .count();
+        if dealt_cnt >= 2 {
+            self.hand_count += 1;
+            self.bomb_pot = self.is_bomb_pot_hand();
+            if self.bomb_pot {
+                self.bomb_pot_bets()?;
+            }
+
             // Prepare randomness (shuffling cards)
-            let rnd_spec = RandomSpec::deck_of_cards();
+            let rnd_spec = match self.deck_spec {
+                DeckSpec::Standard => RandomSpec::deck_of_cards(),
+                spec => RandomSpec::shuffled_list(spec.cards()),
+            };
             self.deck_random_id = effect.init_random_state(rnd_spec);
         }
 
@@ -1226,6 +3048,36 @@ impl GameHandler for Holdem {
             max_deposit,
             rake,
             rake_cap,
+            min_think_time,
+            max_sitout_hands,
+            deposit_increment,
+            ante,
+            max_ante_ratio,
+            headsup_btn_posts_blinds,
+            dealt_in_when_posted,
+            run_it_twice,
+            deck_spec,
+            action_timeouts,
+            betting_mode,
+            ante_type,
+            min_deposit,
+            kill_game,
+            kill_threshold,
+            max_raises_per_street,
+            allin_protection,
+            dead_button,
+            no_flop_no_drop,
+            no_showdown_no_rake,
+            insurance,
+            insurance_fund,
+            bad_beat_drop,
+            bomb_pot_interval,
+            bomb_pot_ante,
+            action_warning,
+            min_players_to_start,
+            blinds_enabled,
+            integrity_threshold,
+            rake_cap_chips,
             ..
         } = init_account.data()?;
 
@@ -1247,6 +3099,36 @@ impl GameHandler for Holdem {
             next_game_start,
             mode: GameMode::Cash,
             table_size: init_account.max_players as _,
+            min_think_time,
+            max_sitout_hands,
+            deposit_increment,
+            ante,
+            max_ante_ratio,
+            headsup_btn_posts_blinds,
+            dealt_in_when_posted,
+            run_it_twice,
+            deck_spec,
+            action_timeouts,
+            betting_mode,
+            ante_type,
+            min_deposit,
+            kill_game,
+            kill_threshold,
+            max_raises_per_street,
+            allin_protection,
+            dead_button,
+            no_flop_no_drop,
+            no_showdown_no_rake,
+            insurance,
+            insurance_pool: insurance_fund,
+            bad_beat_drop,
+            bomb_pot_interval,
+            bomb_pot_ante,
+            action_warning,
+            min_players_to_start: u8::max(min_players_to_start, 2),
+            ante_only: !blinds_enabled,
+            integrity_threshold,
+            rake_cap_chips,
             ..Default::default()
         })
     }
@@ -1259,6 +3141,28 @@ impl GameHandler for Holdem {
                 self.reset_player_timeout(sender)?;
                 let event: GameEvent = GameEvent::try_parse(&raw)?;
                 println!("Player action event: {:?}, sender: {:?}", event, sender);
+                // Hold a betting action that comes too fast until the
+                // minimum think time, it must be valid when it arrives
+                let ready_at = self.action_asked_at + self.min_think_time;
+                let is_betting = matches!(
+                    event,
+                    GameEvent::Bet(_)
+                        | GameEvent::Call
+                        | GameEvent::Check
+                        | GameEvent::Raise(_)
+                        | GameEvent::Fold
+                );
+                if is_betting && self.is_acting_player(sender) && effect.timestamp() < ready_at {
+                    if self.pending_action.is_some() {
+                        return Err(errors::action_already_held());
+                    }
+                    let mut probe = self.clone();
+                    probe.handle_custom_event(&mut Effect::default(), event.clone(), sender)?;
+                    println!("Action from {} is held until {}", sender, ready_at);
+                    self.pending_action = Some(PlayerAction::new(sender, event));
+                    effect.wait_timeout(ready_at - effect.timestamp());
+                    return Ok(());
+                }
                 self.handle_custom_event(effect, event, sender)?;
                 Ok(())
             }
@@ -1312,6 +3216,23 @@ impl GameHandler for Holdem {
                     )?;
                     self.next_state(effect)?;
                     Ok(())
+                } else if self.can_protect_allin(player_id) {
+                    // Treat the timed out player as all-in for the
+                    // chips committed, the uncalled bets are returned
+                    // when collecting bets
+                    if let Some(player) = self.player_map.get_mut(&player_id) {
+                        player.protection_used = true;
+                    }
+                    self.set_player_status(player_id, PlayerStatus::Allin)?;
+                    self.hand_history.add_action(
+                        street,
+                        PlayerAction {
+                            id: player_id,
+                            event: GameEvent::AllinProtected,
+                        },
+                    )?;
+                    self.next_state(effect)?;
+                    Ok(())
                 } else {
                     self.set_player_status(player_id, PlayerStatus::Fold)?;
                     self.hand_history.add_action(
@@ -1326,8 +3247,40 @@ impl GameHandler for Holdem {
                 }
             }
 
+            // The held action is ready to be applied
+            Event::WaitingTimeout if self.pending_action.is_some() => {
+                let Some(PlayerAction { id, event }) = self.pending_action.take() else {
+                    return Err(errors::internal_cannot_find_action_player());
+                };
+                // The action was valid when it arrived.  Should it no longer
+                // be, the player keeps what is left of the original clock
+                if self.handle_custom_event(effect, event, id).is_err() {
+                    if let Some(ActingPlayer { id, clock, .. }) = self.acting_player.clone() {
+                        effect.action_timeout(id, clock.saturating_sub(effect.timestamp()))?;
+                    }
+                }
+                Ok(())
+            }
+
+            // Warn the acting player and dispatch the rest of the time
+            Event::WaitingTimeout if self.acting_player.is_some() => {
+                self.display.clear();
+                let Some(ActingPlayer { id, clock, .. }) = self.acting_player.clone() else {
+                    return Err(errors::internal_cannot_find_action_player());
+                };
+                self.display.push(Display::ActionWarning { player_id: id });
+                effect.action_timeout(id, clock.saturating_sub(effect.timestamp()))?;
+                Ok(())
+            }
+
+            // Not all players agree to run it twice in time, run it once
+            Event::WaitingTimeout if self.stage == HoldemStage::Runner && self.runouts == 0 => {
+                self.deal_runner_boards(effect, 1);
+                Ok(())
+            }
+
             Event::WaitingTimeout | Event::Ready => {
-                if self.player_map.len() >= 2 && effect.count_nodes() >= 1 {
+                if self.can_start_game(effect) {
                     effect.start_game();
                 }
                 Ok(())
@@ -1340,13 +3293,20 @@ impl GameHandler for Holdem {
                 self.display.clear();
 
                 for p in players.into_iter() {
-                    let player = Player::init(p.id(), 0, p.position());
+                    if self.player_map.contains_key(&p.id()) {
+                        continue;
+                    }
+                    let Some(position) = self.find_position(p.position() as usize) else {
+                        effect.warn(format!("Reject player join: {} (Table Full)", p.id()));
+                        continue;
+                    };
+                    let player = Player::init(p.id(), 0, position as u16);
                     self.player_map.insert(p.id(), player);
                 }
 
                 match self.stage {
                     HoldemStage::Init => {
-                        if self.player_map.len() >= 2 && effect.count_nodes() >= 1 {
+                        if self.can_start_game(effect) {
                             effect.start_game();
                         }
                     }
@@ -1354,7 +3314,7 @@ impl GameHandler for Holdem {
                     HoldemStage::Runner | HoldemStage::Settle | HoldemStage::Showdown => {
                         if self.next_game_start > effect.timestamp() {
                             effect.wait_timeout(self.next_game_start - effect.timestamp());
-                        } else if self.player_map.len() >= 2 && effect.count_nodes() >= 1 {
+                        } else if self.can_start_game(effect) {
                             effect.start_game();
                         }
                     }
@@ -1366,16 +3326,21 @@ impl GameHandler for Holdem {
             }
 
             Event::Deposit { deposits } => {
+                self.display.clear();
                 for d in deposits.into_iter() {
-                    if let Some(p) = self.player_map.get_mut(&d.id()) {
-                        if p.chips + p.deposit > 2 * self.max_deposit {
+                    match self.add_deposit(d.id(), d.balance()) {
+                        Err(e) => {
+                            effect.info(format!("Deposit from {} rejected: {:?}", d.id(), e));
                             effect.reject_deposit(&d)?;
-                        } else {
-                            p.deposit += d.balance();
+                        }
+                        Ok(excess) => {
                             effect.accept_deposit(&d)?;
+                            // Refund what's over the max buy-in
+                            if excess > 0 {
+                                effect.info(format!("Refund {} to {}", excess, d.id()));
+                                effect.settle(d.id(), excess, false)?;
+                            }
                         }
-                    } else {
-                        effect.reject_deposit(&d)?;
                     }
                 }
                 Ok(())
@@ -1453,21 +3418,13 @@ impl GameHandler for Holdem {
 
             Event::RandomnessReady { .. } => {
                 self.display.clear();
-                // Cards are dealt to players but remain invisible to them
-                for (idx, (id, player)) in self.player_map.iter().enumerate() {
-                    if player.status != PlayerStatus::Init {
-                        effect.assign(self.deck_random_id, *id, vec![idx * 2, idx * 2 + 1])?;
-                        self.hand_index_map.insert(*id, vec![idx * 2, idx * 2 + 1]);
-                    }
-                }
-
+                self.deal_cards(effect)?;
                 Ok(())
             }
 
             Event::SecretsReady { .. } => match self.stage {
                 HoldemStage::ShareKey => {
                     self.display.clear();
-                    let players_cnt = self.count_ingame_players() * 2;
                     let board_prev_cnt = self.board.len();
                     self.stage = HoldemStage::Play;
 
@@ -1477,14 +3434,10 @@ impl GameHandler for Holdem {
                         }
 
                         Street::Flop => {
-                            let decryption = effect.get_revealed(self.deck_random_id)?;
-                            for i in players_cnt..(players_cnt + 3) {
-                                if let Some(card) = decryption.get(&i) {
-                                    self.board.push(card.clone());
-                                } else {
-                                    return Err(errors::flop_cards_error());
-                                }
-                            }
+                            let Ok(cards) = self.next_board_cards(effect, 3) else {
+                                return Err(errors::flop_cards_error());
+                            };
+                            self.board.extend(cards);
                             self.display.push(Display::DealBoard {
                                 prev: board_prev_cnt,
                                 board: self.board.clone(),
@@ -1494,34 +3447,28 @@ impl GameHandler for Holdem {
                         }
 
                         Street::Turn => {
-                            let decryption = effect.get_revealed(self.deck_random_id)?;
-                            let card_index = players_cnt + 3;
-                            if let Some(card) = decryption.get(&card_index) {
-                                self.board.push(card.clone());
-                                self.display.push(Display::DealBoard {
-                                    prev: board_prev_cnt,
-                                    board: self.board.clone(),
-                                });
-                            } else {
+                            let Ok(cards) = self.next_board_cards(effect, 1) else {
                                 return Err(errors::turn_card_error());
-                            }
+                            };
+                            self.board.extend(cards);
+                            self.display.push(Display::DealBoard {
+                                prev: board_prev_cnt,
+                                board: self.board.clone(),
+                            });
 
                             self.hand_history.set_board(self.board.clone());
                             self.next_state(effect)?;
                         }
 
                         Street::River => {
-                            let decryption = effect.get_revealed(self.deck_random_id)?;
-                            let card_index = players_cnt + 4;
-                            if let Some(card) = decryption.get(&card_index) {
-                                self.board.push(card.clone());
-                                self.display.push(Display::DealBoard {
-                                    prev: board_prev_cnt,
-                                    board: self.board.clone(),
-                                });
-                            } else {
+                            let Ok(cards) = self.next_board_cards(effect, 1) else {
                                 return Err(errors::river_card_error());
-                            }
+                            };
+                            self.board.extend(cards);
+                            self.display.push(Display::DealBoard {
+                                prev: board_prev_cnt,
+                                board: self.board.clone(),
+                            });
 
                             self.hand_history.set_board(self.board.clone());
                             self.next_state(effect)?;
@@ -1536,6 +3483,17 @@ impl GameHandler for Holdem {
                 HoldemStage::Init => {
                     self.display.clear();
                     match self.street {
+                        // The antes are in, deal the flop without
+                        // preflop betting
+                        Street::Init if self.bomb_pot => {
+                            self.street = Street::Preflop;
+                            self.stage = HoldemStage::Play;
+                            self.change_street(effect, Street::Flop)?;
+                            let total_pot = self.pots.iter().map(|p| p.amount).sum();
+                            self.hand_history.set_pot(Street::Flop, total_pot);
+                            Ok(())
+                        }
+
                         Street::Init => {
                             self.street = Street::Preflop;
                             self.stage = HoldemStage::Play;
@@ -1548,9 +3506,21 @@ impl GameHandler for Holdem {
                     }
                 }
 
+                // Only hole cards are revealed, waiting for the
+                // decision on running it twice or the insurance
+                HoldemStage::Runner if self.runouts == 0 => {
+                    self.display.clear();
+                    self.offer_insurance(effect)?;
+                    if self.insurance_offer.is_none() && !self.run_it_twice {
+                        self.deal_runner_boards(effect, 1);
+                    }
+                    Ok(())
+                }
+
                 // Ending, comparing cards
                 HoldemStage::Runner => {
                     self.display.clear();
+                    self.emit_equities(effect)?;
                     let prev_board_cnt = self.board.len();
                     self.update_board(effect)?;
                     self.display.push(Display::DealBoard {
@@ -1563,6 +3533,18 @@ impl GameHandler for Holdem {
                     Ok(())
                 }
 
+                // The hunted cards are display only
+                HoldemStage::Settle if self.rabbit_hunt => {
+                    self.display.clear();
+                    self.show_rabbit_hunt(effect)?;
+                    Ok(())
+                }
+
+                HoldemStage::Settle if self.show_cards.is_some() => {
+                    self.add_voluntary_showdown(effect)?;
+                    Ok(())
+                }
+
                 // Ending, comparing cards
                 HoldemStage::Showdown => {
                     self.display.clear();
@@ -3,7 +3,7 @@ use race_api::prelude::HandleError;
 
 use crate::{
     errors,
-    essential::{GameEvent, Street},
+    essential::{BettingMode, GameEvent, Street},
     evaluator::Category,
 };
 use std::collections::BTreeMap;
@@ -21,6 +21,8 @@ pub enum BlindType {
     Bb,
     Ante,
     Stradle,
+    Kill,
+    DeadSb,
 }
 
 #[derive(Debug, BorshDeserialize, BorshSerialize, PartialEq, Clone)]
@@ -61,11 +63,168 @@ pub struct StreetActions {
     pub actions: Vec<PlayerAction>,
 }
 
+/// The placeholder of a hole card kept hidden in a showdown
+pub const MASKED_CARD: &str = "??";
+
 #[derive(Debug, BorshDeserialize, BorshSerialize, PartialEq, Clone)]
 pub struct Showdown {
-    pub hole_cards: Vec<String>,
+    pub hole_cards: Vec<String>, // a hidden card is `MASKED_CARD`
     pub category: Category,
     pub picks: Vec<String>,
+    pub voluntary: bool, // shown without being called, e.g. by an uncontested winner
+}
+
+/// A seat at the start of a hand, for exporting the hand history
+#[derive(Debug, PartialEq, Clone)]
+pub struct SeatInfo {
+    pub id: u64,
+    pub position: usize, // zero indexed
+    pub chips: u64,
+}
+
+/// The table information not kept in the hand history, required by
+/// the exported text
+#[derive(Debug, PartialEq, Clone)]
+pub struct TableMeta {
+    pub hand_id: u64,
+    pub table_name: String,
+    pub table_size: usize,
+    pub sb: u64,
+    pub bb: u64,
+    pub betting_mode: BettingMode,
+    pub btn: usize,
+    pub timestamp: u64, // msecs since epoch
+    pub seats: Vec<SeatInfo>,
+}
+
+/// Convert a card like "ha" to the "Ah" form
+fn stars_card(card: &str) -> String {
+    let (suit, rank) = card.split_at(1);
+    format!("{}{}", rank.to_uppercase(), suit)
+}
+
+fn stars_cards(cards: &[String]) -> String {
+    cards
+        .iter()
+        .map(|c| stars_card(c))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format an epoch timestamp in msecs as "YYYY/MM/DD hh:mm:ss UTC"
+fn format_utc(timestamp: u64) -> String {
+    let secs = timestamp / 1000;
+    let rem = secs % 86400;
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}/{:02}/{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Track the chips put in pots when replaying the actions
+#[derive(Default)]
+struct ChipsTracker {
+    stacks: BTreeMap<u64, u64>,
+    invested: BTreeMap<u64, u64>,
+    bets: BTreeMap<u64, u64>,
+    street_bet: u64,
+}
+
+impl ChipsTracker {
+    /// Put chips in and return true if the player goes all in
+    fn put_in(&mut self, id: u64, amount: u64, is_bet: bool) -> bool {
+        let stack = self.stacks.entry(id).or_insert(0);
+        *stack = stack.saturating_sub(amount);
+        *self.invested.entry(id).or_insert(0) += amount;
+        if is_bet {
+            let bet = self.bets.entry(id).or_insert(0);
+            *bet += amount;
+            self.street_bet = u64::max(self.street_bet, *bet);
+        }
+        *stack == 0
+    }
+
+    fn bet_of(&self, id: u64) -> u64 {
+        self.bets.get(&id).copied().unwrap_or(0)
+    }
+
+    fn stack_of(&self, id: u64) -> u64 {
+        self.stacks.get(&id).copied().unwrap_or(0)
+    }
+}
+
+/// The rank name of a card like "ha", in plural for the grouped
+/// ranks, e.g. "Aces"
+fn stars_rank(card: &str, plural: bool) -> String {
+    let name = match card.get(1..) {
+        Some("a") => "Ace",
+        Some("k") => "King",
+        Some("q") => "Queen",
+        Some("j") => "Jack",
+        Some("t") => "Ten",
+        Some("9") => "Nine",
+        Some("8") => "Eight",
+        Some("7") => "Seven",
+        Some("6") => "Six",
+        Some("5") => "Five",
+        Some("4") => "Four",
+        Some("3") => "Three",
+        Some("2") => "Deuce",
+        _ => return card.to_string(),
+    };
+    match (plural, name) {
+        (false, _) => name.to_string(),
+        (true, "Six") => "Sixes".to_string(),
+        (true, _) => format!("{}s", name),
+    }
+}
+
+/// Describe the hand shown the PokerStars way, e.g. "three of a
+/// kind, Kings".  The picks are in comparison order, with the
+/// grouped ranks first and the straights from the highest.  None for
+/// the cards shown without a hand.
+fn stars_hand(category: &Category, picks: &[String]) -> Option<String> {
+    if picks.len() < 5 {
+        return None;
+    }
+    let rank = |i: usize| stars_rank(&picks[i], false);
+    let ranks = |i: usize| stars_rank(&picks[i], true);
+    let hand = match category {
+        Category::RoyalFlush => "a Royal Flush".to_string(),
+        Category::StraightFlush => format!("a straight flush, {} to {}", rank(4), rank(0)),
+        Category::FourOfAKind => format!("four of a kind, {}", ranks(0)),
+        Category::FullHouse => format!("a full house, {} full of {}", ranks(0), ranks(3)),
+        Category::Flush => format!("a flush, {} high", rank(0)),
+        Category::Straight => format!("a straight, {} to {}", rank(4), rank(0)),
+        Category::ThreeOfAKind => format!("three of a kind, {}", ranks(0)),
+        Category::TwoPairs => format!("two pair, {} and {}", ranks(0), ranks(2)),
+        Category::Pair => format!("a pair of {}", ranks(0)),
+        Category::HighCard => format!("high card {}", rank(0)),
+    };
+    Some(hand)
+}
+
+fn allin_suffix(allin: bool) -> &'static str {
+    if allin {
+        " and is all-in"
+    } else {
+        ""
+    }
 }
 
 #[derive(Default, Debug, BorshDeserialize, BorshSerialize, PartialEq, Clone)]
@@ -81,6 +240,10 @@ pub struct HandHistory {
     pub showdowns: BTreeMap<u64, Showdown>,
     // Player address -> chips change
     pub chips_change: BTreeMap<u64, ChipsChange>,
+    // Players who folded after seeing the board
+    pub mucked: Vec<u64>,
+    // Player address -> mucked hole cards, for those forced to show
+    pub revealed_muck: Option<BTreeMap<u64, Vec<String>>>,
 }
 
 impl HandHistory {
@@ -145,7 +308,259 @@ impl HandHistory {
         Ok(())
     }
 
+    fn street_actions(&self) -> [(Street, &StreetActions); 4] {
+        [
+            (Street::Preflop, &self.preflop),
+            (Street::Flop, &self.flop),
+            (Street::Turn, &self.turn),
+            (Street::River, &self.river),
+        ]
+    }
+
+    /// The actions recorded so far in current hand, in order.
+    pub fn current_hand_actions(&self) -> impl Iterator<Item = (Street, &PlayerAction)> {
+        self.street_actions()
+            .into_iter()
+            .flat_map(|(street, actions)| actions.actions.iter().map(move |a| (street, a)))
+    }
+
+    /// Return the player who made the last bet or raise in the last
+    /// betting round, None if that round was checked through.
+    pub fn last_aggressor(&self) -> Option<u64> {
+        let (_, last_round) = self
+            .street_actions()
+            .into_iter()
+            .rev()
+            .find(|(_, actions)| !actions.actions.is_empty())?;
+        last_round
+            .actions
+            .iter()
+            .rev()
+            .find(|action| matches!(action.event, GameEvent::Bet(_) | GameEvent::Raise(_)))
+            .map(|action| action.id)
+    }
+
     pub fn add_showdown(&mut self, id: u64, showdown: Showdown) {
         self.showdowns.insert(id, showdown);
     }
+
+    pub fn add_muck(&mut self, id: u64, hole_cards: Option<Vec<String>>) {
+        self.mucked.push(id);
+        if let Some(hole_cards) = hole_cards {
+            self.revealed_muck
+                .get_or_insert_with(BTreeMap::new)
+                .insert(id, hole_cards);
+        }
+    }
+
+    /// Render the hand in PokerStars text format, to be replayed in
+    /// third-party tools.  Players are named by their ids.
+    pub fn to_pokerstars_text(&self, table_meta: &TableMeta) -> String {
+        let limit = match table_meta.betting_mode {
+            BettingMode::NoLimit => "No Limit",
+            BettingMode::PotLimit => "Pot Limit",
+            BettingMode::FixedLimit => "Limit",
+        };
+        let mut lines = vec![
+            format!(
+                "PokerStars Hand #{}: Hold'em {} ({}/{}) - {}",
+                table_meta.hand_id,
+                limit,
+                table_meta.sb,
+                table_meta.bb,
+                format_utc(table_meta.timestamp)
+            ),
+            format!(
+                "Table '{}' {}-max Seat #{} is the button",
+                table_meta.table_name,
+                table_meta.table_size,
+                table_meta.btn + 1
+            ),
+        ];
+
+        let mut tracker = ChipsTracker::default();
+        for seat in table_meta.seats.iter() {
+            lines.push(format!(
+                "Seat {}: {} ({} in chips)",
+                seat.position + 1,
+                seat.id,
+                seat.chips
+            ));
+            tracker.stacks.insert(seat.id, seat.chips);
+        }
+
+        for blind in self.blinds.iter() {
+            let name = match blind.blind_type {
+                BlindType::Sb => "small blind",
+                BlindType::Bb => "big blind",
+                BlindType::Ante => "the ante",
+                BlindType::Stradle => "straddle",
+                BlindType::Kill => "kill blind",
+                BlindType::DeadSb => "dead small blind",
+            };
+            lines.push(format!("{}: posts {} {}", blind.id, name, blind.amount));
+            // Antes and dead blinds go to the pot and don't count as the street bet
+            let live = !matches!(blind.blind_type, BlindType::Ante | BlindType::DeadSb);
+            tracker.put_in(blind.id, blind.amount, live);
+        }
+
+        let board = &self.board;
+        let streets = [
+            (Street::Preflop, "*** HOLE CARDS ***".to_string(), 0),
+            (
+                Street::Flop,
+                format!(
+                    "*** FLOP *** [{}]",
+                    stars_cards(&board[..board.len().min(3)])
+                ),
+                3,
+            ),
+            (
+                Street::Turn,
+                format!(
+                    "*** TURN *** [{}] [{}]",
+                    stars_cards(&board[..board.len().min(3)]),
+                    stars_cards(&board[board.len().min(3)..board.len().min(4)])
+                ),
+                4,
+            ),
+            (
+                Street::River,
+                format!(
+                    "*** RIVER *** [{}] [{}]",
+                    stars_cards(&board[..board.len().min(4)]),
+                    stars_cards(&board[board.len().min(4)..board.len().min(5)])
+                ),
+                5,
+            ),
+        ];
+        for (street, header, board_cnt) in streets {
+            if board.len() < board_cnt {
+                break;
+            }
+            lines.push(header);
+            if street != Street::Preflop {
+                tracker.bets.clear();
+                tracker.street_bet = 0;
+            }
+            for (_, action) in self.current_hand_actions().filter(|(s, _)| *s == street) {
+                let id = action.id;
+                let text = match action.event {
+                    GameEvent::Fold => "folds".to_string(),
+                    GameEvent::Check => "checks".to_string(),
+                    GameEvent::Call => {
+                        let to_call = tracker.street_bet.saturating_sub(tracker.bet_of(id));
+                        let amount = u64::min(to_call, tracker.stack_of(id));
+                        let allin = tracker.put_in(id, amount, true);
+                        format!("calls {}{}", amount, allin_suffix(allin))
+                    }
+                    GameEvent::Bet(amount) => {
+                        let allin = tracker.put_in(id, amount, true);
+                        format!("bets {}{}", amount, allin_suffix(allin))
+                    }
+                    GameEvent::Raise(amount) => {
+                        let street_bet = tracker.street_bet;
+                        let allin = tracker.put_in(id, amount, true);
+                        let raise_to = tracker.bet_of(id);
+                        format!(
+                            "raises {} to {}{}",
+                            raise_to.saturating_sub(street_bet),
+                            raise_to,
+                            allin_suffix(allin)
+                        )
+                    }
+                    GameEvent::AllinProtected => "has timed out".to_string(),
+                    _ => continue,
+                };
+                lines.push(format!("{}: {}", id, text));
+            }
+        }
+
+        if !self.showdowns.is_empty() {
+            lines.push("*** SHOW DOWN ***".to_string());
+            for (id, showdown) in self.showdowns.iter() {
+                let cards = stars_cards(&showdown.hole_cards);
+                match stars_hand(&showdown.category, &showdown.picks) {
+                    Some(hand) => lines.push(format!("{}: shows [{}] ({})", id, cards, hand)),
+                    None => lines.push(format!("{}: shows [{}]", id, cards)),
+                }
+            }
+        }
+
+        let total_pot: u64 = tracker.invested.values().sum();
+        let mut collected_total = 0;
+        for (id, change) in self.chips_change.iter() {
+            if let ChipsChange::Add(amount) = change {
+                let collected = tracker.invested.get(id).copied().unwrap_or(0) + amount;
+                collected_total += collected;
+                lines.push(format!("{} collected {} from pot", id, collected));
+            }
+        }
+
+        lines.push("*** SUMMARY ***".to_string());
+        lines.push(format!(
+            "Total pot {} | Rake {}",
+            total_pot,
+            total_pot.saturating_sub(collected_total)
+        ));
+        if !board.is_empty() {
+            lines.push(format!("Board [{}]", stars_cards(board)));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(category: Category, picks: [&str; 5]) -> Option<String> {
+        stars_hand(&category, &picks.map(String::from))
+    }
+
+    #[test]
+    fn test_stars_hand() {
+        assert_eq!(
+            hand(Category::RoyalFlush, ["sa", "sk", "sq", "sj", "st"]),
+            Some("a Royal Flush".into())
+        );
+        assert_eq!(
+            hand(Category::StraightFlush, ["h9", "h8", "h7", "h6", "h5"]),
+            Some("a straight flush, Five to Nine".into())
+        );
+        assert_eq!(
+            hand(Category::FourOfAKind, ["s6", "h6", "d6", "c6", "sa"]),
+            Some("four of a kind, Sixes".into())
+        );
+        assert_eq!(
+            hand(Category::FullHouse, ["sk", "hk", "dk", "sa", "ha"]),
+            Some("a full house, Kings full of Aces".into())
+        );
+        assert_eq!(
+            hand(Category::Flush, ["ca", "cj", "c8", "c4", "c2"]),
+            Some("a flush, Ace high".into())
+        );
+        assert_eq!(
+            hand(Category::Straight, ["d5", "s4", "h3", "c2", "sa"]),
+            Some("a straight, Ace to Five".into())
+        );
+        assert_eq!(
+            hand(Category::ThreeOfAKind, ["sk", "dk", "hk", "sa", "c7"]),
+            Some("three of a kind, Kings".into())
+        );
+        assert_eq!(
+            hand(Category::TwoPairs, ["sq", "hq", "s2", "d2", "ca"]),
+            Some("two pair, Queens and Deuces".into())
+        );
+        assert_eq!(
+            hand(Category::Pair, ["st", "ht", "sa", "c9", "d4"]),
+            Some("a pair of Tens".into())
+        );
+        assert_eq!(
+            hand(Category::HighCard, ["sa", "hq", "d9", "c7", "s3"]),
+            Some("high card Ace".into())
+        );
+        // Shown voluntarily without a hand
+        assert_eq!(stars_hand(&Category::HighCard, &[]), None);
+    }
 }
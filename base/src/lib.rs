@@ -2,10 +2,12 @@
 //! - Essentials: such as Bet, Pot, Player, Street and so on
 //! - Evaluator: functions and structs used in settle for comparing players' hands
 //! - Game: the core logic of handling various events in the game
+//! - Replay: feeding a recorded hand history back through the game
 //!
 
 pub mod evaluator;
 pub mod essential;
 pub mod game;
 pub mod hand_history;
+pub mod replay;
 pub mod errors;
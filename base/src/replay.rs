@@ -0,0 +1,127 @@
+//! Replay a recorded hand through the game handler, to verify the
+//! engine reproduces the same outcome, e.g. in regression tests or
+//! for resolving disputes.  The blinds are posted and the board is
+//! dealt by the engine, then checked against the history.  Decisions
+//! not kept in the hand history, such as running it twice or buying
+//! insurance, are not replayed.
+
+use race_api::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::errors;
+use crate::essential::GameEvent;
+use crate::game::Holdem;
+use crate::hand_history::{ChipsChange, HandHistory, MASKED_CARD};
+
+/// The table right before the hand starts
+#[derive(Debug, Clone)]
+pub struct InitialState {
+    pub holdem: Holdem,
+    // Player address -> hole cards, those not given are taken from
+    // the showdowns, or masked if never shown
+    pub hole_cards: BTreeMap<u64, Vec<String>>,
+}
+
+/// The table after the replayed hand is settled
+#[derive(Debug, Clone, PartialEq)]
+pub struct FinalState {
+    // Player address -> chips after the hand
+    pub chips: BTreeMap<u64, u64>,
+    // Player address -> chips change
+    pub chips_change: BTreeMap<u64, ChipsChange>,
+}
+
+impl Holdem {
+    /// Replay the hand from the initial state and return the final
+    /// state.  It fails when the blinds posted or the chips changes
+    /// differ from the history.
+    pub fn replay(history: &HandHistory, initial: InitialState) -> Result<FinalState, HandleError> {
+        let InitialState {
+            holdem: mut replayed,
+            hole_cards,
+        } = initial;
+        // Recorded actions are applied as soon as they are asked for
+        replayed.min_think_time = 0;
+        let mut effect = Effect::default();
+
+        replayed.handle_event(&mut effect, Event::GameStart)?;
+        let random_id = replayed.deck_random_id;
+        replayed.handle_event(&mut effect, Event::RandomnessReady { random_id })?;
+        let deck = replayed.replayed_deck(history, &hole_cards);
+        effect.revealed.insert(random_id, deck);
+        replayed.handle_event(
+            &mut effect,
+            Event::SecretsReady {
+                random_ids: vec![random_id],
+            },
+        )?;
+        if replayed.hand_history.blinds != history.blinds {
+            return Err(errors::replay_blinds_mismatch());
+        }
+
+        for (_, action) in history.current_hand_actions() {
+            if action.event == GameEvent::AllinProtected {
+                // Protected when timed out, see `Event::ActionTimeout`
+                replayed.handle_event(
+                    &mut effect,
+                    Event::ActionTimeout {
+                        player_id: action.id,
+                    },
+                )?;
+            } else {
+                replayed.handle_custom_event(&mut effect, action.event.clone(), action.id)?;
+            }
+            // Share the secrets once the engine asks for them: the
+            // board cards and the hole cards to show
+            while !effect.reveals.is_empty() {
+                effect.reveals.clear();
+                replayed.handle_event(
+                    &mut effect,
+                    Event::SecretsReady {
+                        random_ids: vec![random_id],
+                    },
+                )?;
+            }
+        }
+
+        if replayed.hand_history.chips_change != history.chips_change {
+            return Err(errors::replay_chips_change_mismatch());
+        }
+
+        Ok(FinalState {
+            chips: replayed
+                .player_map
+                .values()
+                .map(|p| (p.id, p.chips))
+                .collect(),
+            chips_change: replayed.hand_history.chips_change,
+        })
+    }
+
+    /// The deck as it was revealed in the hand: the hole cards at the
+    /// indexes dealt, followed by the board.
+    fn replayed_deck(
+        &self,
+        history: &HandHistory,
+        hole_cards: &BTreeMap<u64, Vec<String>>,
+    ) -> HashMap<usize, String> {
+        let mut deck = HashMap::new();
+        for (id, idxs) in self.hand_index_map.iter() {
+            let cards = hole_cards
+                .get(id)
+                .or_else(|| history.showdowns.get(id).map(|s| &s.hole_cards));
+            for (i, idx) in idxs.iter().enumerate() {
+                let card = cards
+                    .and_then(|cards| cards.get(i))
+                    .map_or(MASKED_CARD, |c| c.as_str());
+                deck.insert(*idx, card.to_string());
+            }
+        }
+        let board_idxs = self.next_board_card_indexes(history.board.len());
+        for (idx, card) in board_idxs.into_iter().zip(history.board.iter()) {
+            deck.insert(idx, card.clone());
+        }
+        deck
+    }
+}
+
@@ -56,6 +56,10 @@ export class AwardPot {
 
   @field('u64')
   amount!: bigint;
+
+  // 0 for the whole pot, 1 for the high half, 2 for the low half
+  @field('u8')
+  share!: number;
 }
 
 export class ChipsChange {
@@ -125,10 +129,150 @@ export class AwardPots extends Display {
   }
 }
 
+export class ActionTimeouts {
+  @field('u64')
+  preflop!: bigint;
+
+  @field('u64')
+  postflop!: bigint;
+
+  @field('u64')
+  turn!: bigint;
+
+  @field('u64')
+  river!: bigint;
+
+  @field('u64')
+  afk!: bigint;
+
+  constructor(fields: any) {
+    Object.assign(this, fields);
+  }
+}
+
+export class HoldemAccount {
+  @field('u64')
+  sb!: bigint;
+
+  @field('u64')
+  bb!: bigint;
+
+  @field('u64')
+  ante!: bigint;
+
+  @field('u16')
+  rake!: number;
+
+  // The maximum rake in BB
+  @field('u8')
+  rakeCap!: number;
+
+  @field('u64')
+  maxDeposit!: bigint;
+
+  @field(option('string'))
+  theme!: string | undefined;
+
+  @field('u64')
+  minThinkTime!: bigint;
+
+  @field('u8')
+  maxSitoutHands!: number;
+
+  @field('u64')
+  depositIncrement!: bigint;
+
+  @field('u16')
+  maxAnteRatio!: number;
+
+  @field('bool')
+  headsupBtnPostsBlinds!: boolean;
+
+  @field('bool')
+  dealtInWhenPosted!: boolean;
+
+  @field('bool')
+  runItTwice!: boolean;
+
+  @field('u8')
+  deckSpec!: number;
+
+  @field(option(struct(ActionTimeouts)))
+  actionTimeouts!: ActionTimeouts | undefined;
+
+  @field('u8')
+  bettingMode!: number;
+
+  @field('u8')
+  anteType!: number;
+
+  @field('u64')
+  minDeposit!: bigint;
+
+  @field('bool')
+  killGame!: boolean;
+
+  @field('u64')
+  killThreshold!: bigint;
+
+  @field(option('u8'))
+  maxRaisesPerStreet!: number | undefined;
+
+  @field('bool')
+  allinProtection!: boolean;
+
+  @field('bool')
+  deadButton!: boolean;
+
+  @field('bool')
+  noFlopNoDrop!: boolean;
+
+  @field('bool')
+  noShowdownNoRake!: boolean;
+
+  @field('bool')
+  insurance!: boolean;
+
+  @field('u64')
+  insuranceFund!: bigint;
+
+  @field('u64')
+  badBeatDrop!: bigint;
+
+  @field('u16')
+  bombPotInterval!: number;
+
+  @field('u64')
+  bombPotAnte!: bigint;
+
+  @field('u64')
+  actionWarning!: bigint;
+
+  @field('u8')
+  minPlayersToStart!: number;
+
+  @field('bool')
+  blindsEnabled!: boolean;
+
+  @field('u8')
+  integrityThreshold!: number;
+
+  // The maximum rake in chips, replacing `rakeCap`
+  @field(option('u64'))
+  rakeCapChips!: bigint | undefined;
+
+  constructor(fields: any) {
+    Object.assign(this, fields);
+  }
+}
+
 export class Holdem {
   @field('usize')
   deck_random_id!: number;
 
+  @field('u64')
+  maxDeposit!: bigint;
+
   @field('u64')
   sb!: bigint;
 
@@ -144,6 +288,9 @@ export class Holdem {
   @field('u16')
   rake!: number;
 
+  @field('u8')
+  rakeCap!: number;
+
   @field('u8')
   stage!: number;
 
@@ -162,6 +309,9 @@ export class Holdem {
   @field(map('string', 'u64'))
   betMap!: Map<string, bigint>;
 
+  @field(map('string', 'u64'))
+  totalBetMap!: Map<string, bigint>;
+
   @field(map('string', 'u64'))
   prizeMap!: Map<string, bigint>;
 
@@ -8,7 +8,7 @@ use std::collections::BTreeMap;
 use borsh::{BorshDeserialize, BorshSerialize};
 use helper::make_uneven_betmap;
 use race_holdem_base::essential::{
-    AwardPot, Display, GameEvent, HoldemAccount, Player, PlayerResult, PlayerStatus, Pot,
+    AwardPot, Display, GameEvent, HoldemAccount, Player, PlayerResult, PlayerStatus, Pot, PotShare,
 };
 
 const ALICE: u64 = 0;
@@ -59,6 +59,19 @@ fn test_borsh_game_event() {
         GameEvent::Fold,
         GameEvent::Check,
         GameEvent::Raise(60),
+        GameEvent::AgreeRunItTwice,
+        GameEvent::RabbitHunt,
+        GameEvent::ShowCards,
+        GameEvent::SetAutoRebuy(1000),
+        GameEvent::SitOut(2),
+        GameEvent::ChopBlinds,
+        GameEvent::Muck,
+        GameEvent::ShowOne(1),
+        GameEvent::BuyInsurance(100),
+        GameEvent::Observe,
+        GameEvent::SitIn,
+        GameEvent::PostDeadBlind,
+        GameEvent::AllinProtected,
     ];
     for evt in evts.into_iter() {
         println!("Event: {:?}", evt);
@@ -87,10 +100,12 @@ fn test_borsh_display() {
                 AwardPot {
                     winners: vec![ALICE, BOB],
                     amount: 200,
+                    share: PotShare::High,
                 },
                 AwardPot {
                     winners: vec![BOB],
                     amount: 40,
+                    share: PotShare::Low,
                 },
             ],
         },
@@ -117,6 +132,7 @@ fn test_borsh_display() {
                     },
                 ),
             ]),
+            boards: vec![vec!["ca".to_string(), "dt".to_string(), "c6".to_string()]],
         },
         Display::CollectBets {
             old_pots: vec![],
@@ -2,7 +2,7 @@
 
 //! Helper functions used in tests
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use borsh::BorshSerialize;
 use race_api::prelude::*;
@@ -137,6 +137,7 @@ pub fn setup_holdem_state() -> Result<Holdem> {
         table_size: 7,
         hand_history: HandHistory::default(),
         next_game_start: 0,
+        ..Default::default()
     };
     state.arrange_players(0usize)?;
     Ok(state)
@@ -200,6 +201,54 @@ pub fn setup_context() -> GameContext {
     context
 }
 
+// Seat a player whose position follows the id, i.e. player 1 at 0
+pub fn seat_player(holdem: &mut Holdem, id: u64, chips: u64, status: PlayerStatus) {
+    let player = Player::new_with_timeout_and_status(id, chips, id as usize - 1, status);
+    holdem.player_map.insert(id, player);
+}
+
+// Set up a heads-up hand on the flop where nobody has acted yet
+pub fn setup_heads_up_flop() -> Holdem {
+    let mut holdem = Holdem {
+        sb: 10,
+        bb: 20,
+        min_raise: 20,
+        stage: HoldemStage::Play,
+        street: Street::Flop,
+        player_order: vec![1, 2],
+        ..Default::default()
+    };
+    seat_player(&mut holdem, 1, 1000, PlayerStatus::Wait);
+    seat_player(&mut holdem, 2, 1000, PlayerStatus::Wait);
+    holdem
+}
+
+// Deal two hole cards to each seated player in the order of ids
+pub fn deal_hole_cards(holdem: &mut Holdem) {
+    holdem.hand_index_map = holdem
+        .player_map
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (*id, vec![i * 2, i * 2 + 1]))
+        .collect();
+}
+
+// The decrypted deck, with the cards starting from index 0
+pub fn revealed_deck(cards: &[&str]) -> HashMap<usize, String> {
+    cards
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect()
+}
+
+pub fn custom_event(sender: u64, event: GameEvent) -> Event {
+    Event::Custom {
+        sender,
+        raw: event.try_to_vec().unwrap(),
+    }
+}
+
 // ====================================================
 // Helpers for testing Holdem with the race protocol
 // ====================================================
@@ -468,7 +468,8 @@ fn test_runner() -> Result<()> {
         assert!(state.display.contains(&Display::AwardPots {
             pots: vec![AwardPot {
                 winners: vec![bob.id(), alice.id()],
-                amount: 20000
+                amount: 20000,
+                share: PotShare::Whole,
             }]
         }))
     }
@@ -0,0 +1,1567 @@
+//! Tests of the table rules and options of Holdem.  Most of them start
+//! from a heads-up hand on the flop, see `setup_heads_up_flop` in
+//! helper.rs, and drive it with custom events.
+
+mod helper;
+
+use helper::{custom_event, deal_hole_cards, revealed_deck, seat_player, setup_heads_up_flop};
+use race_api::prelude::{Effect, Event, GameHandler};
+use race_holdem_base::errors;
+use race_holdem_base::essential::{
+    ActingContext, ActingPlayer, ActionTimeouts, AnteType, AwardPot, BettingMode, DeckSpec,
+    Display, GameEvent, GameMode, HoldemStage, InsuranceOffer, Player, PlayerStatus, Pot, PotShare,
+    Street, ACTION_TIMEOUT_POSTFLOP,
+};
+use race_holdem_base::evaluator::Category;
+use race_holdem_base::game::Holdem;
+use race_holdem_base::hand_history::{BlindBet, BlindType, HandHistory, PlayerAction, MASKED_CARD};
+use std::collections::{BTreeMap, HashMap};
+
+// Both players have put `bet` into the single pot and are dealt in
+fn setup_heads_up_showdown(bet: u64, status: PlayerStatus) -> Holdem {
+    let mut holdem = setup_heads_up_flop();
+    holdem.deck_random_id = 1;
+    deal_hole_cards(&mut holdem);
+    holdem.total_bet_map = BTreeMap::from([(1, bet), (2, bet)]);
+    holdem.pots = vec![Pot {
+        owners: vec![1, 2],
+        winners: vec![],
+        amount: bet * 2,
+    }];
+    for p in holdem.player_map.values_mut() {
+        p.status = status;
+        p.chips -= bet;
+    }
+    holdem
+}
+
+// A cash game with 5% rake, both players have bet 200 on the flop
+fn setup_raked_heads_up() -> Holdem {
+    let mut holdem = setup_heads_up_flop();
+    holdem.mode = GameMode::Cash;
+    holdem.rake = 50;
+    holdem.rake_cap_chips = Some(1000);
+    holdem.board = vec!["sa".to_string(), "hk".to_string(), "d2".to_string()];
+    holdem.bet_map = BTreeMap::from([(1, 200), (2, 200)]);
+    holdem.total_bet_map = holdem.bet_map.clone();
+    holdem
+}
+
+// Last hand: BTN at 0, SB at 1, BB at 2
+fn setup_dead_button_table() -> Holdem {
+    let mut holdem = Holdem {
+        sb: 10,
+        bb: 20,
+        btn: 0,
+        dead_button: true,
+        blind_positions: Some((1, 2)),
+        stage: HoldemStage::Play,
+        street: Street::Preflop,
+        ..Default::default()
+    };
+    for id in 1..=4 {
+        seat_player(&mut holdem, id, 1000, PlayerStatus::Wait);
+    }
+    holdem
+}
+
+#[test]
+fn test_min_think_time_holds_early_action() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.min_think_time = 2000;
+    let mut effect = Effect::default();
+    effect.timestamp = 1000;
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    // Check arrives 500ms after asking, it's held until the threshold
+    let mut effect = Effect::default();
+    effect.timestamp = 1500;
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Check))
+        .unwrap();
+    assert_eq!(effect.wait_timeout, Some(1500));
+    assert_eq!(
+        holdem.pending_action,
+        Some(PlayerAction::new(1, GameEvent::Check))
+    );
+    assert_eq!(holdem.player_map[&1].status, PlayerStatus::Acting);
+
+    // Only one action is held, a second one is rejected
+    let mut effect = Effect::default();
+    effect.timestamp = 1800;
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::Fold)),
+        Err(errors::action_already_held())
+    );
+    assert_eq!(
+        holdem.pending_action,
+        Some(PlayerAction::new(1, GameEvent::Check))
+    );
+
+    // The held check is applied at the threshold
+    let mut effect = Effect::default();
+    effect.timestamp = 3000;
+    holdem
+        .handle_event(&mut effect, Event::WaitingTimeout)
+        .unwrap();
+    assert_eq!(holdem.pending_action, None);
+    assert_eq!(holdem.player_map[&1].status, PlayerStatus::Acted);
+    assert!(holdem.is_acting_player(2));
+
+    // Check arrives after the threshold, it's applied immediately
+    let mut effect = Effect::default();
+    effect.timestamp = 5500;
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Check))
+        .unwrap();
+    assert_eq!(holdem.pending_action, None);
+    assert_eq!(holdem.street, Street::Turn);
+}
+
+#[test]
+fn test_min_think_time_rejects_invalid_early_action() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.min_think_time = 2000;
+    let mut effect = Effect::default();
+    effect.timestamp = 1000;
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    // Nothing to raise on the flop yet, rejected as it arrives
+    let mut effect = Effect::default();
+    effect.timestamp = 1500;
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::Raise(40))),
+        Err(errors::player_cant_raise())
+    );
+    assert_eq!(holdem.pending_action, None);
+    assert_eq!(effect.wait_timeout, None);
+    assert_eq!(holdem.player_map[&1].status, PlayerStatus::Acting);
+}
+
+#[test]
+fn test_eject_player_exceeding_sitout_limit() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.mode = GameMode::Cash;
+    holdem.max_sitout_hands = 2;
+    holdem.bet_map = BTreeMap::from([(1, 20), (2, 20)]);
+    holdem.total_bet_map = holdem.bet_map.clone();
+    let sitter = holdem.player_map.get_mut(&1).unwrap();
+    sitter.status = PlayerStatus::Fold;
+    sitter.timeout = 1;
+    sitter.sitout_hands = 2;
+    let mut effect = Effect::default();
+
+    holdem.single_player_win(&mut effect, 2).unwrap();
+
+    assert!(!holdem.player_map.contains_key(&1));
+    assert_eq!(holdem.player_map[&2].sitout_hands, 0);
+    assert_eq!(effect.settles.len(), 1);
+}
+
+#[test]
+fn test_no_flop_no_drop() {
+    let mut holdem = setup_raked_heads_up();
+    holdem.no_flop_no_drop = true;
+    let flop = std::mem::take(&mut holdem.board);
+    holdem.collect_bets().unwrap();
+
+    // The hand ends before the flop is dealt
+    assert_eq!(holdem.take_rake_from_pots(), Ok(0));
+    assert_eq!(holdem.pots[0].amount, 400);
+
+    // A flop hand is raked
+    holdem.board = flop;
+    assert_eq!(holdem.take_rake_from_pots(), Ok(20));
+    assert_eq!(holdem.pots[0].amount, 380);
+}
+
+#[test]
+fn test_rake_exempt_contributions_not_raked() {
+    let mut holdem = setup_raked_heads_up();
+    holdem.collect_bets().unwrap();
+    holdem.set_rake_exempt(1, true).unwrap();
+
+    // Only the half from player 2 is raked
+    assert_eq!(holdem.take_rake_from_pots(), Ok(10));
+    assert_eq!(holdem.pots[0].amount, 390);
+}
+
+#[test]
+fn test_no_showdown_no_rake() {
+    let mut holdem = setup_raked_heads_up();
+    holdem.no_showdown_no_rake = true;
+    holdem.player_map.get_mut(&1).unwrap().status = PlayerStatus::Fold;
+    let mut effect = Effect::default();
+
+    holdem.single_player_win(&mut effect, 2).unwrap();
+
+    // The whole pot goes to the winner
+    assert_eq!(holdem.player_map[&2].chips, 1400);
+}
+
+#[test]
+fn test_deposit_must_be_multiple_of_increment() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.max_deposit = 2000;
+    holdem.deposit_increment = 20;
+
+    assert!(!holdem.is_valid_deposit(1, 150));
+    assert!(holdem.is_valid_deposit(1, 160));
+    assert!(!holdem.is_valid_deposit(3, 160));
+}
+
+#[test]
+fn test_initial_buyin_below_min_deposit() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.min_deposit = 400;
+    holdem.max_deposit = 2000;
+    holdem.player_map.insert(3, Player::init(3, 0, 2));
+
+    assert_eq!(
+        holdem.check_deposit(3, 200),
+        Err(errors::deposit_below_min_buyin())
+    );
+    assert_eq!(holdem.check_deposit(3, 400), Ok(()));
+    assert_eq!(
+        holdem.check_deposit(3, 2020),
+        Err(errors::deposit_exceeds_max_buyin())
+    );
+    // A top-up is not subject to the minimum
+    assert_eq!(holdem.check_deposit(1, 200), Ok(()));
+}
+
+#[test]
+fn test_topup_exceeding_max_deposit() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.min_deposit = 400;
+    holdem.max_deposit = 2000;
+    holdem.player_map.get_mut(&1).unwrap().deposit = 500;
+
+    assert_eq!(holdem.check_deposit(1, 500), Ok(()));
+    assert_eq!(
+        holdem.check_deposit(1, 600),
+        Err(errors::deposit_exceeds_max_buyin())
+    );
+    assert_eq!(
+        holdem.check_deposit(3, 100),
+        Err(errors::deposit_player_not_found())
+    );
+}
+
+#[test]
+fn test_deposit_mid_hand_applies_next_hand() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.max_deposit = 1500;
+    holdem.player_map.get_mut(&1).unwrap().chips = 600;
+    holdem.bet_map = BTreeMap::from([(1, 400)]);
+
+    holdem.add_deposit(1, 900).unwrap();
+    assert_eq!(holdem.player_map[&1].chips, 600);
+    assert_eq!(holdem.bet_map[&1], 400);
+    assert_eq!(
+        holdem.display,
+        vec![Display::DepositPending {
+            player_id: 1,
+            amount: 900
+        }]
+    );
+
+    // Won the pot, the deposit is applied up to the max, the rest
+    // is kept for later
+    holdem.player_map.get_mut(&1).unwrap().chips = 1400;
+    let mut effect = Effect::default();
+    holdem.internal_start_game(&mut effect).unwrap();
+    assert_eq!(holdem.player_map[&1].chips, 1500);
+    assert_eq!(holdem.player_map[&1].deposit, 800);
+}
+
+#[test]
+fn test_ante_capped_by_stack_ratio() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.ante = 50;
+    holdem.max_ante_ratio = 100;
+    holdem.player_map.get_mut(&2).unwrap().chips = 30;
+
+    let antes = holdem.ante_bets().unwrap();
+
+    assert_eq!(antes[1], BlindBet::new(2, BlindType::Ante, 3));
+    assert_eq!(holdem.player_map[&2].chips, 27);
+    assert_eq!(holdem.player_map[&2].status, PlayerStatus::Wait);
+    // The ante not covered by the micro-stack is returned
+    assert_eq!(holdem.player_map[&1].chips, 997);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 6);
+    assert!(holdem.bet_map.is_empty());
+}
+
+#[test]
+fn test_bb_ante_in_headsup() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.ante = 20;
+    holdem.ante_type = AnteType::BigBlind;
+    // Heads-up: the BB is the first in order
+    holdem.player_order = vec![2, 1];
+
+    let antes = holdem.ante_bets().unwrap();
+
+    assert_eq!(antes, vec![BlindBet::new(2, BlindType::Ante, 20)]);
+    assert_eq!(holdem.player_map[&1].chips, 1000);
+    assert_eq!(holdem.player_map[&2].chips, 980);
+    assert_eq!(holdem.pots.len(), 1);
+    assert_eq!(holdem.pots[0].owners, vec![2, 1]);
+    assert_eq!(holdem.pots[0].amount, 20);
+    assert!(holdem.bet_map.is_empty());
+}
+
+#[test]
+fn test_single_ante_in_full_ring() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.ante = 50;
+    for id in 3..=6 {
+        seat_player(&mut holdem, id, 1000, PlayerStatus::Wait);
+    }
+    // SB, BB, ..., BTN
+    holdem.player_order = vec![3, 4, 5, 6, 1, 2];
+
+    holdem.ante_type = AnteType::BigBlind;
+    let antes = holdem.ante_bets().unwrap();
+    assert_eq!(antes, vec![BlindBet::new(4, BlindType::Ante, 50)]);
+    assert_eq!(holdem.player_map[&4].chips, 950);
+    assert_eq!(holdem.pots.len(), 1);
+    assert_eq!(holdem.pots[0].owners.len(), 6);
+
+    holdem.pots.clear();
+    holdem.ante_type = AnteType::Button;
+    let antes = holdem.ante_bets().unwrap();
+    assert_eq!(antes, vec![BlindBet::new(2, BlindType::Ante, 50)]);
+    assert_eq!(holdem.player_map[&2].chips, 950);
+    assert_eq!(holdem.pots.len(), 1);
+    assert_eq!(holdem.pots[0].amount, 50);
+}
+
+#[test]
+fn test_current_hand_actions_in_order() {
+    let mut holdem = setup_heads_up_flop();
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(40)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Raise(100)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Call))
+        .unwrap();
+
+    let actions: Vec<_> = holdem.hand_history.current_hand_actions().collect();
+    assert_eq!(
+        actions,
+        vec![
+            (Street::Flop, &PlayerAction::new(1, GameEvent::Bet(40))),
+            (Street::Flop, &PlayerAction::new(2, GameEvent::Raise(100))),
+            (Street::Flop, &PlayerAction::new(1, GameEvent::Call)),
+        ]
+    );
+    assert_eq!(holdem.street, Street::Turn);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 200);
+}
+
+#[test]
+fn test_show_order_starts_from_last_aggressor() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.btn = 3;
+    for p in holdem.player_map.values_mut() {
+        p.status = PlayerStatus::Acted;
+    }
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Acted);
+    seat_player(&mut holdem, 4, 1000, PlayerStatus::Fold);
+    deal_hole_cards(&mut holdem);
+    let actions = [
+        (Street::Turn, PlayerAction::new(3, GameEvent::Bet(50))),
+        (Street::River, PlayerAction::new(1, GameEvent::Check)),
+        (Street::River, PlayerAction::new(2, GameEvent::Bet(100))),
+        (Street::River, PlayerAction::new(3, GameEvent::Call)),
+        (Street::River, PlayerAction::new(4, GameEvent::Fold)),
+        (Street::River, PlayerAction::new(1, GameEvent::Raise(300))),
+        (Street::River, PlayerAction::new(2, GameEvent::Call)),
+        (Street::River, PlayerAction::new(3, GameEvent::Call)),
+    ];
+    for (street, action) in actions {
+        holdem.hand_history.add_action(street, action).unwrap();
+    }
+
+    assert_eq!(holdem.hand_history.last_aggressor(), Some(1));
+    assert_eq!(holdem.show_order(), vec![1, 2, 3]);
+
+    // Player 2 raised last, the folded player never shows
+    let raise = PlayerAction::new(2, GameEvent::Raise(900));
+    holdem
+        .hand_history
+        .add_action(Street::River, raise)
+        .unwrap();
+    assert_eq!(holdem.show_order(), vec![2, 3, 1]);
+}
+
+#[test]
+fn test_show_order_from_btn_when_checked_through() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.btn = 0;
+    deal_hole_cards(&mut holdem);
+    // The turn bet doesn't count on a checked-through river
+    let actions = [
+        (Street::Turn, PlayerAction::new(1, GameEvent::Bet(50))),
+        (Street::River, PlayerAction::new(2, GameEvent::Check)),
+        (Street::River, PlayerAction::new(1, GameEvent::Check)),
+    ];
+    for (street, action) in actions {
+        holdem.hand_history.add_action(street, action).unwrap();
+    }
+
+    assert_eq!(holdem.hand_history.last_aggressor(), None);
+    assert_eq!(holdem.show_order(), vec![2, 1]);
+}
+
+#[test]
+fn test_headsup_btn_posts_both_blinds() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.headsup_btn_posts_blinds = true;
+    // Heads-up: the BB is the first in order, the button is the last
+    holdem.player_order = vec![2, 1];
+    let mut effect = Effect::default();
+
+    holdem.blind_bets(&mut effect).unwrap();
+
+    assert_eq!(holdem.player_map[&1].chips, 970);
+    assert_eq!(holdem.player_map[&2].chips, 1000);
+    assert_eq!(holdem.street_bet, 30);
+    assert_eq!(holdem.acting_player.as_ref().map(|p| p.id), Some(2));
+}
+
+#[test]
+fn test_emit_equities_of_allin_confrontation() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.deck_random_id = 1;
+    deal_hole_cards(&mut holdem);
+    for p in holdem.player_map.values_mut() {
+        p.status = PlayerStatus::Allin;
+    }
+    let mut effect = Effect::default();
+    effect
+        .revealed
+        .insert(1, revealed_deck(&["sa", "ha", "sk", "hk"]));
+
+    holdem.emit_equities(&effect).unwrap();
+
+    let Some(Display::Equities { equities }) = holdem.display.last() else {
+        panic!("Equities not emitted");
+    };
+    assert!((equities[&1] + equities[&2] - 1.0).abs() < 1e-9);
+    assert!((equities[&1] - 0.82).abs() < 0.03);
+}
+
+#[test]
+fn test_run_it_twice_splits_pot_by_board() {
+    let mut holdem = setup_heads_up_showdown(1000, PlayerStatus::Allin);
+    holdem.run_it_twice = true;
+    holdem.stage = HoldemStage::Runner;
+    holdem.street = Street::Showdown;
+    holdem.board = vec!["s2".to_string(), "d7".to_string(), "c9".to_string()];
+    holdem.pots[0].amount = 2001;
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::AgreeRunItTwice))
+        .unwrap();
+    assert_eq!(holdem.runouts, 0);
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::AgreeRunItTwice))
+        .unwrap();
+    assert_eq!(holdem.runouts, 2);
+
+    // AA holds on the first board, KK hits a set on the second one
+    let revealed = revealed_deck(&[
+        "sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3", "dk", "c4",
+    ]);
+    effect.revealed.insert(1, revealed);
+    holdem.update_board(&mut effect).unwrap();
+    holdem.settle(&mut effect).unwrap();
+
+    assert_eq!(holdem.board, vec!["s2", "d7", "c9", "dj", "c3"]);
+    assert_eq!(holdem.second_board, vec!["s2", "d7", "c9", "dk", "c4"]);
+    // The odd chip goes with the first board
+    assert_eq!(holdem.player_map[&1].chips, 1001);
+    assert_eq!(holdem.player_map[&2].chips, 1000);
+    let Some(Display::GameResult { boards, .. }) = holdem
+        .display
+        .iter()
+        .find(|d| matches!(d, Display::GameResult { .. }))
+    else {
+        panic!("GameResult display is missing");
+    };
+    assert_eq!(boards.len(), 2);
+}
+
+#[test]
+fn test_run_it_once_without_agreement() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.run_it_twice = true;
+    holdem.stage = HoldemStage::Runner;
+    holdem.street = Street::Showdown;
+    deal_hole_cards(&mut holdem);
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::AgreeRunItTwice))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, Event::WaitingTimeout)
+        .unwrap();
+    assert_eq!(holdem.runouts, 1);
+    assert!(holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::AgreeRunItTwice))
+        .is_err());
+}
+
+#[test]
+fn test_configured_action_timeouts() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.action_timeouts = Some(ActionTimeouts {
+        preflop: 8000,
+        postflop: 8000,
+        turn: 8000,
+        river: 8000,
+        afk: 3000,
+    });
+    let mut effect = Effect::default();
+    effect.timestamp = 1000;
+
+    holdem.ask_for_action(1, &mut effect).unwrap();
+    assert_eq!(holdem.acting_player.as_ref().map(|p| p.clock), Some(9000));
+
+    // A player who timed out last time gets the AFK timeout
+    holdem.player_map.get_mut(&2).unwrap().timeout = 1;
+    holdem.ask_for_action(2, &mut effect).unwrap();
+    assert_eq!(holdem.acting_player.as_ref().map(|p| p.clock), Some(4000));
+}
+
+#[test]
+fn test_pot_limit_caps_bet_and_raise() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.betting_mode = BettingMode::PotLimit;
+    holdem.pots = vec![Pot {
+        owners: vec![1, 2],
+        winners: vec![],
+        amount: 100,
+    }];
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::Bet(101))),
+        Err(errors::raise_exceeds_pot_limit())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(100)))
+        .unwrap();
+
+    // Call 100, then raise the pot of 300 after calling
+    assert_eq!(holdem.pot_limit_max(2), 400);
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(2, GameEvent::Raise(401))),
+        Err(errors::raise_exceeds_pot_limit())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Raise(400)))
+        .unwrap();
+    assert_eq!(holdem.street_bet, 400);
+}
+
+#[test]
+fn test_fixed_limit_caps_raises_per_street() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.betting_mode = BettingMode::FixedLimit;
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    // Any amount is turned into the small bet on flop
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(500)))
+        .unwrap();
+    assert_eq!(holdem.street_bet, 20);
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Raise(500)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Raise(500)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Raise(500)))
+        .unwrap();
+    assert_eq!(holdem.street_bet, 80);
+    assert_eq!(holdem.street_raises, 3);
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::Raise(20))),
+        Err(errors::betting_capped())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Call))
+        .unwrap();
+    assert_eq!(holdem.player_map[&1].chips, 920);
+    assert_eq!(holdem.street, Street::Turn);
+    assert_eq!(holdem.street_raises, 0);
+    assert_eq!(holdem.fixed_limit_bet_size(), 40);
+}
+
+#[test]
+fn test_max_raises_per_street_in_no_limit() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.max_raises_per_street = Some(2);
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(20)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Raise(40)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Raise(80)))
+        .unwrap();
+    assert_eq!(holdem.street_raises, 2);
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(2, GameEvent::Raise(200))),
+        Err(errors::max_raises_per_street_reached())
+    );
+
+    let mut called = holdem.clone();
+    called
+        .handle_event(&mut effect, custom_event(2, GameEvent::Call))
+        .unwrap();
+    assert_eq!(called.street, Street::Turn);
+    assert_eq!(called.street_raises, 0);
+
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Fold))
+        .unwrap();
+    assert_eq!(holdem.player_map[&2].status, PlayerStatus::Fold);
+}
+
+#[test]
+fn test_allin_protection_on_turn_timeout() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.allin_protection = true;
+    holdem.street = Street::Turn;
+    holdem.total_bet_map = BTreeMap::from([(1, 20), (2, 20)]);
+    holdem.pots = vec![Pot {
+        owners: vec![1, 2],
+        winners: vec![],
+        amount: 40,
+    }];
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(100)))
+        .unwrap();
+
+    // Player 2 is disconnected and times out facing the bet
+    let mut protected = holdem.clone();
+    protected
+        .handle_event(&mut effect, Event::ActionTimeout { player_id: 2 })
+        .unwrap();
+    assert_eq!(protected.player_map[&2].status, PlayerStatus::Allin);
+    assert!(protected.player_map[&2].protection_used);
+    assert_eq!(protected.stage, HoldemStage::Runner);
+    // The uncalled bet is returned
+    assert_eq!(protected.player_map[&1].chips, 1000);
+    assert_eq!(protected.pots.len(), 1);
+    assert_eq!(protected.pots[0].amount, 40);
+
+    // Protection is used up in this session
+    holdem.player_map.get_mut(&2).unwrap().protection_used = true;
+    holdem
+        .handle_event(&mut effect, Event::ActionTimeout { player_id: 2 })
+        .unwrap();
+    assert_eq!(holdem.player_map[&2].status, PlayerStatus::Fold);
+}
+
+#[test]
+fn test_auto_rebuy_tops_up_before_next_hand() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.max_deposit = 2000;
+    let mut effect = Effect::default();
+
+    // Set in the middle of a hand, the stack is untouched
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::SetAutoRebuy(1000)))
+        .unwrap();
+    assert_eq!(holdem.player_map[&1].auto_rebuy_to, Some(1000));
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(2, GameEvent::SetAutoRebuy(3000))),
+        Err(errors::auto_rebuy_not_available())
+    );
+
+    // Player 1 lost 700 chips, both have pending deposits
+    for (id, chips) in [(1, 300), (2, 1700)] {
+        let player = holdem.player_map.get_mut(&id).unwrap();
+        player.chips = chips;
+        player.deposit = 1000;
+    }
+    holdem.fill_player_chips_with_deposits();
+
+    assert_eq!(holdem.player_map[&1].chips, 1000);
+    assert_eq!(holdem.player_map[&1].deposit, 300);
+    assert_eq!(holdem.player_map[&2].chips, 2000);
+    assert_eq!(holdem.player_map[&2].deposit, 700);
+}
+
+#[test]
+fn test_sit_out_two_hands_then_return() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+    let mut effect = Effect::default();
+    holdem
+        .handle_event(&mut effect, custom_event(3, GameEvent::SitOut(2)))
+        .unwrap();
+    // Not until the next hand
+    assert_eq!(holdem.player_map[&3].status, PlayerStatus::Wait);
+
+    for _ in 0..2 {
+        let mut effect = Effect::default();
+        holdem.internal_start_game(&mut effect).unwrap();
+        holdem.deal_cards(&mut effect).unwrap();
+        holdem.arrange_players(holdem.btn).unwrap();
+        assert_eq!(holdem.player_map[&3].status, PlayerStatus::SitOut);
+        assert_eq!(holdem.player_map[&3].chips, 1000);
+        assert!(!holdem.hand_index_map.contains_key(&3));
+        assert!(!holdem.player_order.contains(&3));
+    }
+
+    let mut effect = Effect::default();
+    holdem.internal_start_game(&mut effect).unwrap();
+    holdem.deal_cards(&mut effect).unwrap();
+    assert_eq!(holdem.player_map[&3].status, PlayerStatus::Wait);
+    assert!(holdem.hand_index_map.contains_key(&3));
+}
+
+#[test]
+fn test_dead_button_when_sb_leaves() {
+    let mut holdem = setup_dead_button_table();
+    holdem.player_map.remove(&2);
+    let mut effect = Effect::default();
+
+    // The button is dead at the seat of last SB
+    holdem.btn = holdem.get_next_btn().unwrap();
+    assert_eq!(holdem.btn, 1);
+    holdem.arrange_players(holdem.btn).unwrap();
+    holdem.blind_bets(&mut effect).unwrap();
+
+    // Last BB posts SB, the next player posts BB
+    assert_eq!(holdem.bet_map, BTreeMap::from([(3, 10), (4, 20)]));
+    assert_eq!(holdem.blind_positions, Some((2, 3)));
+    assert!(holdem.is_acting_player(1));
+}
+
+#[test]
+fn test_dead_small_blind_when_bb_leaves() {
+    let mut holdem = setup_dead_button_table();
+    holdem.player_map.remove(&3);
+    let mut effect = Effect::default();
+
+    holdem.btn = holdem.get_next_btn().unwrap();
+    assert_eq!(holdem.btn, 1);
+    holdem.arrange_players(holdem.btn).unwrap();
+    holdem.blind_bets(&mut effect).unwrap();
+
+    // Nobody posts SB, the BB moves to the next player
+    assert_eq!(holdem.bet_map, BTreeMap::from([(4, 20)]));
+    assert_eq!(holdem.blind_positions, Some((2, 3)));
+    assert!(holdem.is_acting_player(1));
+}
+
+#[test]
+fn test_chop_blinds_in_heads_up() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.stage = HoldemStage::Init;
+    let mut effect = Effect::default();
+    holdem.arrange_players(holdem.btn).unwrap();
+    holdem.blind_bets(&mut effect).unwrap();
+    holdem.stage = HoldemStage::Play;
+    assert_eq!(
+        holdem.player_map[&1].chips + holdem.player_map[&2].chips,
+        1970
+    );
+
+    let mut raised = holdem.clone();
+    let sb_id = *raised.player_order.first().unwrap();
+    raised
+        .handle_event(&mut effect, custom_event(sb_id, GameEvent::Raise(60)))
+        .unwrap();
+    assert_eq!(
+        raised.handle_event(&mut effect, custom_event(1, GameEvent::ChopBlinds)),
+        Err(errors::chop_blinds_not_available())
+    );
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::ChopBlinds))
+        .unwrap();
+    assert_eq!(holdem.stage, HoldemStage::Play);
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::ChopBlinds))
+        .unwrap();
+
+    assert_eq!(holdem.stage, HoldemStage::Settle);
+    assert_eq!(holdem.player_map[&1].chips, 1000);
+    assert_eq!(holdem.player_map[&2].chips, 1000);
+    assert!(holdem.display.contains(&Display::ChopBlinds));
+    assert!(holdem.winners.is_empty());
+}
+
+#[test]
+fn test_rabbit_hunt_after_hand_ended_on_flop() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.stage = HoldemStage::Settle;
+    holdem.deck_random_id = 1;
+    holdem.board = vec!["s2".to_string(), "d7".to_string(), "c9".to_string()];
+    deal_hole_cards(&mut holdem);
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::RabbitHunt))
+        .unwrap();
+    assert!(holdem.rabbit_hunt);
+
+    let revealed = revealed_deck(&["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]);
+    effect.revealed.insert(1, revealed);
+    holdem.show_rabbit_hunt(&effect).unwrap();
+
+    assert_eq!(
+        holdem.display,
+        vec![Display::RabbitHunt {
+            board: vec!["s2", "d7", "c9", "dj", "c3"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }]
+    );
+    // The real board is untouched
+    assert_eq!(holdem.board.len(), 3);
+}
+
+#[test]
+fn test_forced_show_of_mucked_cards() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Fold);
+    seat_player(&mut holdem, 4, 1000, PlayerStatus::Fold);
+    deal_hole_cards(&mut holdem);
+    holdem.hand_history.preflop.actions = vec![PlayerAction::new(4, GameEvent::Fold)];
+    holdem.hand_history.flop.actions = vec![PlayerAction::new(3, GameEvent::Fold)];
+    // Player 3 was forced to show, the others are not revealed
+    let decryption = HashMap::from([(4, "sa".to_string()), (5, "ha".to_string())]);
+
+    holdem.record_mucked(&decryption);
+
+    assert_eq!(holdem.hand_history.mucked, vec![3]);
+    let muck = vec!["sa".to_string(), "ha".to_string()];
+    assert_eq!(
+        holdem.hand_history.revealed_muck,
+        Some(BTreeMap::from([(3, muck)]))
+    );
+}
+
+#[test]
+fn test_voluntary_show_by_uncontested_winner() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.stage = HoldemStage::Settle;
+    holdem.deck_random_id = 1;
+    holdem.board = ["s2", "d7", "c9"].map(String::from).to_vec();
+    deal_hole_cards(&mut holdem);
+    holdem.winners = vec![1];
+    let mut effect = Effect::default();
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(2, GameEvent::ShowCards)),
+        Err(errors::show_cards_not_available())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::ShowCards))
+        .unwrap();
+    assert_eq!(holdem.show_cards, Some(1));
+
+    effect.revealed.insert(1, revealed_deck(&["sa", "ha"]));
+    holdem.add_voluntary_showdown(&effect).unwrap();
+
+    let showdown = &holdem.hand_history.showdowns[&1];
+    assert!(showdown.voluntary);
+    assert_eq!(showdown.category, Category::Pair);
+    assert_eq!(showdown.hole_cards, vec!["sa", "ha"]);
+    assert_eq!(holdem.show_cards, None);
+}
+
+#[test]
+fn test_show_one_card_by_uncontested_winner() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.stage = HoldemStage::Settle;
+    holdem.deck_random_id = 1;
+    deal_hole_cards(&mut holdem);
+    holdem.winners = vec![1];
+    let mut effect = Effect::default();
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::ShowOne(2))),
+        Err(errors::show_cards_not_available())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::ShowOne(1)))
+        .unwrap();
+    let revealed: Vec<usize> = effect
+        .reveals
+        .iter()
+        .flat_map(|r| r.indexes.iter().copied())
+        .collect();
+    assert_eq!(revealed, vec![1]);
+
+    let revealed = HashMap::from([(1, "ha".to_string())]);
+    effect.revealed.insert(1, revealed);
+    holdem.add_voluntary_showdown(&effect).unwrap();
+
+    let showdown = &holdem.hand_history.showdowns[&1];
+    assert!(showdown.voluntary);
+    assert_eq!(showdown.hole_cards, vec![MASKED_CARD, "ha"]);
+    assert_eq!(holdem.show_card_index, None);
+}
+
+#[test]
+fn test_rabbit_hunt_rejected_with_complete_board() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.stage = HoldemStage::Settle;
+    holdem.deck_random_id = 1;
+    holdem.board = ["s2", "d7", "c9", "dj", "c3"].map(String::from).to_vec();
+    deal_hole_cards(&mut holdem);
+    let mut effect = Effect::default();
+
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::RabbitHunt)),
+        Err(errors::rabbit_hunt_not_available())
+    );
+
+    // No randomness
+    holdem.board.truncate(3);
+    holdem.deck_random_id = 0;
+    assert_eq!(
+        holdem.handle_event(&mut effect, custom_event(1, GameEvent::RabbitHunt)),
+        Err(errors::rabbit_hunt_not_available())
+    );
+}
+
+#[test]
+fn test_non_posting_player_is_dealt_out() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.sb = 0;
+    holdem.bb = 0;
+    holdem.ante = 10;
+    holdem.dealt_in_when_posted = true;
+    holdem.deck_random_id = 1;
+    seat_player(&mut holdem, 3, 5, PlayerStatus::Wait);
+    let mut effect = Effect::default();
+
+    holdem.deal_cards(&mut effect).unwrap();
+    assert_eq!(holdem.player_map[&3].status, PlayerStatus::Init);
+    assert_eq!(
+        holdem.hand_index_map,
+        BTreeMap::from([(1, vec![0, 1]), (2, vec![2, 3])])
+    );
+
+    holdem.arrange_players(0).unwrap();
+    holdem.ante_bets().unwrap();
+    assert_eq!(holdem.player_map[&3].chips, 5);
+    assert_eq!(holdem.pots.len(), 1);
+    assert_eq!(holdem.pots[0].owners, vec![1, 2]);
+    assert_eq!(holdem.pots[0].amount, 20);
+}
+
+#[test]
+fn test_award_pots_separates_side_pots_with_different_winners() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.pots = vec![
+        Pot {
+            owners: vec![1, 2, 3],
+            winners: vec![],
+            amount: 300,
+        },
+        Pot {
+            owners: vec![2, 3],
+            winners: vec![],
+            amount: 400,
+        },
+    ];
+
+    // Player 1 has the best hand but is only eligible for the main pot
+    holdem
+        .assign_winners(vec![vec![1], vec![3], vec![2]])
+        .unwrap();
+
+    let expected = vec![
+        AwardPot {
+            winners: vec![1],
+            amount: 300,
+            share: PotShare::Whole,
+        },
+        AwardPot {
+            winners: vec![3],
+            amount: 400,
+            share: PotShare::Whole,
+        },
+    ];
+    assert_eq!(holdem.award_pots(), expected);
+    assert_eq!(
+        holdem.display.last(),
+        Some(&Display::AwardPots { pots: expected })
+    );
+}
+
+#[test]
+fn test_integrity_flag_on_repeated_folds_to_one_opponent() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.integrity_threshold = 3;
+    holdem.player_map.get_mut(&1).unwrap().status = PlayerStatus::Fold;
+    let is_flagged = |holdem: &Holdem| {
+        holdem
+            .display
+            .iter()
+            .any(|d| matches!(d, Display::IntegrityFlag { .. }))
+    };
+
+    // Player 1 bets and folds to player 2's raise in each hand
+    for hand in 1..=3 {
+        holdem.display.clear();
+        holdem.hand_history = HandHistory::default();
+        holdem.hand_history.flop.actions = vec![
+            PlayerAction::new(1, GameEvent::Bet(100)),
+            PlayerAction::new(2, GameEvent::Raise(300)),
+            PlayerAction::new(1, GameEvent::Fold),
+        ];
+        holdem.total_bet_map = BTreeMap::from([(1, 100), (2, 100)]);
+        holdem.prize_map = BTreeMap::from([(2, 200)]);
+        holdem.update_chips_map().unwrap();
+        assert_eq!(is_flagged(&holdem), hand == 3);
+    }
+    assert_eq!(
+        holdem.display.last(),
+        Some(&Display::IntegrityFlag {
+            player_id: 1,
+            opponent_id: 2,
+            count: 3,
+        })
+    );
+}
+
+#[test]
+fn test_kill_blind_after_two_scooped_pots() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.kill_game = true;
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+    holdem.last_winners = vec![2, 3];
+    assert_eq!(holdem.find_killer(), None);
+    holdem.last_winners = vec![3, 3];
+    holdem.killer = holdem.find_killer();
+    assert_eq!(holdem.killer, Some(3));
+    // SB, BB, BTN
+    holdem.player_order = vec![1, 2, 3];
+    let mut effect = Effect::default();
+
+    holdem.blind_bets(&mut effect).unwrap();
+
+    assert_eq!(holdem.bet_map[&3], 40);
+    assert_eq!(holdem.street_bet, 40);
+    assert_eq!(holdem.min_raise, 40);
+    assert!(holdem
+        .hand_history
+        .blinds
+        .contains(&BlindBet::new(3, BlindType::Kill, 40)));
+}
+
+#[test]
+fn test_short_killer_goes_allin_for_kill() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.kill_game = true;
+    holdem.kill_threshold = 500;
+    holdem.last_winners = vec![1];
+    holdem.last_pot = 600;
+    holdem.player_map.get_mut(&1).unwrap().chips = 30;
+    holdem.killer = holdem.find_killer();
+    assert_eq!(holdem.killer, Some(1));
+    // Heads-up: the BB is the first in order
+    holdem.player_order = vec![2, 1];
+    let mut effect = Effect::default();
+
+    holdem.blind_bets(&mut effect).unwrap();
+
+    // The SB of 10 counts towards the kill
+    assert_eq!(holdem.bet_map[&1], 30);
+    assert_eq!(holdem.player_map[&1].status, PlayerStatus::Allin);
+    assert!(holdem
+        .hand_history
+        .blinds
+        .contains(&BlindBet::new(1, BlindType::Kill, 20)));
+    assert_eq!(holdem.street_bet, 30);
+}
+
+#[test]
+fn test_deal_it_out_when_blinds_put_all_players_allin() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.player_map.get_mut(&1).unwrap().chips = 10;
+    holdem.player_map.get_mut(&2).unwrap().chips = 20;
+    // Heads-up: the BB is the first in order
+    holdem.player_order = vec![2, 1];
+    let mut effect = Effect::default();
+
+    holdem.blind_bets(&mut effect).unwrap();
+    assert_eq!(holdem.stage, HoldemStage::Runner);
+    assert_eq!(holdem.street, Street::Showdown);
+    assert_eq!(holdem.acting_player, None);
+}
+
+#[test]
+fn test_deal_it_out_when_short_sb_allin_and_bb_covers() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Preflop;
+    holdem.player_map.get_mut(&1).unwrap().chips = 5;
+    holdem.player_order = vec![2, 1];
+    let mut effect = Effect::default();
+
+    // BB has nothing to call, thus no action is asked
+    holdem.blind_bets(&mut effect).unwrap();
+    assert_eq!(holdem.stage, HoldemStage::Runner);
+    assert_eq!(holdem.acting_player, None);
+    assert_eq!(holdem.player_map[&2].status, PlayerStatus::Wait);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 25);
+}
+
+#[test]
+fn test_mucked_loser_cards_never_revealed() {
+    let mut holdem = setup_heads_up_showdown(100, PlayerStatus::Acted);
+    holdem.street = Street::River;
+    holdem.board = ["s2", "d7", "c9", "dj", "c3"].map(String::from).to_vec();
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Muck))
+        .unwrap();
+    holdem.next_state(&mut effect).unwrap();
+
+    assert_eq!(holdem.stage, HoldemStage::Showdown);
+    let revealed: Vec<usize> = effect
+        .reveals
+        .iter()
+        .flat_map(|r| r.indexes.iter().copied())
+        .collect();
+    assert_eq!(revealed, vec![0, 1]);
+
+    // Only the hole cards of player 1 are decrypted
+    effect.revealed.insert(1, revealed_deck(&["sa", "ha"]));
+    holdem.settle(&mut effect).unwrap();
+
+    assert_eq!(holdem.player_map[&1].chips, 1100);
+    assert_eq!(holdem.player_map[&2].chips, 900);
+    assert!(holdem.hand_history.showdowns.contains_key(&1));
+    assert!(!holdem.hand_history.showdowns.contains_key(&2));
+    assert_eq!(holdem.hand_history.mucked, vec![2]);
+    assert_eq!(holdem.hand_history.revealed_muck, None);
+    // Too late to muck after the showdown
+    assert_eq!(holdem.muck(1), Err(errors::muck_not_available()));
+}
+
+#[test]
+fn test_insurance_premium_paid_by_winner() {
+    let mut holdem = setup_heads_up_showdown(1000, PlayerStatus::Allin);
+    holdem.insurance = true;
+    holdem.insurance_pool = 10000;
+    holdem.stage = HoldemStage::Runner;
+    holdem.street = Street::Showdown;
+    holdem.board = ["s2", "d7", "c9", "dj"].map(String::from).to_vec();
+    let mut effect = Effect::default();
+    let revealed = revealed_deck(&["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]);
+    effect.revealed.insert(1, revealed);
+
+    // AA against the two kings left
+    holdem.offer_insurance(&effect).unwrap();
+    let offer = InsuranceOffer {
+        player_id: 1,
+        outs: 2,
+        cards_left: 44,
+        max_premium: 90,
+    };
+    assert_eq!(holdem.insurance_offer, Some(offer));
+
+    assert_eq!(
+        holdem.buy_insurance(&mut effect, 2, 40),
+        Err(errors::insurance_not_available())
+    );
+    assert_eq!(
+        holdem.buy_insurance(&mut effect, 1, 100),
+        Err(errors::insurance_premium_too_large())
+    );
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::BuyInsurance(40)))
+        .unwrap();
+    assert_eq!(holdem.runouts, 1);
+
+    holdem.update_board(&mut effect).unwrap();
+    holdem.settle(&mut effect).unwrap();
+    assert_eq!(holdem.player_map[&1].chips, 1960);
+    assert_eq!(holdem.player_map[&2].chips, 0);
+    assert_eq!(holdem.insurance_pool, 10040);
+}
+
+#[test]
+fn test_heads_up_btn_is_sb_with_player_sitting_out() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+
+    for hand in 0..5 {
+        // Player 3 sits out in the second and third hands
+        if hand == 1 {
+            holdem.sit_out(3, 2).unwrap();
+        }
+        let mut effect = Effect::default();
+        holdem.internal_start_game(&mut effect).unwrap();
+        holdem.stage = HoldemStage::Play;
+        holdem.street = Street::Preflop;
+        holdem.arrange_players(holdem.btn).unwrap();
+        holdem.blind_bets(&mut effect).unwrap();
+
+        let heads_up = holdem.player_order.len() == 2;
+        assert_eq!(heads_up, hand == 1 || hand == 2);
+        if heads_up {
+            let btn_id = holdem
+                .player_map
+                .values()
+                .find(|p| p.position == holdem.btn)
+                .map(|p| p.id)
+                .unwrap();
+            assert_ne!(btn_id, 3);
+            assert_eq!(holdem.bet_map[&btn_id], 10);
+            assert!(holdem.is_acting_player(btn_id));
+        }
+    }
+}
+
+#[test]
+fn test_observer_is_not_dealt_in() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.stage = HoldemStage::Settle;
+    holdem.player_map.insert(3, Player::init(3, 0, 2));
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(3, GameEvent::Observe))
+        .unwrap();
+    holdem.internal_start_game(&mut effect).unwrap();
+    holdem.deal_cards(&mut effect).unwrap();
+    holdem.arrange_players(holdem.btn).unwrap();
+
+    assert_eq!(holdem.player_map[&3].status, PlayerStatus::Observer);
+    assert!(!holdem.hand_index_map.contains_key(&3));
+    assert!(!holdem.player_order.contains(&3));
+    assert_eq!(holdem.count_ingame_players(), 2);
+
+    // Sitting in from the next hand
+    holdem
+        .handle_event(&mut effect, custom_event(3, GameEvent::SitIn))
+        .unwrap();
+    holdem.player_map.get_mut(&3).unwrap().chips = 1000;
+    holdem.internal_start_game(&mut effect).unwrap();
+    holdem.arrange_players(holdem.btn).unwrap();
+    assert!(holdem.player_order.contains(&3));
+}
+
+#[test]
+fn test_bad_beat_drop_and_detection() {
+    let mut holdem = setup_heads_up_showdown(100, PlayerStatus::Acted);
+    holdem.bad_beat_drop = 5;
+    holdem.stage = HoldemStage::Showdown;
+    holdem.street = Street::Showdown;
+    holdem.board = ["s9", "st", "sj", "h9", "d9"].map(String::from).to_vec();
+    let mut effect = Effect::default();
+    // Quads of nines lose to a straight flush
+    effect
+        .revealed
+        .insert(1, revealed_deck(&["c9", "ca", "sq", "sk"]));
+
+    holdem.settle(&mut effect).unwrap();
+
+    assert!(holdem.display.contains(&Display::BadBeat {
+        winner: 2,
+        loser: 1
+    }));
+    assert_eq!(holdem.bad_beat_collected, 5);
+    assert_eq!(holdem.player_map[&2].chips, 1095);
+
+    // No drop without a flop
+    holdem.board.clear();
+    holdem.pots = vec![Pot {
+        owners: vec![1, 2],
+        winners: vec![],
+        amount: 40,
+    }];
+    assert_eq!(holdem.take_bad_beat_drop(), 0);
+    assert_eq!(holdem.bad_beat_collected, 5);
+}
+
+#[test]
+fn test_acting_player_context_mid_street() {
+    let mut holdem = setup_heads_up_flop();
+    assert_eq!(holdem.acting_player_context(), None);
+
+    holdem.acting_player = Some(ActingPlayer {
+        id: 2,
+        position: 1,
+        clock: 0,
+    });
+    holdem.pots = vec![Pot {
+        owners: vec![1, 2],
+        winners: vec![],
+        amount: 100,
+    }];
+    holdem.bet_map.insert(1, 40);
+    holdem.street_bet = 40;
+    holdem.min_raise = 40;
+
+    assert_eq!(
+        holdem.acting_player_context(),
+        Some(ActingContext {
+            player_id: 2,
+            to_call: 40,
+            pot: 140,
+            min_raise: 80,
+            max_raise: 1000,
+        })
+    );
+}
+
+#[test]
+fn test_bomb_pot_deals_flop_with_antes() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+    holdem.bomb_pot_interval = 2;
+    holdem.bomb_pot_ante = 50;
+    holdem.hand_count = 1;
+    let mut effect = Effect::default();
+
+    holdem.internal_start_game(&mut effect).unwrap();
+    holdem.deal_cards(&mut effect).unwrap();
+    holdem
+        .handle_event(&mut effect, Event::SecretsReady { random_ids: vec![] })
+        .unwrap();
+
+    assert!(holdem.bomb_pot);
+    assert_eq!(holdem.street, Street::Flop);
+    assert_eq!(holdem.stage, HoldemStage::ShareKey);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 150);
+    assert!(holdem.bet_map.is_empty());
+    assert!(holdem.player_map.values().all(|p| p.chips == 950));
+    assert_eq!(
+        holdem.hand_history.blinds,
+        vec![
+            BlindBet::new(1, BlindType::Ante, 50),
+            BlindBet::new(2, BlindType::Ante, 50),
+            BlindBet::new(3, BlindType::Ante, 50),
+        ]
+    );
+
+    // The next hand is a regular one
+    holdem.internal_start_game(&mut effect).unwrap();
+    assert!(!holdem.bomb_pot);
+    assert!(holdem.pots.is_empty());
+}
+
+#[test]
+fn test_uncalled_allin_returned_before_runout() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.deck_random_id = 1;
+    deal_hole_cards(&mut holdem);
+    holdem.player_map.get_mut(&2).unwrap().chips = 400;
+    for (id, bet) in [(1, 1000), (2, 400)] {
+        let (allin, _) = holdem.take_bet(id, bet).unwrap();
+        assert!(allin);
+        holdem.set_player_status(id, PlayerStatus::Allin).unwrap();
+    }
+    let mut effect = Effect::default();
+
+    holdem.next_state(&mut effect).unwrap();
+
+    assert_eq!(holdem.stage, HoldemStage::Runner);
+    assert!(holdem.display.contains(&Display::ReturnUncalledBet {
+        player_id: 1,
+        amount: 600
+    }));
+    assert_eq!(holdem.player_map[&1].chips, 600);
+    assert_eq!(holdem.total_bet_map[&1], 400);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 800);
+    assert!(holdem.bet_map.is_empty());
+}
+
+#[test]
+fn test_short_allin_does_not_reopen_raise() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 150, PlayerStatus::Wait);
+    holdem.player_order = vec![1, 2, 3];
+    holdem.btn = 2;
+    let mut effect = Effect::default();
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Bet(100)))
+        .unwrap();
+    holdem
+        .handle_event(&mut effect, custom_event(2, GameEvent::Call))
+        .unwrap();
+    // The 50 raise is short of the min raise of 100
+    holdem
+        .handle_event(&mut effect, custom_event(3, GameEvent::Raise(150)))
+        .unwrap();
+
+    assert_eq!(holdem.street_bet, 150);
+    assert_eq!(holdem.min_raise, 100);
+    assert_eq!(holdem.raise_closed, vec![1, 2]);
+    assert!(holdem.is_acting_player(1));
+    let context = holdem.acting_player_context().unwrap();
+    assert_eq!((context.to_call, context.max_raise), (50, 50));
+
+    let result = holdem.handle_event(&mut effect, custom_event(1, GameEvent::Raise(300)));
+    assert_eq!(result, Err(errors::action_not_reopened()));
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::Call))
+        .unwrap();
+    assert_eq!(holdem.player_map[&1].chips, 850);
+}
+
+#[test]
+fn test_action_warning_before_timeout() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.action_warning = 5000;
+    let mut effect = Effect::default();
+    effect.timestamp = 1000;
+    holdem.ask_for_action(1, &mut effect).unwrap();
+
+    let clock = 1000 + ACTION_TIMEOUT_POSTFLOP;
+    assert_eq!(effect.wait_timeout, Some(ACTION_TIMEOUT_POSTFLOP - 5000));
+    assert!(effect.action_timeout.is_none());
+
+    let mut effect = Effect::default();
+    effect.timestamp = clock - 5000;
+    holdem
+        .handle_event(&mut effect, Event::WaitingTimeout)
+        .unwrap();
+
+    assert_eq!(
+        holdem.display,
+        vec![Display::ActionWarning { player_id: 1 }]
+    );
+    assert!(effect.action_timeout.is_some());
+    assert!(effect.wait_timeout.is_none());
+    assert_eq!(holdem.acting_player.as_ref().map(|a| a.clock), Some(clock));
+}
+
+#[test]
+fn test_post_dead_blind_to_return_from_sitting_out() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+    seat_player(&mut holdem, 4, 1000, PlayerStatus::Wait);
+    holdem.sit_out(1, 2).unwrap();
+    let mut effect = Effect::default();
+
+    holdem
+        .handle_event(&mut effect, custom_event(1, GameEvent::PostDeadBlind))
+        .unwrap();
+    holdem.internal_start_game(&mut effect).unwrap();
+    holdem.stage = HoldemStage::Play;
+    holdem.street = Street::Preflop;
+    holdem.arrange_players(holdem.btn).unwrap();
+    holdem.blind_bets(&mut effect).unwrap();
+
+    // Player 3 and 4 are in the blinds, player 1 is UTG
+    assert!(holdem.player_order.contains(&1));
+    assert_eq!(holdem.player_map[&1].chips, 970);
+    assert_eq!(holdem.bet_map[&1], 20);
+    assert_eq!(holdem.pots.iter().map(|p| p.amount).sum::<u64>(), 10);
+    let blinds = &holdem.hand_history.blinds;
+    assert!(blinds.contains(&BlindBet::new(1, BlindType::DeadSb, 10)));
+    assert!(blinds.contains(&BlindBet::new(1, BlindType::Bb, 20)));
+    assert!(holdem.is_acting_player(1));
+    assert!(holdem.dead_blind_posters.is_empty());
+
+    // Not sitting out any more
+    let result = holdem.post_dead_blind(1);
+    assert_eq!(result, Err(errors::dead_blind_not_available()));
+}
+
+#[test]
+fn test_effective_stack_limited_by_short_stack() {
+    let mut holdem = setup_heads_up_flop();
+    seat_player(&mut holdem, 3, 300, PlayerStatus::Wait);
+    seat_player(&mut holdem, 4, 5000, PlayerStatus::Fold);
+
+    assert_eq!(holdem.effective_stack(1, 2), 1000);
+    assert_eq!(holdem.effective_stack(1, 3), 300);
+    assert_eq!(holdem.effective_stack(3, 99), 0);
+    // The folded big stack doesn't count
+    assert_eq!(holdem.max_callable(1), 1000);
+    holdem.player_map.get_mut(&2).unwrap().status = PlayerStatus::Fold;
+    assert_eq!(holdem.max_callable(1), 300);
+    assert_eq!(holdem.max_callable(3), 300);
+}
+
+#[test]
+fn test_reveal_next_board_cards_on_turn() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.street = Street::Turn;
+    holdem.deck_random_id = 1;
+    holdem.board = vec!["s2".to_string(), "d7".to_string(), "c9".to_string()];
+    deal_hole_cards(&mut holdem);
+    let mut effect = Effect::default();
+
+    // The turn comes after four hole cards and the flop
+    assert_eq!(
+        holdem.reveal_next_board_cards(&mut effect, 1).unwrap(),
+        vec![7]
+    );
+    assert_eq!(holdem.next_board_card_indexes(2), vec![7, 8]);
+
+    let revealed = revealed_deck(&["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]);
+    effect.revealed.insert(1, revealed);
+    assert_eq!(
+        holdem.next_board_cards(&effect, 1).unwrap(),
+        vec!["dj".to_string()]
+    );
+}
+
+#[test]
+fn test_short_deck_spec_deals_from_36_cards() {
+    let mut holdem = setup_heads_up_flop();
+    holdem.deck_spec = DeckSpec::ShortDeck36;
+    holdem.deck_random_id = 1;
+    seat_player(&mut holdem, 3, 1000, PlayerStatus::Wait);
+    let mut effect = Effect::default();
+
+    assert_eq!(holdem.deck_spec.cards().len(), 36);
+    holdem.deal_cards(&mut effect).unwrap();
+    assert_eq!(holdem.hand_index_map[&3], vec![4, 5]);
+    // The flop follows the six hole cards, the river is within the deck
+    assert_eq!(holdem.next_board_card_indexes(3), vec![6, 7, 8]);
+    assert_eq!(holdem.next_board_card_indexes(5).last(), Some(&10));
+}
@@ -132,3 +132,75 @@ fn test_on_preflop_then_runner() -> Result<()> {
     }
     Ok(())
 }
+
+// Three players join a table of two seats in one batch
+// The last one is rejected as the table is full
+#[test]
+fn test_join_full_table_rejected() -> Result<()> {
+    let (_, mut game_acct, mut ctx, mut handler, mut transactor) = setup_holdem_game();
+    let mut alice = TestClient::player("Alice");
+    let mut bob = TestClient::player("Bob");
+    let mut carol = TestClient::player("Charlie");
+    handler.get_mut_state().table_size = 2;
+
+    let sync_evt = create_sync_event(
+        &mut ctx,
+        &mut game_acct,
+        vec![&mut alice, &mut bob, &mut carol],
+        &transactor,
+    );
+    handler.handle_until_no_events(
+        &mut ctx,
+        &sync_evt,
+        vec![&mut alice, &mut bob, &mut carol, &mut transactor],
+    )?;
+
+    {
+        let state = handler.get_state();
+        assert_eq!(state.player_map.len(), 2);
+        assert!(state.player_map.get(&carol.id()).is_none());
+        assert!(state.player_map.values().all(|p| p.position < 2));
+        assert_eq!(state.street, Street::Preflop);
+    }
+
+    Ok(())
+}
+
+// The table requires three players to start
+// Two players wait until the third one joins
+#[test]
+fn test_wait_for_min_players_to_start() -> Result<()> {
+    let (_, mut game_acct, mut ctx, mut handler, mut transactor) = setup_holdem_game();
+    let mut alice = TestClient::player("Alice");
+    let mut bob = TestClient::player("Bob");
+    let mut carol = TestClient::player("Charlie");
+    handler.get_mut_state().min_players_to_start = 3;
+
+    let sync_evt = create_sync_event(&mut ctx, &mut game_acct, vec![&mut alice, &mut bob], &transactor);
+    handler.handle_until_no_events(
+        &mut ctx,
+        &sync_evt,
+        vec![&mut alice, &mut bob, &mut transactor],
+    )?;
+
+    {
+        let state = handler.get_state();
+        assert_eq!(state.player_map.len(), 2);
+        assert_eq!(state.stage, HoldemStage::Init);
+        assert_eq!(state.street, Street::Init);
+    }
+
+    let carol_sync_evt = create_sync_event(&mut ctx, &mut game_acct, vec![&mut carol], &transactor);
+    handler.handle_until_no_events(
+        &mut ctx,
+        &carol_sync_evt,
+        vec![&mut alice, &mut bob, &mut carol, &mut transactor],
+    )?;
+
+    {
+        let state = handler.get_state();
+        assert_eq!(state.street, Street::Preflop);
+    }
+
+    Ok(())
+}
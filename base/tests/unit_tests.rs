@@ -7,10 +7,18 @@ mod helper;
 
 use helper::{
     initial_players, make_even_betmap, make_uneven_betmap, setup_context, setup_holdem_state,
+    setup_two_player_holdem,
 };
-use race_api::prelude::HandleError;
-use race_holdem_base::essential::{ActingPlayer, Display};
-use std::collections::BTreeMap;
+use race_api::prelude::{Event, GameHandler, HandleError};
+use race_holdem_base::errors;
+use race_holdem_base::hand_history::{BlindType, ChipsChange, SeatInfo, TableMeta};
+use race_holdem_base::essential::{
+    ActingPlayer, AwardPot, BettingMode, Display, GameEvent, GameVariant, HoldemStage,
+    InsuranceOffer, Player, PlayerStatus, Pot, PotShare, PotView, Street,
+};
+use race_holdem_base::game::Holdem;
+use race_holdem_base::replay::InitialState;
+use std::collections::{BTreeMap, HashMap};
 
 const ALICE: u64 = 0;
 const BOB: u64 = 1;
@@ -38,10 +46,18 @@ fn test_collect_bets_with_even_bets() -> Result<(), HandleError> {
     assert_eq!(state.pots[0].amount, 200);
     assert_eq!(
         state.display,
-        vec![Display::CollectBets {
-            old_pots: vec![],
-            bet_map: make_even_betmap()
-        }]
+        vec![
+            Display::CollectBets {
+                old_pots: vec![],
+                bet_map: make_even_betmap()
+            },
+            Display::Pots {
+                pots: vec![PotView {
+                    owners: vec![ALICE, BOB, CAROL, DAVE, EVA],
+                    amount: 200,
+                }],
+            },
+        ]
     );
     state.pots = vec![];
     Ok(())
@@ -78,6 +94,114 @@ fn test_collect_bets_with_uneven_bets() -> Result<(), HandleError> {
     Ok(())
 }
 
+#[test]
+fn test_collect_bets_with_nine_way_allin() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    let ids: Vec<u64> = (1..=9).collect();
+    state.player_map = ids
+        .iter()
+        .map(|id| {
+            let player =
+                Player::new_with_timeout_and_status(*id, 0, *id as usize, PlayerStatus::Allin);
+            (*id, player)
+        })
+        .collect();
+    // Nine distinct stacks: 100, 200, ..., 900, all in preflop
+    state.bet_map = ids.iter().map(|id| (*id, id * 100)).collect();
+    state.total_bet_map = state.bet_map.clone();
+    // The antes collected before, owned by all of them
+    state.pots = vec![Pot {
+        owners: ids.clone(),
+        winners: vec![],
+        amount: 90,
+    }];
+    let total_chips: u64 = 90 + state.bet_map.values().sum::<u64>();
+
+    state.collect_bets()?;
+
+    // The first level is merged into the antes pot, the last level
+    // is uncalled and returned to the biggest stack
+    assert_eq!(state.pots.len(), 8);
+    assert_eq!(state.pots[0].amount, 90 + 900);
+    for (i, pot) in state.pots.iter().enumerate() {
+        assert_eq!(pot.owners, ids[i..].to_vec());
+        if i > 0 {
+            assert_eq!(pot.amount, 100 * (9 - i as u64));
+        }
+    }
+    assert_eq!(state.player_map[&9].chips, 100);
+    assert_eq!(state.total_bet_map[&9], 800);
+
+    let pots_chips: u64 = state.pots.iter().map(|p| p.amount).sum();
+    let players_chips: u64 = state.player_map.values().map(|p| p.chips).sum();
+    assert_eq!(pots_chips + players_chips, total_chips);
+    Ok(())
+}
+
+#[test]
+fn test_collect_bets_displays_side_pots() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    // Three all-in players of different stacks and a caller
+    state.player_map = [
+        (1, 0, PlayerStatus::Allin),
+        (2, 0, PlayerStatus::Allin),
+        (3, 0, PlayerStatus::Allin),
+        (4, 700, PlayerStatus::Acted),
+    ]
+    .into_iter()
+    .map(|(id, chips, status)| {
+        let player = Player::new_with_timeout_and_status(id, chips, id as usize, status);
+        (id, player)
+    })
+    .collect();
+    state.bet_map = BTreeMap::from([(1, 100), (2, 200), (3, 300), (4, 300)]);
+    state.total_bet_map = state.bet_map.clone();
+    state.pots = vec![];
+
+    state.collect_bets()?;
+
+    assert_eq!(
+        state.display.last(),
+        Some(&Display::Pots {
+            pots: vec![
+                PotView {
+                    owners: vec![1, 2, 3, 4],
+                    amount: 400,
+                },
+                PotView {
+                    owners: vec![2, 3, 4],
+                    amount: 300,
+                },
+                PotView {
+                    owners: vec![3, 4],
+                    amount: 200,
+                },
+            ],
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_collect_bets_keeps_pots_with_different_owners() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    // A side pot from previous streets
+    state.pots = vec![Pot {
+        owners: vec![ALICE, BOB],
+        winners: vec![],
+        amount: 100,
+    }];
+    state.bet_map = BTreeMap::from([(CAROL, 50), (DAVE, 50)]);
+    state.total_bet_map = state.bet_map.clone();
+
+    state.collect_bets()?;
+
+    assert_eq!(state.pots.len(), 2);
+    assert_eq!(state.pots[1].owners, vec![CAROL, DAVE]);
+    assert_eq!(state.pots[1].amount, 100);
+    Ok(())
+}
+
 #[test]
 fn test_assign_winners() -> Result<(), HandleError> {
     let mut state = setup_holdem_state()?;
@@ -174,7 +298,7 @@ fn test_calc_prize() -> Result<(), HandleError> {
         state.calc_prize()?;
         assert_eq!(state.pots.len(), 1);
         assert_eq!(state.pots[0].winners.len(), 1);
-        assert_eq!(state.prize_map.len(), 2); // there's odd_chips_winner
+        assert_eq!(state.prize_map.len(), 1);
         assert_eq!(state.prize_map.get(&BOB), Some(&200));
 
         state.pots = vec![];
@@ -187,7 +311,7 @@ fn test_calc_prize() -> Result<(), HandleError> {
         state.bet_map = bet_map;
         state.collect_bets()?;
         let winners = vec![
-            // 3 players slipt pot and Alice gets the remainder
+            // 3 players slipt pot and Bob, left to BTN, gets the remainder
             vec![BOB, DAVE, ALICE],
             vec![CAROL],
             vec![EVA],
@@ -197,9 +321,9 @@ fn test_calc_prize() -> Result<(), HandleError> {
         assert_eq!(state.pots.len(), 1);
         assert_eq!(state.pots[0].winners.len(), 3);
         assert_eq!(state.prize_map.len(), 3);
-        assert_eq!(state.prize_map.get(&BOB), Some(&66));
+        assert_eq!(state.prize_map.get(&BOB), Some(&68));
         assert_eq!(state.prize_map.get(&DAVE), Some(&66));
-        assert_eq!(state.prize_map.get(&ALICE), Some(&68));
+        assert_eq!(state.prize_map.get(&ALICE), Some(&66));
 
         state.pots = vec![];
         state.prize_map = BTreeMap::new();
@@ -239,6 +363,88 @@ fn test_calc_prize() -> Result<(), HandleError> {
     Ok(())
 }
 
+#[test]
+fn test_odd_chip_goes_left_of_btn() -> Result<(), HandleError> {
+    let mut player_map = BTreeMap::new();
+    for (id, position) in [(1, 0), (2, 1), (3, 2)] {
+        let player = Player::new_with_timeout_and_status(id, 0, position, PlayerStatus::Acted);
+        player_map.insert(id, player);
+    }
+    let mut state = Holdem {
+        btn: 0,
+        player_map,
+        pots: vec![Pot {
+            owners: vec![1, 2, 3],
+            winners: vec![1, 3],
+            amount: 101,
+        }],
+        ..Default::default()
+    };
+    state.calc_prize()?;
+    // Player 3 sits closer to the left of BTN than player 1 does
+    assert_eq!(state.prize_map, BTreeMap::from([(1, 50), (3, 51)]));
+
+    // The odd chip wraps around BTN
+    state.btn = 2;
+    state.calc_prize()?;
+    assert_eq!(state.prize_map, BTreeMap::from([(1, 51), (3, 50)]));
+    Ok(())
+}
+
+#[test]
+fn test_take_rake_before_splitting_pot() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    state.street = Street::Flop;
+    state.rake = 50;
+    state.bet_map = make_even_betmap();
+    state.collect_bets()?;
+    // 3 players split the 200 pot
+    let winners = vec![vec![BOB, DAVE, ALICE], vec![CAROL], vec![EVA]];
+    state.assign_winners(winners)?;
+
+    // Rake is taken once from the whole pot: 200 * 5% = 10
+    let rake = state.take_rake_from_pots()?;
+    assert_eq!(rake, 10);
+    assert_eq!(state.pots[0].amount, 190);
+
+    // Odd chips are calculated on the net amount: 190 = 63 * 3 + 1
+    state.calc_prize()?;
+    assert_eq!(state.prize_map.get(&BOB), Some(&64));
+    assert_eq!(state.prize_map.get(&DAVE), Some(&63));
+    assert_eq!(state.prize_map.get(&ALICE), Some(&63));
+    assert_eq!(state.prize_map.values().sum::<u64>() + rake, 200);
+    Ok(())
+}
+
+#[test]
+fn test_rake_cap_modes() -> Result<(), HandleError> {
+    let bet_map = BTreeMap::from([
+        (ALICE, 400),
+        (BOB, 400),
+        (CAROL, 400),
+        (DAVE, 400),
+        (EVA, 400),
+    ]);
+    let rake_with_cap = |rake_cap, rake_cap_chips| -> Result<u64, HandleError> {
+        let mut state = setup_holdem_state()?;
+        state.street = Street::Flop;
+        state.rake = 50;
+        state.rake_cap = rake_cap;
+        state.rake_cap_chips = rake_cap_chips;
+        state.bet_map = bet_map.clone();
+        state.collect_bets()?;
+        state.take_rake_from_pots()
+    };
+
+    // 2000 * 5% = 100, capped at 1 BB
+    assert_eq!(rake_with_cap(1, None)?, 20);
+    assert_eq!(rake_with_cap(10, None)?, 100);
+    // Capped at the chips regardless of BB
+    assert_eq!(rake_with_cap(1, Some(60))?, 60);
+    assert_eq!(rake_with_cap(1, Some(500))?, 100);
+    Ok(())
+}
+
 // NOTE: In real cases, players' chips will be decreased by the amount they bet.
 // Here we skip the step of taking bets from them and focus on the prizes they get.
 #[test]
@@ -312,7 +518,7 @@ fn test_update_chips_map_singe_pot() -> Result<(), HandleError> {
     assert_eq!(chips_change_map.get(&DAVE), Some(&0));
     assert_eq!(chips_change_map.get(&CAROL), Some(&0));
     assert_eq!(chips_change_map.get(&EVA), Some(&0));
-    let Some(Display::GameResult{ player_map }) = state.display.iter().find(|d| matches!(d, Display::GameResult { .. }))
+    let Some(Display::GameResult{ player_map, .. }) = state.display.iter().find(|d| matches!(d, Display::GameResult { .. }))
         else {
             panic!("GameResult display is missing");
         };
@@ -362,7 +568,7 @@ fn test_update_chips_map_with_multiple_pot() -> Result<(), HandleError> {
         }
     }
     // println!("-- Display {:?}", state.display);
-    let Some(Display::GameResult { player_map }) = state.display.iter().find(|d| matches!(d, Display::GameResult {..})) else {
+    let Some(Display::GameResult { player_map, .. }) = state.display.iter().find(|d| matches!(d, Display::GameResult {..})) else {
         panic!("GameResult display not found");
     };
     assert_eq!(player_map.get(&ALICE).unwrap().prize, Some(100));
@@ -394,3 +600,583 @@ fn test_blind_bets() -> Result<(), HandleError> {
     assert_eq!(state.bet_map.get(&CAROL), Some(&state.bb));
     Ok(())
 }
+
+#[test]
+fn test_ante_only_bets() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    state.ante_only = true;
+    state.ante = 5;
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+
+    state.blind_bets(&mut efx)?;
+    // The player next to the button acts first
+    assert_eq!(state.acting_player.as_ref().map(|a| a.id), Some(BOB));
+    assert!(state.bet_map.is_empty());
+    assert_eq!(state.street_bet, 0);
+    assert_eq!(state.hand_history.blinds.len(), 6);
+    assert!(state
+        .hand_history
+        .blinds
+        .iter()
+        .all(|b| b.blind_type == BlindType::Ante));
+    assert_eq!(state.pots[0].amount, 30);
+    Ok(())
+}
+
+#[test]
+fn test_update_board_after_dealt_player_left() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    // Three players dealt in, the board comes after their 6 hole cards
+    state.hand_index_map =
+        BTreeMap::from([(ALICE, vec![0, 1]), (BOB, vec![2, 3]), (CAROL, vec![4, 5])]);
+    state.player_map.remove(&CAROL);
+    state.street = Street::Showdown;
+    state.board = vec!["s2".into(), "d7".into(), "c9".into()];
+    state.runouts = 2;
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    let deck = ["s2", "d7", "c9", "dj", "c3", "hk", "h4"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i + 6, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, deck);
+
+    state.update_board(&mut efx)?;
+    assert_eq!(state.board, vec!["s2", "d7", "c9", "dj", "c3"]);
+    assert_eq!(state.second_board, vec!["s2", "d7", "c9", "hk", "h4"]);
+    Ok(())
+}
+
+#[test]
+fn test_replay_reproduces_captured_hand() -> Result<(), HandleError> {
+    let mut initial = setup_two_player_holdem()?;
+    initial.rake = 0;
+
+    // Play a hand to showdown, AA beats KK
+    let mut state = initial.clone();
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    state.internal_start_game(&mut efx)?;
+    state.deal_cards(&mut efx)?;
+    let revealed = ["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, revealed);
+    let secrets_ready = Event::SecretsReady { random_ids: vec![] };
+    state.handle_event(&mut efx, secrets_ready.clone())?;
+    // None for the next street to be dealt
+    let actions = [
+        Some(GameEvent::Call),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Bet(40)),
+        Some(GameEvent::Call),
+        None,
+        Some(GameEvent::Check),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Check),
+        Some(GameEvent::Check),
+    ];
+    for action in actions {
+        match action {
+            Some(event) => {
+                let sender = state.acting_player.as_ref().map(|p| p.id).unwrap();
+                state.handle_custom_event(&mut efx, event, sender)?;
+            }
+            None => state.handle_event(&mut efx, secrets_ready.clone())?,
+        }
+    }
+    assert_eq!(state.stage, HoldemStage::Showdown);
+    state.handle_event(&mut efx, secrets_ready)?;
+    let history = state.hand_history;
+    assert_eq!(
+        history.chips_change,
+        BTreeMap::from([(ALICE, ChipsChange::Add(60)), (BOB, ChipsChange::Sub(60))])
+    );
+
+    // The hole cards are taken from the showdowns
+    let replayed = Holdem::replay(
+        &history,
+        InitialState {
+            holdem: initial.clone(),
+            hole_cards: BTreeMap::new(),
+        },
+    )?;
+    assert_eq!(replayed.chips_change, history.chips_change);
+    assert_eq!(replayed.chips, BTreeMap::from([(ALICE, 1060), (BOB, 940)]));
+
+    // A tampered outcome is caught
+    let mut tampered = history.clone();
+    tampered.chips_change =
+        BTreeMap::from([(ALICE, ChipsChange::Sub(60)), (BOB, ChipsChange::Add(60))]);
+    assert_eq!(
+        Holdem::replay(
+            &tampered,
+            InitialState {
+                holdem: initial,
+                hole_cards: BTreeMap::new(),
+            },
+        ),
+        Err(errors::replay_chips_change_mismatch())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_pokerstars_text_of_played_hand() -> Result<(), HandleError> {
+    let mut state = setup_two_player_holdem()?;
+    state.rake = 0;
+    let seats = state
+        .player_map
+        .values()
+        .map(|p| SeatInfo {
+            id: p.id,
+            position: p.position,
+            chips: p.chips,
+        })
+        .collect();
+
+    // AA checks down against KK after betting the flop
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    state.internal_start_game(&mut efx)?;
+    state.deal_cards(&mut efx)?;
+    let revealed = ["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, revealed);
+    let secrets_ready = Event::SecretsReady { random_ids: vec![] };
+    state.handle_event(&mut efx, secrets_ready.clone())?;
+    // None for the next street to be dealt
+    let actions = [
+        Some(GameEvent::Call),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Bet(40)),
+        Some(GameEvent::Call),
+        None,
+        Some(GameEvent::Check),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Check),
+        Some(GameEvent::Check),
+    ];
+    for action in actions {
+        match action {
+            Some(event) => {
+                let sender = state.acting_player.as_ref().map(|p| p.id).unwrap();
+                state.handle_custom_event(&mut efx, event, sender)?;
+            }
+            None => state.handle_event(&mut efx, secrets_ready.clone())?,
+        }
+    }
+    state.handle_event(&mut efx, secrets_ready)?;
+
+    let table_meta = TableMeta {
+        hand_id: 42,
+        table_name: "Race".into(),
+        table_size: state.table_size as usize,
+        sb: state.sb,
+        bb: state.bb,
+        betting_mode: BettingMode::NoLimit,
+        btn: state.btn,
+        timestamp: 1_700_000_000_000,
+        seats,
+    };
+    let expected = "\
+PokerStars Hand #42: Hold'em No Limit (10/20) - 2023/11/14 22:13:20 UTC
+Table 'Race' 6-max Seat #2 is the button
+Seat 1: 0 (1000 in chips)
+Seat 2: 1 (1000 in chips)
+1: posts small blind 10
+0: posts big blind 20
+*** HOLE CARDS ***
+1: calls 10
+0: checks
+*** FLOP *** [2s 7d 9c]
+0: bets 40
+1: calls 40
+*** TURN *** [2s 7d 9c] [Jd]
+0: checks
+1: checks
+*** RIVER *** [2s 7d 9c Jd] [3c]
+0: checks
+1: checks
+*** SHOW DOWN ***
+0: shows [As Ah] (a pair of Aces)
+1: shows [Ks Kh] (a pair of Kings)
+0 collected 120 from pot
+*** SUMMARY ***
+Total pot 120 | Rake 0
+Board [2s 7d 9c Jd 3c]";
+    assert_eq!(state.hand_history.to_pokerstars_text(&table_meta), expected);
+    Ok(())
+}
+
+#[test]
+fn test_allin_protection_on_turn_is_recorded_and_replayed() -> Result<(), HandleError> {
+    let mut initial = setup_two_player_holdem()?;
+    initial.rake = 0;
+    initial.allin_protection = true;
+
+    let mut state = initial.clone();
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    state.internal_start_game(&mut efx)?;
+    state.deal_cards(&mut efx)?;
+    let revealed = ["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "c3"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, revealed);
+    let secrets_ready = Event::SecretsReady { random_ids: vec![] };
+    state.handle_event(&mut efx, secrets_ready.clone())?;
+    // None for the next street to be dealt
+    let actions = [
+        Some(GameEvent::Call),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Check),
+        Some(GameEvent::Check),
+        None,
+        Some(GameEvent::Bet(100)),
+    ];
+    for action in actions {
+        match action {
+            Some(event) => {
+                let sender = state.acting_player.as_ref().map(|p| p.id).unwrap();
+                state.handle_custom_event(&mut efx, event, sender)?;
+            }
+            None => state.handle_event(&mut efx, secrets_ready.clone())?,
+        }
+    }
+    assert_eq!(state.street, Street::Turn);
+
+    // The player facing the bet on the turn is disconnected
+    let timed_out = state.acting_player.as_ref().map(|p| p.id).unwrap();
+    let timeout = Event::ActionTimeout {
+        player_id: timed_out,
+    };
+    state.handle_event(&mut efx, timeout)?;
+    assert_eq!(state.player_map[&timed_out].status, PlayerStatus::Allin);
+    assert_eq!(state.stage, HoldemStage::Runner);
+    let last_action = state.hand_history.turn.actions.last().unwrap();
+    assert_eq!(last_action.id, timed_out);
+    assert_eq!(last_action.event, GameEvent::AllinProtected);
+
+    // Deal the river and settle
+    state.handle_event(&mut efx, secrets_ready.clone())?;
+    state.handle_event(&mut efx, secrets_ready)?;
+    let history = state.hand_history;
+    assert_eq!(
+        history.chips_change,
+        BTreeMap::from([(ALICE, ChipsChange::Add(20)), (BOB, ChipsChange::Sub(20))])
+    );
+
+    // Replayed as a timeout, the bet is not called
+    let replayed = Holdem::replay(
+        &history,
+        InitialState {
+            holdem: initial,
+            hole_cards: BTreeMap::new(),
+        },
+    )?;
+    assert_eq!(replayed.chips_change, history.chips_change);
+    assert_eq!(replayed.chips, BTreeMap::from([(ALICE, 1020), (BOB, 980)]));
+    Ok(())
+}
+
+#[test]
+fn test_insurance_compensates_outdrawn_buyer() -> Result<(), HandleError> {
+    let mut state = setup_two_player_holdem()?;
+    state.rake = 0;
+    state.insurance = true;
+    state.insurance_pool = 10000;
+    state.stage = HoldemStage::Runner;
+    state.street = Street::Showdown;
+    state.board = vec!["s2".into(), "d7".into(), "c9".into(), "dj".into()];
+    state.hand_index_map = BTreeMap::from([(ALICE, vec![0, 1]), (BOB, vec![2, 3])]);
+    state.total_bet_map = BTreeMap::from([(ALICE, 1000), (BOB, 1000)]);
+    state.pots = vec![Pot {
+        owners: vec![ALICE, BOB],
+        winners: vec![],
+        amount: 2000,
+    }];
+    for p in state.player_map.values_mut() {
+        p.status = PlayerStatus::Allin;
+        p.chips = 0;
+    }
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    // The king on the river gives Bob the trips
+    let revealed = ["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "dk"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, revealed);
+
+    state.offer_insurance(&efx)?;
+    assert_eq!(
+        state.insurance_offer,
+        Some(InsuranceOffer {
+            player_id: ALICE,
+            outs: 2,
+            cards_left: 44,
+            max_premium: 90,
+        })
+    );
+    state.buy_insurance(&mut efx, ALICE, 40)?;
+    state.update_board(&mut efx)?;
+    state.settle(&mut efx)?;
+
+    // Paid 40 * 42 / 2 out of the pool
+    assert!(state.display.contains(&Display::InsurancePayout {
+        player_id: ALICE,
+        amount: 840,
+    }));
+    assert_eq!(state.player_map[&ALICE].chips, 840);
+    assert_eq!(state.player_map[&BOB].chips, 2000);
+    assert_eq!(state.insurance_pool, 9160);
+    Ok(())
+}
+
+#[test]
+fn test_insurance_without_cards_but_outs_is_not_settled() -> Result<(), HandleError> {
+    let mut state = setup_two_player_holdem()?;
+    state.rake = 0;
+    state.insurance = true;
+    state.insurance_pool = 10000;
+    state.stage = HoldemStage::Runner;
+    state.street = Street::Showdown;
+    state.board = vec!["s2".into(), "d7".into(), "c9".into(), "dj".into()];
+    state.hand_index_map = BTreeMap::from([(ALICE, vec![0, 1]), (BOB, vec![2, 3])]);
+    state.total_bet_map = BTreeMap::from([(ALICE, 1000), (BOB, 1000)]);
+    state.pots = vec![Pot {
+        owners: vec![ALICE, BOB],
+        winners: vec![],
+        amount: 2000,
+    }];
+    for p in state.player_map.values_mut() {
+        p.status = PlayerStatus::Allin;
+        p.chips = 0;
+    }
+    // Every card left is an out, which is never offered
+    state.insurance_offer = Some(InsuranceOffer {
+        player_id: ALICE,
+        outs: 44,
+        cards_left: 44,
+        max_premium: 90,
+    });
+    state.insurance_premium = 40;
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    let revealed = ["sa", "ha", "sk", "hk", "s2", "d7", "c9", "dj", "dk"]
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, c.to_string()))
+        .collect();
+    efx.revealed.insert(state.deck_random_id, revealed);
+
+    state.update_board(&mut efx)?;
+    state.settle(&mut efx)?;
+
+    assert!(!state
+        .display
+        .iter()
+        .any(|d| matches!(d, Display::InsurancePayout { .. })));
+    assert_eq!(state.player_map[&ALICE].chips, 0);
+    assert_eq!(state.player_map[&BOB].chips, 2000);
+    assert_eq!(state.insurance_pool, 10000);
+    Ok(())
+}
+
+#[test]
+fn test_deposit_over_max_is_partially_accepted() -> Result<(), HandleError> {
+    let mut state = setup_two_player_holdem()?;
+    state.max_deposit = 1500;
+    state.stage = HoldemStage::Play;
+    state.player_map.get_mut(&ALICE).unwrap().chips = 600;
+
+    // Only 900 fits under the max, the rest is refunded
+    assert_eq!(state.add_deposit(ALICE, 1200), Ok(300));
+    assert_eq!(state.player_map[&ALICE].deposit, 900);
+    assert_eq!(
+        state.display,
+        vec![Display::DepositPending {
+            player_id: ALICE,
+            amount: 900
+        }]
+    );
+
+    // Nothing more fits
+    assert_eq!(
+        state.add_deposit(ALICE, 100),
+        Err(errors::deposit_exceeds_max_buyin())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_hi_lo_pots_split_between_high_and_low() -> Result<(), HandleError> {
+    let mut state = setup_holdem_state()?;
+    state.pots = vec![
+        Pot {
+            owners: vec![ALICE, BOB, CAROL],
+            winners: vec![],
+            amount: 301,
+        },
+        Pot {
+            owners: vec![ALICE, CAROL],
+            winners: vec![],
+            amount: 200,
+        },
+    ];
+    // Only Bob has a low, who isn't in the side pot
+    let winners = vec![vec![ALICE], vec![CAROL], vec![BOB]];
+    state.assign_hi_lo_winners(winners, vec![vec![BOB]])?;
+    state.calc_prize()?;
+
+    // The odd chip goes to the high half, the side pot is scooped
+    assert_eq!(state.prize_map.get(&ALICE), Some(&351));
+    assert_eq!(state.prize_map.get(&BOB), Some(&150));
+    assert_eq!(state.prize_map.get(&CAROL), None);
+    assert_eq!(
+        state.award_pots(),
+        vec![
+            AwardPot {
+                winners: vec![ALICE],
+                amount: 151,
+                share: PotShare::High,
+            },
+            AwardPot {
+                winners: vec![BOB],
+                amount: 150,
+                share: PotShare::Low,
+            },
+            AwardPot {
+                winners: vec![ALICE],
+                amount: 200,
+                share: PotShare::Whole,
+            },
+        ]
+    );
+    Ok(())
+}
+
+// Settle an Omaha Hi-Lo hand of Alice, Bob and Carol, who put in 100
+// each.  Return the prizes and the pots awarded.
+fn settle_omaha_hi_lo(
+    hole_cards: [[&str; 4]; 3],
+    board: [&str; 5],
+) -> Result<(BTreeMap<u64, u64>, Vec<AwardPot>), HandleError> {
+    let mut state = setup_holdem_state()?;
+    state.variant = GameVariant::OmahaHiLo;
+    state.rake = 0;
+    state.stage = HoldemStage::Showdown;
+    state.street = Street::Showdown;
+    state.board = board.iter().map(|c| c.to_string()).collect();
+    let mut decryption = HashMap::new();
+    for (i, id) in [ALICE, BOB, CAROL].into_iter().enumerate() {
+        state
+            .hand_index_map
+            .insert(id, (i * 4..(i + 1) * 4).collect());
+        state.total_bet_map.insert(id, 100);
+        for (j, card) in hole_cards[i].iter().enumerate() {
+            decryption.insert(i * 4 + j, card.to_string());
+        }
+    }
+    for player in state.player_map.values_mut() {
+        player.status = if state.hand_index_map.contains_key(&player.id) {
+            PlayerStatus::Acted
+        } else {
+            PlayerStatus::Fold
+        };
+    }
+    state.pots = vec![Pot {
+        owners: vec![ALICE, BOB, CAROL],
+        winners: vec![],
+        amount: 300,
+    }];
+
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+    efx.revealed.insert(state.deck_random_id, decryption);
+    state.settle(&mut efx)?;
+    Ok((state.prize_map, state.award_pots()))
+}
+
+#[test]
+fn test_omaha_hi_lo_settle() -> Result<(), HandleError> {
+    // Alice has quads, Bob has the only low
+    let hole_cards = [
+        ["hk", "sk", "ht", "cj"],
+        ["da", "d4", "cq", "hj"],
+        ["c9", "h9", "dq", "hq"],
+    ];
+    let (prize_map, award_pots) = settle_omaha_hi_lo(hole_cards, ["s2", "d3", "h7", "ck", "dk"])?;
+    assert_eq!(prize_map, BTreeMap::from([(ALICE, 150), (BOB, 150)]));
+    assert_eq!(
+        award_pots,
+        vec![
+            AwardPot {
+                winners: vec![ALICE],
+                amount: 150,
+                share: PotShare::High,
+            },
+            AwardPot {
+                winners: vec![BOB],
+                amount: 150,
+                share: PotShare::Low,
+            },
+        ]
+    );
+
+    // No low qualifies on the board, the high scoops
+    let (prize_map, award_pots) = settle_omaha_hi_lo(hole_cards, ["s9", "dt", "sq", "ck", "dk"])?;
+    assert_eq!(prize_map, BTreeMap::from([(ALICE, 300)]));
+    assert_eq!(award_pots.len(), 1);
+    assert_eq!(award_pots[0].share, PotShare::Whole);
+    Ok(())
+}
+
+#[test]
+fn test_omaha_hi_lo_quartered() -> Result<(), HandleError> {
+    // Alice has quads and shares the 7-4-3-2-A low with Bob
+    let hole_cards = [
+        ["hk", "sk", "ha", "s4"],
+        ["da", "d4", "cq", "hj"],
+        ["c9", "h9", "dq", "hq"],
+    ];
+    let (prize_map, award_pots) = settle_omaha_hi_lo(hole_cards, ["s2", "d3", "h7", "ck", "dk"])?;
+    assert_eq!(prize_map, BTreeMap::from([(ALICE, 225), (BOB, 75)]));
+    assert_eq!(award_pots[1].share, PotShare::Low);
+    assert_eq!(award_pots[1].winners.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_omaha_hi_lo_deals_four_hole_cards() -> Result<(), HandleError> {
+    let mut state = setup_two_player_holdem()?;
+    state.variant = GameVariant::OmahaHiLo;
+    for player in state.player_map.values_mut() {
+        player.status = PlayerStatus::Wait;
+    }
+    let ctx = setup_context();
+    let mut efx = ctx.derive_effect();
+
+    state.deal_cards(&mut efx)?;
+    assert_eq!(state.hand_index_map[&ALICE], vec![0, 1, 2, 3]);
+    assert_eq!(state.hand_index_map[&BOB], vec![4, 5, 6, 7]);
+    // The flop follows the eight hole cards
+    assert_eq!(state.next_board_card_indexes(3), vec![8, 9, 10]);
+    Ok(())
+}
@@ -11,7 +11,9 @@ use race_api::{
     types::{EntryLock, GameDeposit},
 };
 
-use race_holdem_mtt_base::{ChipsChange, HoldemBridgeEvent, MttTablePlayer, MttTableState};
+use race_holdem_mtt_base::{
+    prizes_by_shares, ChipsChange, HoldemBridgeEvent, MttTablePlayer, MttTableState,
+};
 use race_proc_macro::game_handler;
 
 #[derive(Default, BorshSerialize, BorshDeserialize)]
@@ -30,6 +32,8 @@ pub struct LtMttAccountData {
     // pub blind_rules: Vec<u8>,
     // pub prize_rules: Vec<u8>,
     pub subgame_bundle: String,
+    // For the amounts not matched by `ticket_rules`
+    pub ticket_brackets: Vec<TicketBracket>,
 }
 
 #[derive(Default, BorshSerialize, BorshDeserialize, Debug, Clone)]
@@ -46,6 +50,46 @@ impl TicketRule {
     }
 }
 
+/// A ticket rule for the deposit amounts in a bracket, both inclusive.
+/// It applies only when no `TicketRule` matches the amount exactly.
+/// Among the matched brackets, the one with the highest `min_amount`
+/// wins, then the first in order.
+#[derive(Default, BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct TicketBracket {
+    // `None` in `deposit_times` means any other time.  `0` represents the first time.
+    deposit_times: Option<usize>,
+    min_amount: u64,
+    max_amount: Option<u64>, // `None` means no upper limit
+    chips: u64,
+}
+
+impl TicketBracket {
+    fn is_match(&self, times: usize, amount: u64) -> bool {
+        self.deposit_times.map(|t| t == times).unwrap_or(true)
+            && amount >= self.min_amount
+            && self.max_amount.map_or(true, |max| amount <= max)
+    }
+}
+
+/// Find the chips for a deposit, by the exact ticket rules first, then
+/// by the brackets.
+fn match_ticket_chips(
+    rules: &Vec<TicketRule>,
+    brackets: &Vec<TicketBracket>,
+    times: usize,
+    amount: u64,
+) -> Option<u64> {
+    if let Some(rule) = rules.iter().find(|r| r.is_match(times, amount)) {
+        return Some(rule.chips);
+    }
+    brackets
+        .iter()
+        .filter(|b| b.is_match(times, amount))
+        // The first of the highest, as `max_by_key` returns the last
+        .min_by_key(|b| Reverse(b.min_amount))
+        .map(|b| b.chips)
+}
+
 fn default_ticket_rules() -> Vec<TicketRule> {
     vec![
         TicketRule {
@@ -92,13 +136,13 @@ pub struct BlindRule {
     ante: u64,
 }
 
-fn match_blind_rule_by_chips(rules: &Vec<BlindRule>, chips: u64) -> &BlindRule {
+fn match_blind_rule_by_chips(rules: &Vec<BlindRule>, chips: u64) -> HandleResult<&BlindRule> {
     // BlindRules MUST ensures sorted by max_chips asc, because the client can pass custom rules.
     // Sort Vec<BlindRule> in init_state handler.
     rules
         .iter()
         .find(|rule| chips <= rule.max_chips.unwrap_or(u64::max_value()))
-        .expect("BlindRules Error")
+        .ok_or(errors::error_blind_rule_not_found())
 }
 
 fn default_blind_rules() -> Vec<BlindRule> {
@@ -217,6 +261,7 @@ pub struct LtMtt {
     tables: BTreeMap<usize, MttTableState>,
     table_assigns: BTreeMap<u64, usize>,
     // theme: Option<String>,
+    ticket_brackets: Vec<TicketBracket>,
 }
 
 impl GameHandler for LtMtt {
@@ -229,9 +274,10 @@ impl GameHandler for LtMtt {
             mut ticket_rules,
             total_prize,
             subgame_bundle,
+            ticket_brackets,
         } = init_account.data()?;
 
-        if ticket_rules.is_empty() {
+        if ticket_rules.is_empty() && ticket_brackets.is_empty() {
             ticket_rules = default_ticket_rules();
         }
 
@@ -251,6 +297,7 @@ impl GameHandler for LtMtt {
             total_prize,
             subgame_bundle,
             blind_rules,
+            ticket_brackets,
             ..Default::default()
         };
 
@@ -301,8 +348,10 @@ impl GameHandler for LtMtt {
 }
 
 impl LtMtt {
-    fn on_ready(&self, effect: &mut Effect) -> HandleResult<()> {
+    fn on_ready(&mut self, effect: &mut Effect) -> HandleResult<()> {
         effect.info("callback on_ready...");
+        // Reconcile the seats after the recovery from a checkpoint
+        self.match_table_for_pending_users(effect)?;
         effect.set_entry_lock(EntryLock::Closed);
         effect.start_game();
 
@@ -388,11 +437,12 @@ impl LtMtt {
                 return Ok(None);
             };
 
-            let Some(ticket_rule) = self
-                .ticket_rules
-                .iter()
-                .find(|tr| tr.is_match(player.deposit_history.len(), deposit.balance()))
-            else {
+            let Some(chips) = match_ticket_chips(
+                &self.ticket_rules,
+                &self.ticket_brackets,
+                player.deposit_history.len(),
+                deposit.balance(),
+            ) else {
                 effect.info(format!(
                     "on_deposit: Not found matched TicketRule for {} deposit: {}.",
                     deposit.id(),
@@ -402,7 +452,7 @@ impl LtMtt {
                 return Ok(None);
             };
 
-            player.chips = ticket_rule.chips;
+            player.chips = chips;
             player.deposit_history.push(deposit.balance());
             effect.info(format!(
                 "on_deposit: User {} deposit {}.",
@@ -435,15 +485,35 @@ impl LtMtt {
         } = game_result
         {
             effect.info(format!("on_game_result: table_id: {}", table_id));
+            // The final result of a table closed already
+            if !self.tables.contains_key(&table_id) {
+                effect.info(format!("on_game_result: ignore closed table {}", table_id));
+                return Ok(());
+            }
             self.tables.insert(table_id, table);
             self.apply_chips_change(chips_change)?;
-            let new_table = self.tables.get(&table_id).expect("error_table_not_found");
+            let new_table = self
+                .tables
+                .get(&table_id)
+                .ok_or(errors::error_table_not_found())?;
+            // No one is left on the table, close it instead of starting
+            // a new game.  A new table is created for the next player.
+            if new_table.players.is_empty() {
+                effect.info(format!("on_game_result: close empty table {}", table_id));
+                effect.bridge_event(table_id, HoldemBridgeEvent::CloseTable)?;
+                self.tables.remove(&table_id);
+                self.table_assigns.retain(|_, id| *id != table_id);
+                effect.checkpoint();
+                return Ok(());
+            }
             effect.bridge_event(
                 table_id,
                 HoldemBridgeEvent::StartGame {
                     sb: new_table.sb,
                     bb: new_table.bb,
                     moved_players: vec![],
+                    ante: new_table.ante,
+                    start_time: 0,
                 },
             )?;
             effect.checkpoint();
@@ -481,6 +551,52 @@ impl LtMtt {
         Ok(())
     }
 
+    /// Seat the pending users, those sat in with chips but not assigned
+    /// to any table, e.g. when the sit in was not done before a restart.
+    /// The users are relocated with one event per table.
+    fn match_table_for_pending_users(&mut self, effect: &mut Effect) -> HandleResult<()> {
+        let pending: Vec<LtMttPlayer> = self
+            .rankings
+            .iter()
+            .filter(|p| {
+                matches!(p.status, LtMttPlayerStatus::SatIn)
+                    && p.chips > 0
+                    && !self.table_assigns.contains_key(&p.player_id)
+            })
+            .cloned()
+            .collect();
+
+        let mut relocates: BTreeMap<usize, Vec<MttTablePlayer>> = BTreeMap::new();
+        for player in pending.iter() {
+            let mut mtt_table_player = MttTablePlayer::new(player.player_id, player.chips, 0);
+            let table_id = self.find_or_create_table(effect, player)?;
+            let table_ref = self
+                .tables
+                .get_mut(&table_id)
+                .ok_or(errors::error_table_not_found())?;
+            table_ref.add_player(&mut mtt_table_player);
+            self.table_assigns.insert(player.player_id, table_id);
+            relocates
+                .entry(table_id)
+                .or_default()
+                .push(mtt_table_player);
+        }
+
+        for (table_id, players) in relocates {
+            effect.info(format!(
+                "match_table_for_pending_users: {} users sit in table {}.",
+                players.len(),
+                table_id
+            ));
+            effect.bridge_event(table_id, HoldemBridgeEvent::Relocate { players })?;
+        }
+        if !pending.is_empty() {
+            effect.checkpoint();
+        }
+
+        Ok(())
+    }
+
     fn apply_chips_change(&mut self, chips_change: BTreeMap<u64, ChipsChange>) -> HandleResult<()> {
         for (player_id, change) in chips_change.into_iter() {
             let player = self
@@ -510,19 +626,40 @@ impl LtMtt {
             return Ok(());
         }
 
-        let total_shares: u8 = self.prize_rules.iter().take(self.rankings.len()).sum();
-        let prize_share: u64 = self.total_prize / total_shares as u64;
+        for (player_id, prize) in self.calc_prizes() {
+            effect.settle(player_id, prize, false)?;
+        }
 
-        for (i, ranking) in self.rankings.iter().enumerate() {
-            let player_id = ranking.player_id;
+        Ok(())
+    }
 
-            if let Some(rank) = self.prize_rules.get(i) {
-                let prize: u64 = prize_share * *rank as u64;
-                effect.settle(player_id, prize, false)?;
+    /// Calculate the prizes of rankings by `prize_rules`, in the same
+    /// way as MTT: the shares of unused slots are redistributed.
+    /// Players with equal chips split the prizes of the slots they take
+    /// equally, the odd chips go to the first of them.
+    fn calc_prizes(&self) -> Vec<(u64, u64)> {
+        let slot_prizes =
+            prizes_by_shares(self.total_prize, &self.prize_rules, self.rankings.len());
+        let slot_prize = |i: usize| slot_prizes.get(i).copied().unwrap_or(0);
+
+        let mut prizes = Vec::new();
+        let mut start = 0;
+        while start < self.rankings.len() && start < self.prize_rules.len() {
+            let chips = self.rankings[start].chips;
+            let end = start
+                + self.rankings[start..]
+                    .iter()
+                    .take_while(|r| r.chips == chips)
+                    .count();
+            let total: u64 = (start..end).map(slot_prize).sum();
+            let cnt = (end - start) as u64;
+            for (i, ranking) in self.rankings[start..end].iter().enumerate() {
+                let odd_chips = if i == 0 { total % cnt } else { 0 };
+                prizes.push((ranking.player_id, total / cnt + odd_chips));
             }
+            start = end;
         }
-
-        Ok(())
+        prizes
     }
 
     #[allow(unused)]
@@ -543,7 +680,7 @@ impl LtMtt {
                 .then_with(|| a.players.len().cmp(&b.players.len()))
         });
 
-        let matched_blind_rule = match_blind_rule_by_chips(&self.blind_rules, player.chips);
+        let matched_blind_rule = match_blind_rule_by_chips(&self.blind_rules, player.chips)?;
 
         if let Some((&id, _)) = sorted_tables.iter().find(|(_id, table)| {
             matched_blind_rule.sb <= table.sb && table.players.len() < self.table_size as _
@@ -561,7 +698,7 @@ impl LtMtt {
             max_chips: _,
             sb,
             bb,
-            ante: _,
+            ante,
         } = blind_rule;
 
         let table = MttTableState {
@@ -572,6 +709,8 @@ impl LtMtt {
             players: Vec::new(),
             next_game_start: 0,
             hand_id: 0,
+            ante,
+            bb_ante: false,
         };
 
         effect.launch_sub_game(self.subgame_bundle.clone(), self.table_size as _, &table)?;
@@ -587,6 +726,210 @@ mod tests {
     use borsh::BorshDeserialize;
     use race_api::{effect::Effect, event::Event};
 
+    fn ranking(player_id: u64, chips: u64) -> LtMttPlayer {
+        LtMttPlayer {
+            player_id,
+            chips,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tied_rankings_split_prizes() {
+        let ltmtt = LtMtt {
+            total_prize: 1000,
+            prize_rules: vec![50, 30, 20],
+            rankings: vec![
+                ranking(1, 5000),
+                ranking(2, 3000),
+                ranking(3, 3000),
+                ranking(4, 1000),
+            ],
+            ..Default::default()
+        };
+        // Ranks 2 and 3 share (300 + 200) / 2
+        assert_eq!(ltmtt.calc_prizes(), vec![(1, 500), (2, 250), (3, 250)]);
+    }
+
+    #[test]
+    fn test_tie_spanning_last_prize_slot() {
+        let ltmtt = LtMtt {
+            total_prize: 1000,
+            prize_rules: vec![50, 30, 20],
+            rankings: vec![
+                ranking(1, 5000),
+                ranking(2, 3000),
+                ranking(3, 1000),
+                ranking(4, 1000),
+            ],
+            ..Default::default()
+        };
+        // The 3rd prize is split with the player out of prize slots
+        assert_eq!(
+            ltmtt.calc_prizes(),
+            vec![(1, 500), (2, 300), (3, 100), (4, 100)]
+        );
+    }
+
+    #[test]
+    fn test_prizes_with_fewer_rankings_than_slots() {
+        let ltmtt = LtMtt {
+            total_prize: 1000,
+            prize_rules: vec![50, 30, 20],
+            rankings: vec![ranking(1, 5000), ranking(2, 3000)],
+            ..Default::default()
+        };
+        // 50 : 30 of the whole prize
+        assert_eq!(ltmtt.calc_prizes(), vec![(1, 625), (2, 375)]);
+    }
+
+    #[test]
+    fn test_close_emptied_table() {
+        let mut ltmtt = LtMtt {
+            rankings: vec![ranking(1, 15000)],
+            ..Default::default()
+        };
+        let table = MttTableState {
+            table_id: 1,
+            sb: 100,
+            bb: 200,
+            ..Default::default()
+        };
+        ltmtt.tables.insert(1, table.clone());
+        ltmtt.table_assigns.insert(1, 1);
+
+        // The only player leaves the table
+        let mut effect = Effect::default();
+        let game_result = HoldemBridgeEvent::GameResult {
+            hand_id: 1,
+            table_id: 1,
+            chips_change: BTreeMap::new(),
+            table,
+        };
+        ltmtt.on_game_result(&mut effect, game_result).unwrap();
+
+        assert!(ltmtt.tables.is_empty());
+        assert!(ltmtt.table_assigns.is_empty());
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![(1, HoldemBridgeEvent::CloseTable)]
+        );
+
+        // The closed table replies with its final result, which is
+        // not closed again
+        let mut effect = Effect::default();
+        let final_result = HoldemBridgeEvent::GameResult {
+            hand_id: 1,
+            table_id: 1,
+            chips_change: BTreeMap::new(),
+            table: MttTableState::default(),
+        };
+        ltmtt.on_game_result(&mut effect, final_result).unwrap();
+
+        assert!(ltmtt.tables.is_empty());
+        assert!(effect.bridge_events.is_empty());
+    }
+
+    #[test]
+    fn test_sit_in_without_blind_rule_for_chips() {
+        let mut ltmtt = LtMtt {
+            table_size: 6,
+            blind_rules: vec![BlindRule {
+                max_chips: Some(150_000),
+                sb: 50,
+                bb: 100,
+                ante: 0,
+            }],
+            ..Default::default()
+        };
+        let player = ranking(1, 200_000);
+        let mut effect = Effect::default();
+
+        assert_eq!(
+            ltmtt.do_sit_in(&mut effect, &player),
+            Err(errors::error_blind_rule_not_found())
+        );
+        assert!(ltmtt.tables.is_empty());
+        assert!(ltmtt.table_assigns.is_empty());
+    }
+
+    #[test]
+    fn test_ticket_brackets() {
+        let bracket = |min_amount, max_amount, chips| TicketBracket {
+            deposit_times: None,
+            min_amount,
+            max_amount,
+            chips,
+        };
+        let brackets = vec![
+            bracket(1_000, Some(1_999), 10_000),
+            bracket(2_000, None, 25_000),
+            bracket(0, None, 1_000),
+        ];
+        let rules = vec![TicketRule {
+            deposit_times: None,
+            deposit_amount: 1_500,
+            chips: 20_000,
+        }];
+
+        let chips = |times, amount| match_ticket_chips(&rules, &brackets, times, amount);
+
+        // Inside a bracket
+        assert_eq!(chips(0, 1_234), Some(10_000));
+        assert_eq!(chips(1, 1_000_000), Some(25_000));
+        // The exact match takes precedence
+        assert_eq!(chips(0, 1_500), Some(20_000));
+        // Both brackets match, the one with the higher min amount wins
+        assert_eq!(chips(0, 2_000), Some(25_000));
+        // Outside all brackets but the catch-all
+        assert_eq!(chips(0, 999), Some(1_000));
+        // Outside all brackets, the deposit is rejected
+        let brackets = brackets[..2].to_vec();
+        assert_eq!(match_ticket_chips(&rules, &brackets, 0, 999), None);
+    }
+
+    #[test]
+    fn test_seat_pending_users_on_ready() {
+        let mut ltmtt = LtMtt {
+            table_size: 6,
+            blind_rules: default_blind_rules(),
+            rankings: vec![ranking(1, 15000), ranking(2, 15000), ranking(3, 15000)],
+            ..Default::default()
+        };
+        let mut table = MttTableState {
+            table_id: 1,
+            sb: 50,
+            bb: 100,
+            ..Default::default()
+        };
+        table.add_player(&mut MttTablePlayer::new(1, 15000, 0));
+        ltmtt.tables.insert(1, table);
+        ltmtt.table_assigns.insert(1, 1);
+        // Out of chips, waiting for the deposit
+        ltmtt.rankings.push(ranking(4, 0));
+
+        let mut effect = Effect::default();
+        ltmtt.handle_event(&mut effect, Event::Ready).unwrap();
+
+        assert_eq!(
+            ltmtt.table_assigns,
+            BTreeMap::from([(1, 1), (2, 1), (3, 1)])
+        );
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![(
+                1,
+                HoldemBridgeEvent::Relocate {
+                    players: vec![
+                        MttTablePlayer::new(2, 15000, 1),
+                        MttTablePlayer::new(3, 15000, 2)
+                    ]
+                }
+            )]
+        );
+        assert!(effect.is_checkpoint());
+    }
+
     #[test]
     fn test() {
         let effect = [
@@ -609,6 +952,11 @@ mod tests {
 
         let mut effect = Effect::try_from_slice(&effect).unwrap();
         let event = Event::try_from_slice(&event).unwrap();
+        // The state was captured before the ticket brackets were
+        // appended, add them as empty
+        if let Some(state) = effect.handler_state.as_mut() {
+            state.extend(Vec::<TicketBracket>::new().try_to_vec().unwrap());
+        }
 
         let mut ltmtt = effect.__handler_state::<LtMtt>();
         ltmtt.handle_event(&mut effect, event).unwrap();
@@ -29,22 +29,22 @@ pub struct MttTableState {
     pub bb: u64,
     pub players: Vec<MttTablePlayer>,
     pub next_game_start: u64,
+    pub ante: u64,
+    // Only the BB posts the ante, for the whole table
+    pub bb_ante: bool,
 }
 
 impl MttTableState {
+    /// Seat a player at the table.  The player keeps the position from
+    /// the previous table when it's free here, otherwise takes the first
+    /// free position.
     pub fn add_player(&mut self, player: &mut MttTablePlayer) {
-        let mut table_position = 0;
-        for i in 0.. {
-            if self
-                .players
-                .iter()
-                .find(|p| p.table_position == i)
-                .is_none()
-            {
-                table_position = i;
-                break;
-            }
-        }
+        let is_free = |i: usize| self.players.iter().all(|p| p.table_position != i);
+        let table_position = if is_free(player.table_position) {
+            player.table_position
+        } else {
+            (0..).find(|i| is_free(*i)).unwrap_or_default()
+        };
         self.players.push(MttTablePlayer {
             id: player.id,
             chips: player.chips,
@@ -59,12 +59,15 @@ impl MttTableState {
 /// through such events to the mtt handler.  Also see [`race_api::event::Event::Bridge`].
 #[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub enum HoldemBridgeEvent {
-    /// Start game with specified SB and BB.
+    /// Start game with specified SB, BB and ante.
     /// The `moved_players` indicates those should be removed before next hand.
+    /// The `start_time` defers the next hand, e.g. until a break ends, 0 for no deferring.
     StartGame {
         sb: u64,
         bb: u64,
         moved_players: Vec<u64>,
+        ante: u64,
+        start_time: u64,
     },
     /// Add players to current game.
     Relocate { players: Vec<MttTablePlayer> },
@@ -78,6 +81,10 @@ pub enum HoldemBridgeEvent {
         chips_change: BTreeMap<u64, ChipsChange>,
         table: MttTableState,
     },
+    /// Add chips to a seated player's stack, e.g. for an add-on.
+    AddChips { player_id: u64, chips: u64 },
+    /// Change the max number of players between hands, e.g. for the final table.
+    Reconfigure { table_size: u8 },
 }
 
 #[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone)]
@@ -87,3 +94,23 @@ pub enum ChipsChange {
 }
 
 impl BridgeEvent for HoldemBridgeEvent {}
+
+/// Split the prize pool to the ranks by the shares of `prize_rules`,
+/// one prize for each rank that has a rule.  When fewer players than
+/// the prize slots finish, the shares of the unused slots are
+/// redistributed to the used ones in proportion, so the whole pool is
+/// always paid out.  The odd chips from rounding go to the first.
+pub fn prizes_by_shares(prize_pool: u64, prize_rules: &[u8], ranks: usize) -> Vec<u64> {
+    let rules: Vec<u64> = prize_rules.iter().take(ranks).map(|r| *r as u64).collect();
+    let total_shares: u64 = rules.iter().sum();
+    if total_shares == 0 {
+        return vec![0; rules.len()];
+    }
+    let mut prizes: Vec<u64> = rules
+        .iter()
+        .map(|r| (prize_pool as u128 * *r as u128 / total_shares as u128) as u64)
+        .collect();
+    let odd_chips = prize_pool - prizes.iter().sum::<u64>();
+    prizes[0] += odd_chips;
+    prizes
+}
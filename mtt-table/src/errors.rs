@@ -12,3 +12,7 @@ custom_err!(invalid_bridge_event);
 custom_err!(duplicated_player_in_relocate);
 custom_err!(duplicated_position_in_relocate);
 custom_err!(invalid_player_in_start_game);
+custom_err!(invalid_player_in_add_chips);
+custom_err!(invalid_position_in_relocate);
+custom_err!(invalid_table_size_in_reconfigure);
+custom_err!(reconfigure_in_play);
@@ -1,11 +1,11 @@
 mod errors;
 
-use std::collections::btree_map::Entry;
+use std::collections::{btree_map::Entry, BTreeMap};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use race_api::event::BridgeEvent;
 use race_api::prelude::*;
-use race_holdem_base::essential::{GameMode, HoldemStage, Player, PlayerStatus};
+use race_holdem_base::essential::{AnteType, GameMode, HoldemStage, Player, PlayerStatus};
 use race_holdem_base::game::Holdem;
 use race_holdem_mtt_base::{ChipsChange, HoldemBridgeEvent, MttTablePlayer, MttTableState};
 use race_proc_macro::game_handler;
@@ -25,6 +25,8 @@ impl GameHandler for MttTable {
         let MttTableState {
             sb,
             bb,
+            ante,
+            bb_ante,
             players,
             table_id,
             btn,
@@ -40,6 +42,12 @@ impl GameHandler for MttTable {
             btn,
             sb,
             bb,
+            ante,
+            ante_type: if bb_ante {
+                AnteType::BigBlind
+            } else {
+                AnteType::EachPlayer
+            },
             table_size: init_account.max_players as _,
             mode: GameMode::Mtt,
             player_map,
@@ -65,23 +73,10 @@ impl GameHandler for MttTable {
             }
             _ => {
                 self.holdem.handle_event(effect, event)?;
+                self.apply_pending_chips();
                 // Check if there's a checkpoint
                 if effect.is_checkpoint() {
-                    let players = self
-                        .holdem
-                        .player_map
-                        .values()
-                        .map(|p| MttTablePlayer::new(p.id, p.chips, p.position as _))
-                        .collect();
-                    let mtt_table_state = MttTableState {
-                        table_id: self.table_id,
-                        btn: self.holdem.btn,
-                        hand_id: self.hand_id,
-                        sb: self.holdem.sb,
-                        bb: self.holdem.bb,
-                        next_game_start: self.holdem.next_game_start,
-                        players,
-                    };
+                    let mtt_table_state = self.table_state();
                     let chips_change = self
                         .holdem
                         .hand_history
@@ -113,6 +108,43 @@ impl GameHandler for MttTable {
 }
 
 impl MttTable {
+    fn is_between_hands(&self) -> bool {
+        matches!(self.holdem.stage, HoldemStage::Init | HoldemStage::Settle)
+    }
+
+    /// Move the chips queued as deposits onto the stacks when no hand
+    /// is in play.  The queued chips keep a busted player in the game.
+    fn apply_pending_chips(&mut self) {
+        if !self.is_between_hands() {
+            return;
+        }
+        for player in self.holdem.player_map.values_mut() {
+            player.chips += player.deposit;
+            player.deposit = 0;
+        }
+    }
+
+    fn table_state(&self) -> MttTableState {
+        let players = self
+            .holdem
+            .player_map
+            .values()
+            // Count the queued chips, already added by the master
+            .map(|p| MttTablePlayer::new(p.id, p.chips + p.deposit, p.position as _))
+            .collect();
+        MttTableState {
+            table_id: self.table_id,
+            btn: self.holdem.btn,
+            hand_id: self.hand_id,
+            sb: self.holdem.sb,
+            bb: self.holdem.bb,
+            next_game_start: self.holdem.next_game_start,
+            players,
+            ante: self.holdem.ante,
+            bb_ante: self.holdem.ante_type == AnteType::BigBlind,
+        }
+    }
+
     fn handle_bridge_event(
         &mut self,
         effect: &mut Effect,
@@ -123,14 +155,16 @@ impl MttTable {
                 sb,
                 bb,
                 moved_players,
+                ante,
+                start_time,
             } => {
-                let timeout = self
-                    .holdem
-                    .next_game_start
+                let timeout = u64::max(self.holdem.next_game_start, start_time)
                     .saturating_sub(effect.timestamp());
                 self.holdem.reset_state()?;
+                self.apply_pending_chips();
                 self.holdem.sb = sb;
                 self.holdem.bb = bb;
+                self.holdem.ante = ante;
                 for id in moved_players {
                     match self.holdem.player_map.entry(id) {
                         Entry::Vacant(_) => return Err(errors::invalid_player_in_start_game()),
@@ -152,6 +186,9 @@ impl MttTable {
                     if self.holdem.position_occupied(table_position) {
                         return Err(errors::duplicated_position_in_relocate())
                     }
+                    if table_position >= self.holdem.table_size as usize {
+                        return Err(errors::invalid_position_in_relocate())
+                    }
                     match self.holdem.player_map.entry(id) {
                         Entry::Vacant(e) =>
                             e.insert(Player::new_with_timeout_and_status(
@@ -174,10 +211,45 @@ impl MttTable {
                     effect.wait_timeout(timeout);
                 }
             }
+            // Report the final chips before leaving, the changes of
+            // the settled hands have been reported already.  The
+            // master reconciles the rest from the players' chips
             HoldemBridgeEvent::CloseTable => {
+                let evt = HoldemBridgeEvent::GameResult {
+                    hand_id: self.hand_id,
+                    table: self.table_state(),
+                    chips_change: BTreeMap::new(),
+                    table_id: self.table_id,
+                };
+                effect.bridge_event(0, evt)?;
                 self.holdem.player_map.clear();
                 effect.checkpoint();
             }
+            // The stack can't change in the middle of a hand, the
+            // chips are queued till it's settled
+            HoldemBridgeEvent::AddChips { player_id, chips } => {
+                let Some(player) = self.holdem.player_map.get_mut(&player_id) else {
+                    return Err(errors::invalid_player_in_add_chips());
+                };
+                player.deposit += chips;
+                self.apply_pending_chips();
+            }
+            // The seated players must fit in the new size
+            HoldemBridgeEvent::Reconfigure { table_size } => {
+                if !self.is_between_hands() {
+                    return Err(errors::reconfigure_in_play());
+                }
+                let fits = table_size as usize >= self.holdem.player_map.len()
+                    && self
+                        .holdem
+                        .player_map
+                        .values()
+                        .all(|p| p.position < table_size as usize);
+                if !fits {
+                    return Err(errors::invalid_table_size_in_reconfigure());
+                }
+                self.holdem.table_size = table_size;
+            }
             _ => return Err(errors::internal_invalid_bridge_event()),
         };
         Ok(())
@@ -259,6 +331,8 @@ mod tests {
             sb: 100,
             bb: 200,
             moved_players: vec![999], // Invalid player ID
+            ante: 0,
+            start_time: 0,
         };
         let result = mtt_table.handle_bridge_event(&mut effect, invalid_player_id_event);
         assert!(result.is_err());
@@ -274,6 +348,8 @@ mod tests {
             sb: 100,
             bb: 200,
             moved_players: vec![1, 2],
+            ante: 20,
+            start_time: 0,
         };
 
         mtt_table
@@ -282,6 +358,7 @@ mod tests {
 
         assert_eq!(mtt_table.holdem.sb, 100);
         assert_eq!(mtt_table.holdem.bb, 200);
+        assert_eq!(mtt_table.holdem.ante, 20);
         assert_eq!(mtt_table.holdem.player_map.len(), 1);
     }
 
@@ -445,6 +522,33 @@ mod tests {
         assert!(effect.is_checkpoint());
     }
 
+    #[test]
+    fn test_close_table_reports_final_result() {
+        let mut mtt_table = mtt_table_with_3_players();
+        let mut effect = Effect::default();
+
+        mtt_table
+            .handle_bridge_event(&mut effect, HoldemBridgeEvent::CloseTable)
+            .unwrap();
+
+        assert_eq!(effect.bridge_events.len(), 1);
+        let actual_event: HoldemBridgeEvent =
+            BorshDeserialize::try_from_slice(&effect.bridge_events[0].raw).unwrap();
+        let HoldemBridgeEvent::GameResult {
+            table,
+            chips_change,
+            ..
+        } = actual_event
+        else {
+            panic!("GameResult is not reported");
+        };
+        // The chips to reconcile are in the players
+        assert_eq!(table.players, default_3_players());
+        assert!(chips_change.is_empty());
+        assert!(mtt_table.holdem.player_map.is_empty());
+        assert!(effect.is_checkpoint());
+    }
+
     #[test]
     fn test_handle_event_with_checkpoint() {
         let mut mtt_table = mtt_table_with_3_players();
@@ -465,6 +569,8 @@ mod tests {
                 bb: 200,
                 next_game_start: 0,
                 players: default_3_players(),
+                ante: 0,
+                bb_ante: false,
             },
             chips_change,
             table_id: 1,
@@ -476,4 +582,73 @@ mod tests {
 
         assert_eq!(expected_event, actual_event);
     }
+
+    #[test]
+    fn test_handle_bridge_event_add_chips() {
+        let mut mtt_table = mtt_table_with_3_players();
+        let mut effect = Effect::default();
+        let add_chips = |player_id| HoldemBridgeEvent::AddChips {
+            player_id,
+            chips: 500,
+        };
+
+        // Added at once between hands
+        mtt_table
+            .handle_bridge_event(&mut effect, add_chips(1))
+            .unwrap();
+        assert_eq!(mtt_table.holdem.player_map[&1].chips, 1500);
+
+        // Queued in the middle of a hand, but reported
+        mtt_table.holdem.stage = HoldemStage::Play;
+        mtt_table
+            .handle_bridge_event(&mut effect, add_chips(1))
+            .unwrap();
+        assert_eq!(mtt_table.holdem.player_map[&1].chips, 1500);
+        assert_eq!(mtt_table.table_state().players[0].chips, 2000);
+
+        // Applied once the hand is settled
+        mtt_table.holdem.stage = HoldemStage::Settle;
+        mtt_table.apply_pending_chips();
+        assert_eq!(mtt_table.holdem.player_map[&1].chips, 2000);
+        assert_eq!(mtt_table.holdem.player_map[&1].deposit, 0);
+
+        let result = mtt_table.handle_bridge_event(&mut effect, add_chips(5));
+        assert_eq!(result, Err(errors::invalid_player_in_add_chips()));
+    }
+
+    #[test]
+    fn test_handle_bridge_event_reconfigure() {
+        let mut mtt_table = mtt_table_with_3_players();
+        let mut effect = Effect::default();
+        let reconfigure = |table_size| HoldemBridgeEvent::Reconfigure { table_size };
+
+        // Too small for the seated players
+        let result = mtt_table.handle_bridge_event(&mut effect, reconfigure(2));
+        assert_eq!(result, Err(errors::invalid_table_size_in_reconfigure()));
+
+        mtt_table
+            .handle_bridge_event(&mut effect, reconfigure(6))
+            .unwrap();
+        assert_eq!(mtt_table.holdem.table_size, 6);
+
+        // Relocating is bound by the new size
+        let bridge_event = HoldemBridgeEvent::Relocate {
+            players: vec![MttTablePlayer::new(4, 1000, 7)],
+        };
+        let result = mtt_table.handle_bridge_event(&mut effect, bridge_event);
+        assert_eq!(result, Err(errors::invalid_position_in_relocate()));
+
+        let bridge_event = HoldemBridgeEvent::Relocate {
+            players: vec![MttTablePlayer::new(4, 1000, 5)],
+        };
+        mtt_table
+            .handle_bridge_event(&mut effect, bridge_event)
+            .unwrap();
+        assert_eq!(mtt_table.holdem.player_map[&4].position, 5);
+
+        // Not in the middle of a hand
+        mtt_table.holdem.stage = HoldemStage::Play;
+        let result = mtt_table.handle_bridge_event(&mut effect, reconfigure(9));
+        assert_eq!(result, Err(errors::reconfigure_in_play()));
+    }
 }
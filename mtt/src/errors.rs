@@ -13,6 +13,30 @@ custom_err!(error_empty_blind_rules);
 custom_err!(error_player_id_not_found);
 custom_err!(error_invalid_bridge_event);
 custom_err!(error_invalid_index_usage);
-custom_err!(error_custom_event_not_allowed);
 custom_err!(error_invalid_table_id);
-custom_err!(error_leave_not_allowed);
+custom_err!(error_seat_seed_not_revealed);
+
+race_holdem_base::coded_err! {
+    error_custom_event_not_allowed => CustomEventNotAllowed = 1000,
+    error_leave_not_allowed => LeaveNotAllowed = 1001,
+    error_freeroll_with_ticket => FreerollWithTicket = 1002,
+    error_chop_not_available => ChopNotAvailable = 1003,
+    error_addon_price_mismatch => AddonPriceMismatch = 1004,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        let mut codes: Vec<u16> = ErrorCode::ALL.iter().map(|c| c.code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
+        assert_eq!(
+            ErrorCode::of(&error_leave_not_allowed()),
+            Some(ErrorCode::LeaveNotAllowed)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Independent Chip Model (ICM) for chopping the prizes.
+//!
+//! The chance of a player finishing at a position is the chance of
+//! the player winning among those not placed yet, which is the
+//! player's share of their chips.  The chances are propagated by the
+//! set of placed players, position by position.  To keep the cost
+//! reasonable, only the first `MAX_ICM_DEPTH` positions are calculated
+//! in this way, the prizes left are split equally among the players
+//! not placed.
+
+use std::collections::BTreeMap;
+
+/// The max number of positions calculated one by one
+const MAX_ICM_DEPTH: usize = 9;
+
+/// The max number of players, as the placed ones are kept in a bitmask
+const MAX_ICM_PLAYERS: usize = 64;
+
+fn unplaced_players(n: usize, mask: u64) -> Vec<usize> {
+    (0..n).filter(|i| mask & (1 << i) == 0).collect()
+}
+
+fn icm_equities(chips: &[u64], prizes: &[u64]) -> Vec<f64> {
+    let n = chips.len();
+    let mut equities = vec![0.0; n];
+    // Placed players (bitmask) -> the chance of reaching it.  An
+    // ordered map keeps the float operations in the same order on
+    // every node.
+    let mut reached = BTreeMap::from([(0u64, 1.0f64)]);
+    let depth = usize::min(prizes.len(), MAX_ICM_DEPTH);
+
+    for prize in prizes.iter().take(depth) {
+        let mut next = BTreeMap::new();
+        for (mask, prob) in reached.into_iter() {
+            let unplaced = unplaced_players(n, mask);
+            let total: u64 = unplaced.iter().map(|i| chips[*i]).sum();
+            for i in unplaced.iter().copied() {
+                // Players without chips share the chance only when
+                // nobody else has chips
+                let p = if total > 0 {
+                    prob * chips[i] as f64 / total as f64
+                } else {
+                    prob / unplaced.len() as f64
+                };
+                if p == 0.0 {
+                    continue;
+                }
+                equities[i] += p * *prize as f64;
+                *next.entry(mask | (1 << i)).or_insert(0.0) += p;
+            }
+        }
+        reached = next;
+    }
+
+    let rest: u64 = prizes.iter().skip(depth).sum();
+    if rest > 0 {
+        for (mask, prob) in reached.into_iter() {
+            let unplaced = unplaced_players(n, mask);
+            let share = rest as f64 / unplaced.len() as f64;
+            for i in unplaced {
+                equities[i] += prob * share;
+            }
+        }
+    }
+    equities
+}
+
+/// Compute the ICM payouts of players by their chips.  The
+/// `remaining_prizes` are the prizes not awarded yet, from the first
+/// position.  The payouts add up to the remaining prizes, players with
+/// equal stacks get equal equities and the odd chips go to the
+/// largest fractions.
+pub fn compute_icm_payouts(chips: &[u64], remaining_prizes: &[u64]) -> Vec<u64> {
+    if chips.is_empty() {
+        return vec![];
+    }
+    let prizes: Vec<u64> = remaining_prizes.iter().take(chips.len()).copied().collect();
+    let equities = if chips.len() > MAX_ICM_PLAYERS {
+        // Too many players, fall back to a chip chop
+        let total_chips: u64 = chips.iter().sum::<u64>().max(1);
+        let total: u64 = prizes.iter().sum();
+        chips
+            .iter()
+            .map(|c| total as f64 * *c as f64 / total_chips as f64)
+            .collect()
+    } else {
+        icm_equities(chips, &prizes)
+    };
+
+    // Average over equal stacks, to avoid the float errors
+    let averaged: Vec<f64> = chips
+        .iter()
+        .map(|c| {
+            let same: Vec<f64> = chips
+                .iter()
+                .zip(equities.iter())
+                .filter(|(c2, _)| *c2 == c)
+                .map(|(_, e)| *e)
+                .collect();
+            same.iter().sum::<f64>() / same.len() as f64
+        })
+        .collect();
+
+    let mut payouts: Vec<u64> = averaged.iter().map(|e| e.floor() as u64).collect();
+    let total: u64 = prizes.iter().sum();
+    let paid: u64 = payouts.iter().sum();
+    // Fractions are compared in micro-chips, the ties go to the larger stacks
+    let fraction = |i: usize| ((averaged[i] - averaged[i].floor()) * 1e6).round() as u64;
+    let mut by_fraction: Vec<usize> = (0..chips.len()).collect();
+    by_fraction.sort_by(|a, b| {
+        fraction(*b)
+            .cmp(&fraction(*a))
+            .then(chips[*b].cmp(&chips[*a]))
+    });
+    for i in by_fraction
+        .into_iter()
+        .cycle()
+        .take(total.saturating_sub(paid) as usize)
+    {
+        payouts[i] += 1;
+    }
+    payouts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icm_known_example() {
+        // Equities: 3839.29, 3275, 2885.71
+        let payouts = compute_icm_payouts(&[5000, 3000, 2000], &[5000, 3000, 2000]);
+        assert_eq!(payouts, vec![3839, 3275, 2886]);
+    }
+
+    #[test]
+    fn test_icm_equal_stacks() {
+        let payouts = compute_icm_payouts(&[1000, 1000, 1000], &[500, 300, 100]);
+        assert_eq!(payouts, vec![300, 300, 300]);
+
+        let payouts = compute_icm_payouts(&[4000, 2000, 2000], &[500, 300, 200]);
+        assert_eq!(payouts[1], payouts[2]);
+        assert_eq!(payouts.iter().sum::<u64>(), 1000);
+    }
+
+    #[test]
+    fn test_icm_many_players_sum_to_prizes() {
+        let chips: Vec<u64> = (1..=12).map(|i| i * 1000).collect();
+        let prizes: Vec<u64> = (1..=12).rev().map(|i| i * 100).collect();
+        let payouts = compute_icm_payouts(&chips, &prizes);
+        assert_eq!(payouts.iter().sum::<u64>(), prizes.iter().sum::<u64>());
+        // More chips, more equity
+        assert!(payouts.windows(2).all(|w| w[0] <= w[1]));
+    }
+}
@@ -17,28 +17,104 @@
 //! starts.  The same data structure as in cash table is used for each
 //! table in the tournament.
 //!
+//! The seats are drawn by a shuffle seeded with the start timestamp
+//! and the registered players, so the seating doesn't follow the
+//! registration order, but it can be reproduced from the game.
+//!
+//! Once the players are moved to the final table, the table is closed
+//! after the hand, and the survivors redraw their seats at a new
+//! table with their chips.
+//!
 //! ## Entry
 //!
 //! The supported entry type is `Ticket` which supports only one
 //! deposit amount.  After a player is eliminated, he can join again
 //! by rebuy a ticket with a same amount of deposit.  This must be
 //! done before `entry_close_time` or Final Table stage.  An invalid
-//! deposit will be rejected immediately.
+//! deposit will be rejected immediately.  With `max_entries` set, a
+//! player can enter at most such times, including the first entry.
+//!
+//! The add-on is offered once to each player for `ADDON_DURATION`
+//! from `addon_time`.  A deposit of `addon_price` from a player with
+//! chips in this window buys `addon_chips` onto the stack, and it
+//! doesn't count as an entry.  A deposit of another amount is
+//! rejected.
+//!
+//! With `latereg_avg_stack` enabled, a player entering during
+//! `Playing` stage receives the average stack of the alive players,
+//! if it's greater than `start_chips`.
+//!
+//! ## Blinds
+//!
+//! The blinds level raises by every `blind_interval` from the game
+//! start.  With `freeze_blinds_headsup` enabled, the blinds clock
+//! stops once the last two players play heads-up and no more entries
+//! are allowed.
+//!
+//! The `breaks` schedule pauses after some levels.  The blinds level
+//! doesn't advance during a break, and the tables wait for the break
+//! to end before starting the next hand.
+//!
+//! With the `ByHands` progression, the blinds level raises by every
+//! given number of hands completed across all tables instead.
 //!
 //! ## Settlement
 //!
 //! The game ends when only one player remains.  The prizes are
 //! distributed based on the proportion define in `prize_rules`(value
-//! by per thousand).
+//! by per thousand).  When fewer players than the prize slots entered,
+//! the shares of the unused slots are redistributed to the others in
+//! proportion, the whole prize pool is paid out anyway.
+//!
+//! In a freeroll, the entries are free and the prizes come from
+//! `freeroll_prize_pool` which is funded by the operator.
+//!
+//! With `guaranteed_prize` set, the prizes are distributed from at
+//! least the guaranteed amount.  When the entries fall short, the
+//! overlay is paid from `guarantee_fund`, which the organizer puts
+//! into the game account when creating it.  The overlay never
+//! exceeds the fund, and the unused fund is returned to the organizer
+//! at the settlement.  The rake is always taken from the actual
+//! entries.
+//!
+//! In a satellite, the game ends as soon as the number of remaining
+//! players drops to the number of seats, and each seat is an equal
+//! share of the prize pool.  When players are eliminated in the same
+//! hand on the bubble, the one who started the hand with more chips
+//! takes the seat.
+//!
+//! ## Chop
+//!
+//! At the final table, the remaining players can agree to chop by
+//! sending `MttEvent::AgreeChop`.  Once all of them agree, the game
+//! ends and the prizes left are paid by their ICM equities instead,
+//! see [`compute_icm_payouts`].
+//!
+//! ## Rake
+//!
+//! The `rake`(value by per thousand) is taken from each entry, the
+//! rest goes to the prize pool.  With `per_player_rake_cap` set, the
+//! rake collected from one player across all his entries never
+//! exceeds the cap, and the excess goes to the prize pool as well.
 
 mod errors;
+mod icm;
+
+pub use errors::ErrorCode;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use errors::error_leave_not_allowed;
 use race_api::prelude::*;
-use race_holdem_mtt_base::{ChipsChange, HoldemBridgeEvent, MttTablePlayer, MttTableState};
+use race_holdem_mtt_base::{
+    prizes_by_shares, ChipsChange, HoldemBridgeEvent, MttTablePlayer, MttTableState,
+};
 use race_proc_macro::game_handler;
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+
+pub use icm::compute_icm_payouts;
+
+/// How long the add-on is offered from `addon_time`
+pub const ADDON_DURATION: u64 = 5 * 60 * 1000;
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Default, Debug, Clone, Copy)]
 pub enum MttStage {
@@ -48,7 +124,7 @@ pub enum MttStage {
     Completed,
 }
 
-#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq, Default)]
+#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq, Default, Clone, Copy)]
 pub enum PlayerRankStatus {
     #[default]
     Alive,
@@ -61,6 +137,7 @@ pub struct PlayerRank {
     chips: u64,
     status: PlayerRankStatus,
     position: u16,
+    entries: u16, // the number of accepted entries
 }
 
 impl PlayerRank {
@@ -70,10 +147,27 @@ impl PlayerRank {
             chips,
             status,
             position,
+            entries: 0,
         }
     }
 }
 
+/// A player's line of the standings
+#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone)]
+pub struct MttStanding {
+    pub player_id: u64,
+    pub chips: u64,
+    pub status: PlayerRankStatus,
+    pub position: u16,
+    pub table_id: Option<GameId>, // None for the players not seated
+}
+
+/// The tournament standings, in the order of ranks
+#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq, Default, Clone)]
+pub struct MttStandings {
+    pub standings: Vec<MttStanding>,
+}
+
 #[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub struct PlayerRankCheckpoint {
     id: u64,
@@ -81,15 +175,20 @@ pub struct PlayerRankCheckpoint {
     table_id: GameId,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq, Default, Clone)]
 pub struct BlindRuleItem {
     sb_x: u32,
     bb_x: u32,
+    ante_x: u32,
 }
 
 impl BlindRuleItem {
     fn new(sb_x: u32, bb_x: u32) -> Self {
-        Self { sb_x, bb_x }
+        Self {
+            sb_x,
+            bb_x,
+            ante_x: 0,
+        }
     }
 }
 
@@ -105,11 +204,73 @@ fn default_blind_rules() -> Vec<BlindRuleItem> {
     .collect()
 }
 
+/// The random bytes to reveal for the seed of the seat draw
+const SEAT_SEED_BYTES: usize = 8;
+
+/// A shuffled list of all byte values, of which the first
+/// `SEAT_SEED_BYTES` seed the seat draw.
+fn seat_seed_spec() -> RandomSpec {
+    RandomSpec::shuffled_list((0..=255u8).map(|b| b.to_string()).collect())
+}
+
+/// Build the seed of the seat draw from the revealed bytes.
+fn seat_seed(revealed: &HashMap<usize, String>) -> HandleResult<u64> {
+    (0..SEAT_SEED_BYTES).try_fold(0u64, |seed, i| {
+        let byte = revealed
+            .get(&i)
+            .and_then(|b| b.parse::<u8>().ok())
+            .ok_or(errors::error_seat_seed_not_revealed())?;
+        Ok((seed << 8) | byte as u64)
+    })
+}
+
+/// Return a shuffled order of `n` seats, drawn by a splitmix64
+/// generator with `seed`.
+fn draw_seats(seed: u64, n: usize) -> Vec<usize> {
+    let mut state = seed;
+    let mut next_random = || {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    };
+    let mut order: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let j = (next_random() % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+    order
+}
+
+/// How the blinds levels advance
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum BlindProgression {
+    // A level lasts `blind_interval` msecs of play
+    #[default]
+    ByTime,
+    // A level lasts the number of hands completed across all tables
+    ByHands(u32),
+}
+
+/// A break of `duration` msecs once `after_level` levels are played
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
+pub struct BreakRule {
+    after_level: u32,
+    duration: u64,
+}
+
 #[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
 pub struct BlindInfo {
     blind_base: u64,
     blind_interval: u64,
     blind_rules: Vec<BlindRuleItem>,
+    // Step the ante towards next level's within a level
+    interpolate_antes: bool,
+    // Use a single BB ante instead of one ante from each player
+    bb_ante: bool,
+    breaks: Vec<BreakRule>,
+    progression: BlindProgression,
 }
 
 impl Default for BlindInfo {
@@ -118,6 +279,10 @@ impl Default for BlindInfo {
             blind_base: 10,
             blind_interval: 60_000,
             blind_rules: default_blind_rules(),
+            interpolate_antes: false,
+            bb_ante: false,
+            breaks: vec![],
+            progression: BlindProgression::ByTime,
         }
     }
 }
@@ -130,6 +295,24 @@ impl BlindInfo {
     }
 }
 
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq, Default, Clone, Copy)]
+pub enum PrizeMode {
+    // Prizes by `prize_rules`
+    #[default]
+    Graduated,
+    // The number of equal seats to award
+    Satellite(u16),
+}
+
+/// MTT specific custom events.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
+pub enum MttEvent {
+    // Agree to chop the prizes left by ICM
+    AgreeChop,
+}
+
+impl CustomEvent for MttEvent {}
+
 #[derive(Default, BorshSerialize, BorshDeserialize)]
 pub struct MttAccountData {
     start_time: u64,
@@ -141,6 +324,20 @@ pub struct MttAccountData {
     prize_rules: Vec<u8>,
     theme: Option<String>, // optional NFT theme
     subgame_bundle: String,
+    latereg_avg_stack: bool,
+    freeze_blinds_headsup: bool,
+    min_open_tables_during_latereg: u8,
+    reserve_per_table: u8, // the seats kept at each table for late registrants
+    freeroll_prize_pool: u64,
+    rake: u16,                // the rake (per thousand) of each entry
+    per_player_rake_cap: u64, // the max total rake from one player, 0 to disable
+    prize_mode: PrizeMode,
+    max_entries: u16, // the max entries of a player, 0 for unlimited
+    addon_time: u64,
+    addon_chips: u64,      // the chips of an add-on, 0 to disable
+    guaranteed_prize: u64, // the min prize pool, 0 for no guarantee
+    guarantee_fund: u64,   // held by the game account to cover the overlay
+    addon_price: u64,      // the deposit required for an add-on
 }
 
 #[derive(Debug, BorshSerialize, BorshDeserialize, Default)]
@@ -172,6 +369,30 @@ pub struct Mtt {
     subgame_bundle: String,
     winners: Vec<MttWinner>,
     launched_table_ids: Vec<GameId>,
+    latereg_avg_stack: bool,
+    freeze_blinds_headsup: bool,
+    min_open_tables_during_latereg: u8,
+    reserve_per_table: u8,
+    freeroll_prize_pool: u64,
+    rake: u16,
+    per_player_rake_cap: u64,
+    total_rake: u64,
+    player_rakes: BTreeMap<u64, u64>,
+    prize_mode: PrizeMode,
+    max_entries: u16,
+    chop_agreed: Vec<u64>, // the players agreed to chop
+    addon_time: u64,
+    addon_chips: u64,
+    addon_players: Vec<u64>, // the players took the add-on
+    guaranteed_prize: u64,
+    guarantee_fund: u64,
+    final_table_redrawn: bool,
+    hands_completed: u64, // the hands completed across all tables
+    table_hand_ids: BTreeMap<GameId, usize>, // the last completed hand of each table
+    addon_price: u64,
+    // The randomness to seed the first seat draw, revealed before the
+    // tables are created
+    seat_random_id: Option<RandomId>,
 }
 
 impl GameHandler for Mtt {
@@ -186,10 +407,28 @@ impl GameHandler for Mtt {
             prize_rules,
             theme,
             subgame_bundle,
+            latereg_avg_stack,
+            freeze_blinds_headsup,
+            min_open_tables_during_latereg,
+            reserve_per_table,
+            freeroll_prize_pool,
+            rake,
+            per_player_rake_cap,
+            prize_mode,
+            max_entries,
+            addon_time,
+            addon_chips,
+            guaranteed_prize,
+            guarantee_fund,
+            addon_price,
         } = init_account.data()?;
 
         blind_info.with_default_blind_rules();
 
+        if freeroll_prize_pool > 0 && ticket > 0 {
+            return Err(errors::error_freeroll_with_ticket());
+        }
+
         let state = Self {
             start_time,
             entry_close_time,
@@ -200,6 +439,21 @@ impl GameHandler for Mtt {
             ticket,
             theme,
             subgame_bundle,
+            latereg_avg_stack,
+            freeze_blinds_headsup,
+            min_open_tables_during_latereg,
+            reserve_per_table,
+            freeroll_prize_pool,
+            rake,
+            per_player_rake_cap,
+            prize_mode,
+            max_entries,
+            addon_time,
+            addon_chips,
+            guaranteed_prize,
+            guarantee_fund,
+            addon_price,
+            total_prize: freeroll_prize_pool,
             ..Default::default()
         };
 
@@ -209,19 +463,27 @@ impl GameHandler for Mtt {
     fn handle_event(&mut self, effect: &mut Effect, event: Event) -> Result<(), HandleError> {
         // Update time elapsed for blinds calculation.
         if self.stage == MttStage::Playing {
-            self.time_elapsed = self.time_elapsed + effect.timestamp() - self.timestamp;
+            if !self.is_blinds_frozen(effect.timestamp()) {
+                self.time_elapsed = self.time_elapsed + effect.timestamp() - self.timestamp;
+            }
             self.timestamp = effect.timestamp();
         }
 
         match event {
-            Event::Custom { .. } => {
-                return Err(errors::error_custom_event_not_allowed())?;
+            // Players only act while the tournament is played
+            Event::Custom { .. } if self.stage != MttStage::Playing => {
+                return Err(errors::error_custom_event_not_allowed());
             }
 
+            Event::Custom { sender, raw } => match MttEvent::try_parse(&raw)? {
+                MttEvent::AgreeChop => self.agree_chop(effect, sender)?,
+            },
+
             Event::Ready => {
                 match self.stage {
-                    // Schedule game start or start directly
-                    MttStage::Init => {
+                    // Schedule game start or start directly, unless the seat
+                    // draw is already pending
+                    MttStage::Init if self.seat_random_id.is_none() => {
                         if self.start_time > effect.timestamp {
                             effect.wait_timeout(self.start_time - effect.timestamp);
                         } else {
@@ -248,17 +510,24 @@ impl GameHandler for Mtt {
                             chips: 0,
                             status: PlayerRankStatus::Out,
                             position: p.position(),
+                            entries: 0,
                         });
                     }
                 }
                 MttStage::Playing => {
                     if !self.is_final_table && effect.timestamp() <= self.entry_close_time {
                         for p in players {
+                            // A busted player rejoining for rebuy keeps the rank
+                            if self.ranks.iter().any(|r| r.id == p.id()) {
+                                effect.info(format!("Player rejoins for rebuy: {}", p.id()));
+                                continue;
+                            }
                             self.ranks.push(PlayerRank {
                                 id: p.id(),
                                 chips: 0,
                                 status: PlayerRankStatus::Out,
                                 position: p.position(),
+                                entries: 0,
                             });
                         }
                     } else {
@@ -279,6 +548,19 @@ impl GameHandler for Mtt {
                 // For any case that the player is not in the game,
                 // the deposit should be rejected.
 
+                let (addons, deposits): (Vec<_>, Vec<_>) = deposits
+                    .into_iter()
+                    .partition(|d| self.is_addon(effect.timestamp(), d.id()));
+                for d in addons {
+                    if let Err(e) = self.accept_addon(effect, d.id(), d.balance()) {
+                        effect.warn(format!("Reject player add-on: {} ({:?})", d.id(), e));
+                        effect.reject_deposit(&d)?;
+                    } else {
+                        effect.info(format!("Accept player add-on: {}", d.id()));
+                        effect.accept_deposit(&d)?;
+                    }
+                }
+
                 if self.is_final_table {
                     for d in deposits {
                         effect.warn(format!(
@@ -295,28 +577,13 @@ impl GameHandler for Mtt {
                 } else {
                     for d in deposits {
                         let player_id = d.id();
-                        if let Some(rank) = self.ranks.iter_mut().find(|r| r.id == player_id) {
-                            if rank.chips == 0 {
-                                rank.chips = self.start_chips;
-                                rank.status = PlayerRankStatus::Alive;
-                                effect.info(format!("Accept player deposit: {}", d.id()));
-                                effect.accept_deposit(&d)?;
-                                self.total_prize += d.balance();
-                                self.add_new_player(effect, player_id)?;
-                                self.update_alives();
-                            } else {
-                                effect.warn(format!(
-                                    "Reject player deposit: {} (Player Has Chips)",
-                                    d.id()
-                                ));
-                                effect.reject_deposit(&d)?;
-                            }
-                        } else {
-                            effect.warn(format!(
-                                "Reject player deposit: {} (Player Not In Game)",
-                                d.id()
-                            ));
+                        if let Some(reason) = self.entry_rejection(player_id, d.balance()) {
+                            effect.warn(format!("Reject player deposit: {} ({})", d.id(), reason));
                             effect.reject_deposit(&d)?;
+                        } else {
+                            effect.info(format!("Accept player deposit: {}", d.id()));
+                            effect.accept_deposit(&d)?;
+                            self.accept_entry(effect, player_id, d.balance())?;
                         }
                     }
                 }
@@ -338,15 +605,30 @@ impl GameHandler for Mtt {
                         .info("Game has only one player, set it the winner and complete the game");
                     self.apply_prizes(effect)?;
                 } else {
-                    // Start game normally
+                    // The seats are drawn once the randomness is revealed
                     effect.info(format!("Start game with {} players", self.ranks.len()));
-                    self.stage = MttStage::Playing;
-                    self.create_tables(effect)?;
-                    self.update_alives();
+                    self.seat_random_id = Some(effect.init_random_state(seat_seed_spec()));
                 }
                 effect.checkpoint();
             }
 
+            Event::RandomnessReady { random_id } if self.seat_random_id == Some(random_id) => {
+                effect.reveal(random_id, (0..SEAT_SEED_BYTES).collect());
+            }
+
+            Event::SecretsReady { random_ids } if self.stage == MttStage::Init => {
+                let Some(random_id) = self.seat_random_id.filter(|id| random_ids.contains(id))
+                else {
+                    return Ok(());
+                };
+                let seed = seat_seed(effect.get_revealed(random_id)?)?;
+                self.timestamp = effect.timestamp();
+                self.stage = MttStage::Playing;
+                self.create_tables(effect, seed)?;
+                self.update_alives();
+                effect.checkpoint();
+            }
+
             Event::Bridge { raw, .. } => {
                 let bridge_event = HoldemBridgeEvent::try_parse(&raw)?;
                 match bridge_event {
@@ -355,12 +637,28 @@ impl GameHandler for Mtt {
                         chips_change,
                         table,
                         ..
+                    } if !self.tables.contains_key(&table_id) => {
+                        // The final result of a closed table, not to
+                        // be reseated
+                        self.apply_chips_change(effect, chips_change)?;
+                        if self.stage == MttStage::Playing {
+                            self.reconcile_closed_table(effect, table)?;
+                        }
+                        effect.checkpoint();
+                    }
+                    HoldemBridgeEvent::GameResult {
+                        hand_id,
+                        table_id,
+                        chips_change,
+                        table,
                     } => {
+                        self.count_hand(table_id, hand_id);
                         self.tables.insert(table_id, table);
-                        self.apply_chips_change(chips_change)?;
+                        self.apply_chips_change(effect, chips_change)?;
                         self.update_tables(effect, table_id)?;
                         self.apply_prizes(effect)?;
                         self.maybe_set_entry_close(effect);
+                        effect.info(format!("Standings: {:?}", self.standings()));
                         effect.checkpoint();
                     }
                     _ => return Err(errors::error_invalid_bridge_event()),
@@ -368,17 +666,27 @@ impl GameHandler for Mtt {
             }
 
             Event::SubGameReady { game_id } => {
-                self.launched_table_ids.push(game_id);
+                // The events may arrive in any order, or more than once
+                let Some(table) = self.tables.get(&game_id) else {
+                    effect.warn(format!("Ready event from unknown table: {}", game_id));
+                    return Ok(());
+                };
+                for p in table.players.iter() {
+                    self.table_assigns.insert(p.id, game_id);
+                }
+                if !self.launched_table_ids.contains(&game_id) {
+                    self.launched_table_ids.push(game_id);
+                }
                 effect.checkpoint();
             }
 
             Event::WaitingTimeout => match self.stage {
                 // Scheduled game start
-                MttStage::Init => {
+                MttStage::Init if self.seat_random_id.is_none() => {
                     effect.start_game();
                 }
 
-                MttStage::Playing => {}
+                MttStage::Init | MttStage::Playing => {}
 
                 MttStage::Completed => {}
             },
@@ -398,13 +706,18 @@ impl Mtt {
             .ok_or(errors::error_player_id_not_found())
     }
 
-    // When there is only one player alive, the game is over and the one is winner
+    // When there is only one player alive, the game is over and the one is winner.
+    // A satellite is over once the alive players are no more than the seats.
     fn has_winner(&self) -> bool {
-        self.ranks
+        let alives = self
+            .ranks
             .iter()
             .filter(|r| r.status == PlayerRankStatus::Alive)
-            .count()
-            == 1
+            .count();
+        match self.prize_mode {
+            PrizeMode::Graduated => alives == 1,
+            PrizeMode::Satellite(seats) => (1..=seats as usize).contains(&alives),
+        }
     }
 
     fn launch_table(&mut self, effect: &mut Effect, table: MttTableState) -> Result<(), HandleError> {
@@ -413,25 +726,26 @@ impl Mtt {
         Ok(())
     }
 
-    fn create_tables(&mut self, effect: &mut Effect) -> Result<(), HandleError> {
+    /// Create the tables with the seats drawn by `seed`.
+    fn create_tables(&mut self, effect: &mut Effect, seed: u64) -> Result<(), HandleError> {
         let num_of_players = self.ranks.len();
         let num_of_tables =
             (self.table_size as usize + num_of_players - 1) / self.table_size as usize;
+        let seat_order = draw_seats(seed, num_of_players);
         for i in 0..num_of_tables {
             let mut players = Vec::<MttTablePlayer>::new();
             let mut j = i;
-            let table_id = i + 1;
-            while let Some(r) = self.ranks.get(j as usize) {
+            let table_id = effect.next_sub_game_id();
+            while let Some(r) = seat_order.get(j).and_then(|k| self.ranks.get(*k)) {
                 players.push(MttTablePlayer::new(
                     r.id,
                     r.chips,
                     (j / num_of_tables) as usize, // player's table position
                 ));
-                self.table_assigns.insert(r.id, table_id as _);
+                self.table_assigns.insert(r.id, table_id);
                 j += num_of_tables;
             }
-            let (sb, bb) = self.calc_blinds()?;
-            let table_id = effect.next_sub_game_id();
+            let (sb, bb, ante) = self.calc_blinds()?;
             let table = MttTableState {
                 table_id: table_id.into(),
                 btn: 0,
@@ -440,10 +754,14 @@ impl Mtt {
                 players,
                 next_game_start: 0,
                 hand_id: 0,
+                ante,
+                bb_ante: self.blind_info.bb_ante,
             };
             self.launch_table(effect, table)?;
         }
 
+        // A single table is drawn already
+        self.final_table_redrawn = num_of_tables == 1;
         self.maybe_set_final_table();
 
         Ok(())
@@ -451,8 +769,11 @@ impl Mtt {
 
     fn apply_chips_change(
         &mut self,
+        effect: &mut Effect,
         chips_change: BTreeMap<u64, ChipsChange>,
     ) -> Result<(), HandleError> {
+        // The players eliminated in this hand with their chips before it
+        let mut eliminated = Vec::<(u64, u64)>::new();
         for (pid, change) in chips_change.into_iter() {
             let rank = self
                 .ranks
@@ -464,8 +785,16 @@ impl Mtt {
                     rank.chips += amount;
                 }
                 ChipsChange::Sub(amount) => {
-                    rank.chips -= amount;
+                    if amount > rank.chips {
+                        effect.warn(format!(
+                            "Player {} loses {} with {} chips",
+                            pid, amount, rank.chips
+                        ));
+                    }
+                    let chips_before = rank.chips;
+                    rank.chips = rank.chips.saturating_sub(amount);
                     if rank.chips == 0 {
+                        eliminated.push((rank.id, chips_before));
                         rank.status = PlayerRankStatus::Out;
                         // In such case, we want to unset player's assignment to table
                         self.table_assigns.remove(&rank.id);
@@ -474,6 +803,158 @@ impl Mtt {
             }
         }
         self.sort_ranks();
+        self.rank_eliminated(eliminated);
+        self.update_alives();
+        Ok(())
+    }
+
+    /// Reconcile the ranks with the final chips of a closed table.
+    /// Those seated at another table moved with their chips, the
+    /// others keep the chips they had when the table closed.
+    fn reconcile_closed_table(
+        &mut self,
+        effect: &mut Effect,
+        table: MttTableState,
+    ) -> Result<(), HandleError> {
+        let mut chips_change = BTreeMap::new();
+        for player in table.players {
+            let seated = self
+                .table_assigns
+                .get(&player.id)
+                .is_some_and(|id| self.tables.contains_key(id));
+            let Some(rank) = self
+                .ranks
+                .iter()
+                .find(|r| r.id == player.id && r.status == PlayerRankStatus::Alive)
+            else {
+                continue;
+            };
+            if seated || player.chips == rank.chips {
+                continue;
+            }
+            let change = if player.chips > rank.chips {
+                ChipsChange::Add(player.chips - rank.chips)
+            } else {
+                ChipsChange::Sub(rank.chips - player.chips)
+            };
+            chips_change.insert(player.id, change);
+        }
+        self.apply_chips_change(effect, chips_change)
+    }
+
+    /// Place the players eliminated in the same hand right behind the
+    /// players with chips, the more chips before the hand, the better
+    /// the finish.
+    fn rank_eliminated(&mut self, mut eliminated: Vec<(u64, u64)>) {
+        eliminated.sort_by(|(_, c1), (_, c2)| c2.cmp(c1));
+        let mut eliminated_ranks = Vec::with_capacity(eliminated.len());
+        for (id, _) in eliminated {
+            if let Some(i) = self.ranks.iter().position(|r| r.id == id) {
+                eliminated_ranks.push(self.ranks.remove(i));
+            }
+        }
+        let at = self.ranks.iter().take_while(|r| r.chips > 0).count();
+        self.ranks.splice(at..at, eliminated_ranks);
+    }
+
+    /// The chips granted to a new entry.  A late registrant receives
+    /// the average stack when `latereg_avg_stack` is enabled.
+    fn entry_chips(&self) -> u64 {
+        if !self.latereg_avg_stack || self.stage != MttStage::Playing {
+            return self.start_chips;
+        }
+
+        u64::max(self.average_stack(), self.start_chips)
+    }
+
+    /// Return the reason to reject an entry, None if it can be accepted.
+    fn entry_rejection(&self, player_id: u64, amount: u64) -> Option<&'static str> {
+        let Some(rank) = self.ranks.iter().find(|r| r.id == player_id) else {
+            return Some("Player Not In Game");
+        };
+        if self.freeroll_prize_pool > 0 && amount > 0 {
+            Some("Freeroll")
+        } else if self.max_entries > 0 && rank.entries >= self.max_entries {
+            Some("Max Entries")
+        } else if rank.chips > 0 {
+            Some("Player Has Chips")
+        } else {
+            None
+        }
+    }
+
+    /// Grant the entry chips to a player who has paid the ticket, and
+    /// seat it.
+    fn accept_entry(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+        amount: u64,
+    ) -> HandleResult<()> {
+        if let Some(rank) = self.ranks.iter_mut().find(|r| r.id == player_id) {
+            rank.entries += 1;
+        }
+        self.collect_entry(player_id, amount);
+        self.sit_in_player(effect, player_id)
+    }
+
+    /// Whether a deposit from the player is an add-on, which requires
+    /// the player to have chips.
+    fn is_addon(&self, timestamp: u64, player_id: u64) -> bool {
+        self.addon_chips > 0
+            && (self.addon_time..self.addon_time + ADDON_DURATION).contains(&timestamp)
+            && !self.addon_players.contains(&player_id)
+            && self
+                .get_rank(player_id)
+                .is_some_and(|r| r.status == PlayerRankStatus::Alive && r.chips > 0)
+    }
+
+    /// Add the chips of an add-on to the player's stack, the table is
+    /// notified as well.  The amount must be the add-on price.
+    fn accept_addon(
+        &mut self,
+        effect: &mut Effect,
+        player_id: u64,
+        amount: u64,
+    ) -> HandleResult<()> {
+        if amount != self.addon_price {
+            return Err(errors::error_addon_price_mismatch());
+        }
+        let Some(rank) = self.ranks.iter_mut().find(|r| r.id == player_id) else {
+            return Err(errors::error_player_id_not_found())?;
+        };
+        rank.chips += self.addon_chips;
+        self.addon_players.push(player_id);
+        self.collect_entry(player_id, amount);
+
+        if let Some(table_id) = self.table_assigns.get(&player_id) {
+            if let Some(player) = self
+                .tables
+                .get_mut(table_id)
+                .and_then(|t| t.players.iter_mut().find(|p| p.id == player_id))
+            {
+                player.chips += self.addon_chips;
+            }
+            effect.bridge_event(
+                *table_id as _,
+                HoldemBridgeEvent::AddChips {
+                    player_id,
+                    chips: self.addon_chips,
+                },
+            )?;
+        }
+        self.sort_ranks();
+        Ok(())
+    }
+
+    fn sit_in_player(&mut self, effect: &mut Effect, player_id: u64) -> HandleResult<()> {
+        let chips = self.entry_chips();
+        let Some(rank) = self.ranks.iter_mut().find(|r| r.id == player_id) else {
+            return Err(errors::error_player_id_not_found())?;
+        };
+        rank.chips = chips;
+        rank.status = PlayerRankStatus::Alive;
+        self.add_new_player(effect, player_id)?;
         self.update_alives();
         Ok(())
     }
@@ -490,17 +971,98 @@ impl Mtt {
         self.ranks.sort_by(|r1, r2| r2.chips.cmp(&r1.chips));
     }
 
-    fn calc_blinds(&self) -> Result<(u64, u64), HandleError> {
-        let time_elapsed = self.time_elapsed;
-        let level = time_elapsed / self.blind_info.blind_interval;
-        let mut blind_rule = self.blind_info.blind_rules.get(level as usize);
-        if blind_rule.is_none() {
-            blind_rule = self.blind_info.blind_rules.last();
+    /// Whether the blinds clock is frozen for the heads-up play.  It's
+    /// only frozen when nobody else can enter the game.
+    fn is_blinds_frozen(&self, timestamp: u64) -> bool {
+        self.freeze_blinds_headsup
+            && self.alives == 2
+            && self.tables.len() == 1
+            && (self.is_final_table || timestamp > self.entry_close_time)
+    }
+
+    /// Return the time played for the blinds levels, which excludes
+    /// the breaks, and the time left in current break.
+    fn calc_play_time(&self) -> (u64, u64) {
+        self.calc_play_time_at(self.time_elapsed)
+    }
+
+    fn calc_play_time_at(&self, time_elapsed: u64) -> (u64, u64) {
+        let blind_interval = self.blind_info.blind_interval;
+        let mut breaks: Vec<&BreakRule> = self.blind_info.breaks.iter().collect();
+        breaks.sort_by_key(|b| b.after_level);
+        let mut play_time = time_elapsed;
+        for b in breaks {
+            let break_start = b.after_level as u64 * blind_interval;
+            if play_time < break_start {
+                break;
+            }
+            let in_break = play_time - break_start;
+            if in_break < b.duration {
+                return (break_start, b.duration - in_break);
+            }
+            play_time -= b.duration;
+        }
+        (play_time, 0)
+    }
+
+    /// Return the time to start next hand, 0 if there's no break.
+    fn calc_start_time(&self) -> u64 {
+        match self.calc_play_time() {
+            (_, 0) => 0,
+            (_, break_left) => self.timestamp + break_left,
+        }
+    }
+
+    /// Count a completed hand by its id, a result of the same hand
+    /// from a table is counted once.
+    fn count_hand(&mut self, table_id: GameId, hand_id: usize) {
+        if self.table_hand_ids.insert(table_id, hand_id) != Some(hand_id) {
+            self.hands_completed += 1;
+        }
+    }
+
+    /// Return the progress in current level and the length of a
+    /// level, in msecs or hands by the blinds progression.
+    fn calc_level_progress(&self) -> (u64, u64) {
+        match self.blind_info.progression {
+            BlindProgression::ByTime => {
+                let (play_time, _) = self.calc_play_time();
+                let blind_interval = self.blind_info.blind_interval;
+                (play_time % blind_interval, blind_interval)
+            }
+            BlindProgression::ByHands(hands) => {
+                let hands = u64::max(hands as u64, 1);
+                (self.hands_completed % hands, hands)
+            }
+        }
+    }
+
+    /// Return SB, BB and ante of current level.  With
+    /// `interpolate_antes`, the ante steps linearly towards the next
+    /// level's ante by the progress in current level.
+    fn calc_blinds(&self) -> Result<(u64, u64, u64), HandleError> {
+        let blind_base = self.blind_info.blind_base;
+        let level = self.current_level_index();
+        let blind_rule = self
+            .blind_info
+            .blind_rules
+            .get(level)
+            .ok_or(errors::error_empty_blind_rules())?;
+        let sb = blind_rule.sb_x as u64 * blind_base;
+        let bb = blind_rule.bb_x as u64 * blind_base;
+        let mut ante = blind_rule.ante_x as u64 * blind_base;
+
+        if self.blind_info.interpolate_antes {
+            if let Some(next_rule) = self.blind_info.blind_rules.get(level as usize + 1) {
+                let next_ante = next_rule.ante_x as u64 * blind_base;
+                if next_ante > ante {
+                    let (level_elapsed, level_length) = self.calc_level_progress();
+                    ante += (next_ante - ante) * level_elapsed / level_length;
+                }
+            }
         }
-        let blind_rule = blind_rule.ok_or(errors::error_empty_blind_rules())?;
-        let sb = blind_rule.sb_x as u64 * self.blind_info.blind_base;
-        let bb = blind_rule.bb_x as u64 * self.blind_info.blind_base;
-        Ok((sb, bb))
+
+        Ok((sb, bb, ante))
     }
 
     /// Return the tables with least players and most players in a
@@ -636,13 +1198,15 @@ impl Mtt {
                 players: players.clone(),
             },
         )?;
-        let (sb, bb) = self.calc_blinds()?;
+        let (sb, bb, ante) = self.calc_blinds()?;
         effect.bridge_event(
             from_table_id as _,
             HoldemBridgeEvent::StartGame {
                 sb,
                 bb,
                 moved_players,
+                ante,
+                start_time: self.calc_start_time(),
             },
         )?;
 
@@ -658,7 +1222,10 @@ impl Mtt {
     /// When there are enough empty seats to accept all the players
     /// from current table, close current table and move players to
     /// empty seats. In this scenario, a CloseTable event and a few
-    /// Relocate events will be emitted.
+    /// Relocate events will be emitted.  While the entry is open,
+    /// `reserve_per_table` seats at each table are not counted as
+    /// empty, they are kept for late registrants.  A lone player is
+    /// moved to any empty seat, reserved or not.
     ///
     /// When current table is the one with most players and have at
     /// least two more players than the table with least payers, move
@@ -672,14 +1239,19 @@ impl Mtt {
             let Some((_, final_table)) = self.tables.first_key_value() else {
                 return Err(errors::error_table_not_fonud());
             };
+            if final_table.players.len() > 1 && !self.final_table_redrawn {
+                return self.redraw_final_table(effect);
+            }
             if final_table.players.len() > 1 {
-                let (sb, bb) = self.calc_blinds()?;
+                let (sb, bb, ante) = self.calc_blinds()?;
                 effect.bridge_event(
                     table_id as _,
                     HoldemBridgeEvent::StartGame {
                         sb,
                         bb,
                         moved_players: Vec::with_capacity(0),
+                        ante,
+                        start_time: self.calc_start_time(),
                     },
                 )?;
             }
@@ -700,6 +1272,14 @@ impl Mtt {
         let smallest_table_players_count = smallest_table.players.len();
         let largest_table_players_count = largest_table.players.len();
         let current_table_players_count = current_table.players.len();
+        let latereg_open = !self.is_final_table && effect.timestamp() <= self.entry_close_time;
+        // The seats reserved for late registrants are not taken,
+        // except by a lone player who can't play on
+        let reserved_seats = if latereg_open && current_table_players_count > 1 {
+            self.reserve_per_table as usize
+        } else {
+            0
+        };
         let total_empty_seats = self
             .tables
             .iter()
@@ -707,12 +1287,18 @@ impl Mtt {
                 if *id == table_id {
                     0
                 } else {
-                    table_size - t.players.len()
+                    table_size.saturating_sub(t.players.len() + reserved_seats)
                 }
             })
             .sum::<usize>();
 
-        if current_table_players_count <= total_empty_seats {
+        // Keep a minimum number of tables for late registrants, but
+        // never leave a single player waiting at a table
+        let keep_tables_open = latereg_open
+            && self.tables.len() <= self.min_open_tables_during_latereg as usize
+            && current_table_players_count > 1;
+
+        if current_table_players_count <= total_empty_seats && !keep_tables_open {
             self.close_table_and_move_players_to_other_tables(effect, table_id)?;
         } else if table_id == largest_table_id
             && largest_table_players_count > smallest_table_players_count + 1
@@ -732,13 +1318,15 @@ impl Mtt {
             // Otherwise this table should wait another table for
             // merging.
             if table.players.len() > 1 {
-                let (sb, bb) = self.calc_blinds()?;
+                let (sb, bb, ante) = self.calc_blinds()?;
                 effect.bridge_event(
                     table_id as _,
                     HoldemBridgeEvent::StartGame {
                         sb,
                         bb,
                         moved_players: Vec::with_capacity(0),
+                        ante,
+                        start_time: self.calc_start_time(),
                     },
                 )?;
             }
@@ -749,6 +1337,47 @@ impl Mtt {
         Ok(())
     }
 
+    /// Close the final table and launch a new one, where the
+    /// survivors redraw their seats with their chips.
+    fn redraw_final_table(&mut self, effect: &mut Effect) -> HandleResult<()> {
+        let Some((old_table_id, old_table)) = self.tables.pop_first() else {
+            return Err(errors::error_table_not_fonud());
+        };
+        effect.bridge_event(old_table_id as _, HoldemBridgeEvent::CloseTable)?;
+
+        let seed = old_table
+            .players
+            .iter()
+            .fold(effect.timestamp(), |seed, p| seed.rotate_left(5) ^ p.id);
+        let table_id = effect.next_sub_game_id();
+        let players: Vec<MttTablePlayer> = draw_seats(seed, old_table.players.len())
+            .into_iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let p = &old_table.players[k];
+                MttTablePlayer::new(p.id, p.chips, i)
+            })
+            .collect();
+        for p in players.iter() {
+            self.table_assigns.insert(p.id, table_id);
+        }
+
+        let (sb, bb, ante) = self.calc_blinds()?;
+        let table = MttTableState {
+            table_id,
+            btn: 0,
+            sb,
+            bb,
+            players,
+            next_game_start: 0,
+            hand_id: 0,
+            ante,
+            bb_ante: self.blind_info.bb_ante,
+        };
+        self.final_table_redrawn = true;
+        self.launch_table(effect, table)
+    }
+
     fn maybe_set_entry_close(&self, effect: &mut Effect) {
         effect.set_entry_lock(EntryLock::Closed);
     }
@@ -768,7 +1397,7 @@ impl Mtt {
             return Ok(());
         }
 
-        let (sb, bb) = self.calc_blinds()?;
+        let (sb, bb, ante) = self.calc_blinds()?;
 
         let Some(rank) = self.ranks.iter_mut().find(|r| r.id == player_id) else {
             return Err(errors::error_player_id_not_found())?;
@@ -776,20 +1405,31 @@ impl Mtt {
 
         let mut player = MttTablePlayer::new(rank.id, rank.chips, 0);
 
-        for (table_id, table) in self.tables.iter_mut() {
-            if table.players.len() < self.table_size as usize {
-                self.table_assigns.insert(player_id, *table_id);
-                table.add_player(&mut player);
+        // A busted player may still be listed at the previous table
+        for table in self.tables.values_mut() {
+            table.players.retain(|p| p.id != player_id);
+        }
 
-                effect.bridge_event(
-                    *table_id,
-                    HoldemBridgeEvent::Relocate {
-                        players: vec![player.clone()],
-                    },
-                )?;
-                effect.info(format!("Add player {} to table {}", player_id, table_id));
-                return Ok(());
-            }
+        // Seat the player at the table with the least players
+        let table_size = self.table_size as usize;
+        let sparse_table = self
+            .tables
+            .iter_mut()
+            .filter(|(_, t)| t.players.len() < table_size)
+            .min_by_key(|(_, t)| t.players.len());
+
+        if let Some((table_id, table)) = sparse_table {
+            self.table_assigns.insert(player_id, *table_id);
+            table.add_player(&mut player);
+
+            effect.bridge_event(
+                *table_id,
+                HoldemBridgeEvent::Relocate {
+                    players: vec![player.clone()],
+                },
+            )?;
+            effect.info(format!("Add player {} to table {}", player_id, table_id));
+            return Ok(());
         }
 
         // Table is full, create a new table with this player.
@@ -806,6 +1446,8 @@ impl Mtt {
             players,
             next_game_start: 0,
             hand_id: 0,
+            ante,
+            bb_ante: self.blind_info.bb_ante,
         };
 
         self.table_assigns.insert(player_id, table_id);
@@ -813,6 +1455,20 @@ impl Mtt {
         Ok(())
     }
 
+    /// Split an entry into rake and prize.  The rake is limited by
+    /// the rake taken from this player so far when
+    /// `per_player_rake_cap` is set.
+    fn collect_entry(&mut self, player_id: u64, amount: u64) {
+        let mut rake = amount * self.rake as u64 / 1000;
+        let player_rake = self.player_rakes.entry(player_id).or_insert(0);
+        if self.per_player_rake_cap > 0 {
+            rake = rake.min(self.per_player_rake_cap.saturating_sub(*player_rake));
+        }
+        *player_rake += rake;
+        self.total_rake += rake;
+        self.total_prize += amount - rake;
+    }
+
     /// Apply the prizes and mark the game as completed.
     fn apply_prizes(&mut self, effect: &mut Effect) -> HandleResult<()> {
         if !self.has_winner() {
@@ -820,72 +1476,282 @@ impl Mtt {
             return Ok(());
         }
 
-        let total_shares: u8 = self.prize_rules.iter().take(self.ranks.len()).sum();
-        let prize_share: u64 = self.total_prize / total_shares as u64;
-
-        // Get eligible ids for prizes
-        for (i, rank) in self.ranks.iter().enumerate() {
-            let id = rank.id;
-            if let Some(rule) = self.prize_rules.get(i) {
-                let prize: u64 = prize_share * *rule as u64;
-                self.winners.push(MttWinner {
-                    player_id: id,
-                    prize,
-                });
-                effect.settle(id, prize, false)?;
+        let prizes = match self.prize_mode {
+            PrizeMode::Graduated => self.position_prizes(),
+            PrizeMode::Satellite(seats) => {
+                for table_id in self.tables.keys() {
+                    effect.bridge_event(*table_id as _, HoldemBridgeEvent::CloseTable)?;
+                }
+                self.satellite_prizes(seats as usize)
             }
-        }
+        };
+        self.settle_prizes(effect, prizes)
+    }
 
-        self.stage = MttStage::Completed;
-        Ok(())
+    /// The prizes of the positions by the prize rules, one for each
+    /// rank that has a rule.
+    fn position_prizes(&self) -> Vec<u64> {
+        prizes_by_shares(self.prize_pool(), &self.prize_rules, self.ranks.len())
     }
 
-    pub fn get_rank(&self, id: u64) -> Option<&PlayerRank> {
-        self.ranks.iter().find(|r| r.id == id)
+    /// Award the seats to the top ranks equally, the odd chips go to
+    /// the first one.  The ranks keep the players eliminated in the
+    /// same hand in the order of their chips before the hand, thus the
+    /// bubble is broken by the chips.
+    fn satellite_prizes(&self, seats: usize) -> Vec<u64> {
+        let seats = usize::min(seats, self.ranks.len());
+        let prize = self.prize_pool() / seats as u64;
+        let odd_chips = self.prize_pool() % seats as u64;
+        (0..seats)
+            .map(|i| if i == 0 { prize + odd_chips } else { prize })
+            .collect()
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// Settle the prizes to the ranks in order, transfer the rake and
+    /// mark the game as completed.
+    fn settle_prizes(&mut self, effect: &mut Effect, prizes: Vec<u64>) -> HandleResult<()> {
+        if self.overlay() > 0 {
+            effect.info(format!("Overlay funded by organizer: {}", self.overlay()));
+        }
+        if self.total_prize + self.guarantee_fund < self.guaranteed_prize {
+            effect.warn(format!(
+                "Guarantee {} is short of funds: {}",
+                self.guaranteed_prize,
+                self.total_prize + self.guarantee_fund
+            ));
+        }
 
-    use super::*;
+        for (rank, prize) in self.ranks.iter().zip(prizes.into_iter()) {
+            self.winners.push(MttWinner {
+                player_id: rank.id,
+                prize,
+            });
+            effect.settle(rank.id, prize, false)?;
+        }
 
-    // Create Mtt with a number of players. If `with_tables` is
-    // true, also create MttTable states.
-    pub fn create_mtt_with_players(player_nums_per_table: &[usize], table_size: u8) -> Mtt {
-        let start_chips = 10000;
-        let mut mtt = Mtt {
-            table_size,
-            start_chips,
-            ..Default::default()
-        };
+        if self.total_rake > 0 {
+            effect.transfer(0, self.total_rake);
+        }
 
-        let mut rank_id = 1;
-        let mut table_id = 0;
+        // Return what's left of the organizer's fund
+        let unused_fund = self.guarantee_fund - self.overlay();
+        if unused_fund > 0 {
+            effect.transfer(0, unused_fund);
+        }
 
-        for player_num in player_nums_per_table {
-            table_id += 1;
-            let table_state = MttTableState {
-                table_id,
-                btn: 0,
-                sb: DEFAULT_SB,
-                bb: DEFAULT_BB,
-                players: vec![],
-                ..Default::default()
-            };
+        self.stage = MttStage::Completed;
+        Ok(())
+    }
 
-            mtt.tables.insert(table_id, table_state);
-            let table_state = mtt.tables.get_mut(&table_id).unwrap();
+    /// Record the agreement of a player to chop at the final table.
+    /// Once all the remaining players agree, the prizes are chopped.
+    fn agree_chop(&mut self, effect: &mut Effect, sender: u64) -> HandleResult<()> {
+        let is_alive = self
+            .get_rank(sender)
+            .is_some_and(|r| r.status == PlayerRankStatus::Alive);
+        if self.prize_mode != PrizeMode::Graduated || !self.is_final_table || !is_alive {
+            return Err(errors::error_chop_not_available());
+        }
 
-            for i in 0..*player_num {
-                mtt.ranks.push(PlayerRank {
-                    id: rank_id,
-                    chips: start_chips,
-                    status: PlayerRankStatus::Alive,
-                    position: rank_id as u16 % table_size as u16,
-                });
+        if !self.chop_agreed.contains(&sender) {
+            self.chop_agreed.push(sender);
+        }
 
-                let player = MttTablePlayer::new(rank_id, start_chips, i);
+        let all_agreed = self
+            .ranks
+            .iter()
+            .filter(|r| r.status == PlayerRankStatus::Alive)
+            .all(|r| self.chop_agreed.contains(&r.id));
+        if all_agreed {
+            self.apply_chop_prizes(effect)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the position prizes of the remaining players with
+    /// their ICM payouts.  The eliminated players keep their position
+    /// prizes.  The table is closed.
+    fn apply_chop_prizes(&mut self, effect: &mut Effect) -> HandleResult<()> {
+        let mut prizes = self.position_prizes();
+        let chips: Vec<u64> = self
+            .ranks
+            .iter()
+            .take_while(|r| r.status == PlayerRankStatus::Alive)
+            .map(|r| r.chips)
+            .collect();
+        let remaining_prizes: Vec<u64> = prizes.iter().take(chips.len()).copied().collect();
+        let payouts = compute_icm_payouts(&chips, &remaining_prizes);
+        prizes.resize(usize::max(prizes.len(), payouts.len()), 0);
+        prizes[..payouts.len()].copy_from_slice(&payouts);
+
+        effect.info(format!("Chop agreed by {:?}", self.chop_agreed));
+        for table_id in self.tables.keys() {
+            effect.bridge_event(*table_id as _, HoldemBridgeEvent::CloseTable)?;
+        }
+        self.settle_prizes(effect, prizes)
+    }
+
+    pub fn get_rank(&self, id: u64) -> Option<&PlayerRank> {
+        self.ranks.iter().find(|r| r.id == id)
+    }
+
+    /// The index of current blinds level, by the time played or the
+    /// hands completed.  It stays at the last level once the structure
+    /// runs out.
+    pub fn current_level_index(&self) -> usize {
+        let level = match self.blind_info.progression {
+            BlindProgression::ByTime => {
+                let (play_time, _) = self.calc_play_time();
+                (play_time / self.blind_info.blind_interval) as usize
+            }
+            BlindProgression::ByHands(hands) => {
+                (self.hands_completed / u64::max(hands as u64, 1)) as usize
+            }
+        };
+        usize::min(level, self.blind_info.blind_rules.len().saturating_sub(1))
+    }
+
+    /// Return the next blinds level and the msecs until it starts,
+    /// including the break left.  Once the structure caps at the last
+    /// level, the last level is returned with 0.  The msecs are also 0
+    /// when the levels advance by hands.
+    pub fn next_level_info(&self, now: u64) -> (BlindRuleItem, u64) {
+        if let BlindProgression::ByHands(_) = self.blind_info.progression {
+            let rules = &self.blind_info.blind_rules;
+            let next_rule = rules.get(self.current_level_index() + 1).or(rules.last());
+            return (next_rule.cloned().unwrap_or_default(), 0);
+        }
+        let mut time_elapsed = self.time_elapsed;
+        if self.stage == MttStage::Playing && !self.is_blinds_frozen(now) {
+            time_elapsed += now.saturating_sub(self.timestamp);
+        }
+        let (play_time, break_left) = self.calc_play_time_at(time_elapsed);
+        let blind_interval = self.blind_info.blind_interval;
+        let level = (play_time / blind_interval) as usize;
+        match self.blind_info.blind_rules.get(level + 1) {
+            Some(next_rule) => {
+                let level_left = (level as u64 + 1) * blind_interval - play_time;
+                (next_rule.clone(), level_left + break_left)
+            }
+            None => (
+                self.blind_info
+                    .blind_rules
+                    .last()
+                    .cloned()
+                    .unwrap_or_default(),
+                0,
+            ),
+        }
+    }
+
+    /// The prize pool to distribute, which is never less than the
+    /// guaranteed prize as far as the guarantee fund covers it.
+    pub fn prize_pool(&self) -> u64 {
+        self.total_prize + self.overlay()
+    }
+
+    /// The amount the organizer covers for the guarantee, limited to
+    /// the guarantee fund.
+    pub fn overlay(&self) -> u64 {
+        self.guaranteed_prize
+            .saturating_sub(self.total_prize)
+            .min(self.guarantee_fund)
+    }
+
+    /// The average chips of the alive players, 0 if there's none.
+    pub fn average_stack(&self) -> u64 {
+        let (total, count) = self
+            .ranks
+            .iter()
+            .filter(|r| r.status == PlayerRankStatus::Alive)
+            .fold((0, 0u64), |(total, count), r| (total + r.chips, count + 1));
+        if count == 0 {
+            0
+        } else {
+            total / count
+        }
+    }
+
+    /// The id of the alive player with the most chips.  The tie goes
+    /// to the higher rank.
+    pub fn chip_leader(&self) -> Option<u64> {
+        self.ranks
+            .iter()
+            .filter(|r| r.status == PlayerRankStatus::Alive)
+            .rev()
+            .max_by_key(|r| r.chips)
+            .map(|r| r.id)
+    }
+
+    /// The number of alive players.
+    pub fn players_remaining(&self) -> usize {
+        self.alives
+    }
+
+    /// The standings of all players, by chips from high to low.  The
+    /// eliminated players are `Out` with no chips.
+    pub fn standings(&self) -> MttStandings {
+        let standings = self
+            .ranks
+            .iter()
+            .map(|r| MttStanding {
+                player_id: r.id,
+                chips: if r.status == PlayerRankStatus::Out {
+                    0
+                } else {
+                    r.chips
+                },
+                status: r.status,
+                position: r.position,
+                table_id: self.table_assigns.get(&r.id).copied(),
+            })
+            .collect();
+        MttStandings { standings }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Create Mtt with a number of players. If `with_tables` is
+    // true, also create MttTable states.
+    pub fn create_mtt_with_players(player_nums_per_table: &[usize], table_size: u8) -> Mtt {
+        let start_chips = 10000;
+        let mut mtt = Mtt {
+            table_size,
+            start_chips,
+            ..Default::default()
+        };
+
+        let mut rank_id = 1;
+        let mut table_id = 0;
+
+        for player_num in player_nums_per_table {
+            table_id += 1;
+            let table_state = MttTableState {
+                table_id,
+                btn: 0,
+                sb: DEFAULT_SB,
+                bb: DEFAULT_BB,
+                players: vec![],
+                ..Default::default()
+            };
+
+            mtt.tables.insert(table_id, table_state);
+            let table_state = mtt.tables.get_mut(&table_id).unwrap();
+
+            for i in 0..*player_num {
+                mtt.ranks.push(PlayerRank {
+                    id: rank_id,
+                    chips: start_chips,
+                    status: PlayerRankStatus::Alive,
+                    position: rank_id as u16 % table_size as u16,
+                    entries: 1,
+                });
+
+                let player = MttTablePlayer::new(rank_id, start_chips, i);
                 table_state.players.push(player);
                 mtt.table_assigns.insert(rank_id, table_id);
                 rank_id += 1;
@@ -1149,6 +2015,8 @@ mod tests {
                         sb: DEFAULT_SB,
                         bb: DEFAULT_BB,
                         moved_players: vec![4],
+                        ante: 0,
+                        start_time: 0,
                     },
                 )
             ]
@@ -1200,8 +2068,8 @@ mod tests {
                     3,
                     HoldemBridgeEvent::Relocate {
                         players: vec![
-                            MttTablePlayer::new(3, 10000, 1),
-                            MttTablePlayer::new(0, 20000, 2),
+                            MttTablePlayer::new(3, 10000, 2),
+                            MttTablePlayer::new(0, 20000, 1),
                         ],
                     }
                 )
@@ -1257,6 +2125,254 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_keep_min_open_tables_until_latereg_closes() {
+        let mut mtt = create_mtt_with_players(&[2, 2], 3);
+        mtt.min_open_tables_during_latereg = 2;
+        mtt.entry_close_time = 100;
+
+        let game_result_event = |hand_id, chips_change| {
+            let game_result = HoldemBridgeEvent::GameResult {
+                hand_id,
+                table_id: 1,
+                chips_change,
+                table: MttTableState {
+                    hand_id,
+                    table_id: 1,
+                    players: vec![MttTablePlayer::new(1, 20000, 0)],
+                    ..Default::default()
+                },
+            };
+            Event::Bridge {
+                dest_game_id: 0,
+                from_game_id: 1,
+                raw: borsh::to_vec(&game_result).unwrap(),
+            }
+        };
+
+        // Late registration is open, table 1 is kept
+        let mut effect = Effect::default();
+        effect.timestamp = 50;
+        let chips_change =
+            BTreeMap::from([(1, ChipsChange::Add(10000)), (2, ChipsChange::Sub(10000))]);
+        mtt.handle_event(&mut effect, game_result_event(1, chips_change))
+            .unwrap();
+        assert_eq!(mtt.tables.len(), 2);
+        assert!(effect.bridge_events.is_empty());
+
+        // Late registration is closed, table 1 gets closed
+        let mut effect = Effect::default();
+        effect.timestamp = 200;
+        mtt.handle_event(&mut effect, game_result_event(2, BTreeMap::new()))
+            .unwrap();
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![
+                (1, HoldemBridgeEvent::CloseTable),
+                (
+                    2,
+                    HoldemBridgeEvent::Relocate {
+                        players: vec![MttTablePlayer::new(1, 20000, 2),],
+                    }
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reserve_seats_per_table_during_latereg() {
+        let game_result_event = || {
+            let game_result = HoldemBridgeEvent::GameResult {
+                hand_id: 1,
+                table_id: 1,
+                chips_change: BTreeMap::from([
+                    (1, ChipsChange::Add(10000)),
+                    (3, ChipsChange::Sub(10000)),
+                ]),
+                table: MttTableState {
+                    hand_id: 1,
+                    table_id: 1,
+                    players: vec![
+                        MttTablePlayer::new(1, 20000, 0),
+                        MttTablePlayer::new(2, 10000, 1),
+                    ],
+                    ..Default::default()
+                },
+            };
+            Event::Bridge {
+                dest_game_id: 0,
+                from_game_id: 1,
+                raw: borsh::to_vec(&game_result).unwrap(),
+            }
+        };
+
+        // One of the two seats left at table 2 is reserved, table 1
+        // is kept
+        let mut mtt = create_mtt_with_players(&[3, 2], 4);
+        mtt.reserve_per_table = 1;
+        mtt.entry_close_time = 100;
+        let mut effect = Effect::default();
+        effect.timestamp = 50;
+        mtt.handle_event(&mut effect, game_result_event()).unwrap();
+        assert_eq!(mtt.tables.len(), 2);
+
+        // Without reserved seats, the tables consolidate right away
+        let mut mtt = create_mtt_with_players(&[3, 2], 4);
+        mtt.reserve_per_table = 0;
+        mtt.entry_close_time = 100;
+        let mut effect = Effect::default();
+        effect.timestamp = 50;
+        mtt.handle_event(&mut effect, game_result_event()).unwrap();
+        assert_eq!(mtt.tables.len(), 1);
+        assert_eq!(mtt.table_assigns.get(&1), Some(&2));
+        assert_eq!(mtt.table_assigns.get(&2), Some(&2));
+    }
+
+    #[test]
+    fn test_lone_player_takes_reserved_seat_during_latereg() {
+        let mut mtt = create_mtt_with_players(&[2, 2], 3);
+        mtt.reserve_per_table = 1;
+        mtt.entry_close_time = 100;
+        let mut effect = Effect::default();
+        effect.timestamp = 50;
+        let game_result = HoldemBridgeEvent::GameResult {
+            hand_id: 1,
+            table_id: 1,
+            chips_change: BTreeMap::from([
+                (1, ChipsChange::Add(10000)),
+                (2, ChipsChange::Sub(10000)),
+            ]),
+            table: MttTableState {
+                hand_id: 1,
+                table_id: 1,
+                players: vec![MttTablePlayer::new(1, 20000, 0)],
+                ..Default::default()
+            },
+        };
+        let game_result_event = Event::Bridge {
+            dest_game_id: 0,
+            from_game_id: 1,
+            raw: borsh::to_vec(&game_result).unwrap(),
+        };
+        mtt.handle_event(&mut effect, game_result_event).unwrap();
+
+        // The only empty seat at table 2 is reserved, but the lone
+        // player is not left waiting
+        assert_eq!(mtt.tables.len(), 1);
+        assert_eq!(mtt.table_assigns.get(&1), Some(&2));
+        assert_eq!(mtt.tables.get(&2).unwrap().players.len(), 3);
+    }
+
+    #[test]
+    fn test_final_result_of_closed_table_not_reseated() {
+        let mut mtt = create_mtt_with_players(&[2, 2], 3);
+        mtt.stage = MttStage::Playing;
+        // Player 5 is not seated since table 3 closed
+        mtt.ranks
+            .push(PlayerRank::new(5, 10000, PlayerRankStatus::Alive, 0));
+        let mut effect = Effect::default();
+        // Table 3 was closed, player 1 is seated at table 1 now
+        let game_result = HoldemBridgeEvent::GameResult {
+            hand_id: 1,
+            table_id: 3,
+            chips_change: BTreeMap::new(),
+            table: MttTableState {
+                hand_id: 1,
+                table_id: 3,
+                players: vec![
+                    MttTablePlayer::new(1, 20000, 0),
+                    MttTablePlayer::new(5, 12000, 1),
+                ],
+                ..Default::default()
+            },
+        };
+        let game_result_event = Event::Bridge {
+            dest_game_id: 0,
+            from_game_id: 3,
+            raw: borsh::to_vec(&game_result).unwrap(),
+        };
+
+        mtt.handle_event(&mut effect, game_result_event).unwrap();
+
+        assert_eq!(mtt.tables.len(), 2);
+        assert_eq!(mtt.table_assigns.get(&1), Some(&1));
+        assert!(effect.bridge_events.is_empty());
+        // Player 1 moved with the chips, player 5 keeps the final chips
+        assert_eq!(mtt.get_rank(1).map(|r| r.chips), Some(10000));
+        assert_eq!(mtt.get_rank(5).map(|r| r.chips), Some(12000));
+    }
+
+    #[test]
+    fn test_lone_player_moved_while_keeping_min_open_tables() {
+        let mut mtt = create_mtt_with_players(&[3, 3, 2], 4);
+        mtt.min_open_tables_during_latereg = 3;
+        mtt.entry_close_time = 100;
+        let mut effect = Effect::default();
+        effect.timestamp = 50;
+
+        // Player 7 lost to player 8, who is left alone at table 3
+        let game_result = HoldemBridgeEvent::GameResult {
+            hand_id: 1,
+            table_id: 3,
+            chips_change: BTreeMap::from([
+                (7, ChipsChange::Sub(10000)),
+                (8, ChipsChange::Add(10000)),
+            ]),
+            table: MttTableState {
+                hand_id: 1,
+                table_id: 3,
+                players: vec![MttTablePlayer::new(8, 20000, 1)],
+                ..Default::default()
+            },
+        };
+        let game_result_event = Event::Bridge {
+            dest_game_id: 0,
+            from_game_id: 3,
+            raw: borsh::to_vec(&game_result).unwrap(),
+        };
+
+        mtt.handle_event(&mut effect, game_result_event).unwrap();
+
+        assert_eq!(mtt.tables.len(), 2);
+        assert_eq!(mtt.table_assigns.get(&8), Some(&1));
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![
+                (3, HoldemBridgeEvent::CloseTable),
+                (
+                    1,
+                    HoldemBridgeEvent::Relocate {
+                        players: vec![MttTablePlayer::new(8, 20000, 3)],
+                    }
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_out_of_order_sub_game_ready_keeps_assignments() {
+        let mut mtt = create_mtt_with_players(&[3, 3, 2], 3);
+        mtt.stage = MttStage::Playing;
+        // Partially populated assignments
+        mtt.table_assigns.remove(&4);
+        mtt.table_assigns.insert(7, 1);
+
+        for game_id in [3, 1, 3, 2] {
+            let mut effect = Effect::default();
+            mtt.handle_event(&mut effect, Event::SubGameReady { game_id })
+                .unwrap();
+            assert!(effect.is_checkpoint());
+        }
+
+        assert_eq!(mtt.launched_table_ids, vec![3, 1, 2]);
+        assert_eq!(mtt.table_assigns.len(), 8);
+        for (table_id, table) in mtt.tables.iter() {
+            for p in table.players.iter() {
+                assert_eq!(mtt.table_assigns.get(&p.id), Some(table_id));
+            }
+        }
+    }
+
     #[test]
     fn test_game_result_given_2_tables_move_one_player() {
         // Create three tables with number of players: 3, 3, 1
@@ -1302,6 +2418,8 @@ mod tests {
                         moved_players: vec![1],
                         sb: DEFAULT_SB,
                         bb: DEFAULT_BB,
+                        ante: 0,
+                        start_time: 0,
                     }
                 )
             ]
@@ -1315,6 +2433,690 @@ mod tests {
         assert_eq!(mtt.tables.get(&2).map(|t| t.players.len()), Some(2));
     }
 
+    #[test]
+    fn test_freeroll_distributes_prize_pool() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.freeroll_prize_pool = 1000;
+        mtt.total_prize = 1000;
+        mtt.prize_rules = vec![50, 30, 20];
+        mtt.ranks[1].chips = 0;
+        mtt.ranks[1].status = PlayerRankStatus::Out;
+        mtt.ranks[2].chips = 0;
+        mtt.ranks[2].status = PlayerRankStatus::Out;
+        let mut effect = Effect::default();
+
+        mtt.apply_prizes(&mut effect).unwrap();
+
+        assert_eq!(mtt.stage, MttStage::Completed);
+        let prizes: Vec<u64> = mtt.winners.iter().map(|w| w.prize).collect();
+        assert_eq!(prizes, vec![500, 300, 200]);
+    }
+
+    #[test]
+    fn test_position_prizes_by_number_of_players() {
+        // Exactly as many players as prize slots
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.total_prize = 1000;
+        mtt.prize_rules = vec![50, 30, 20];
+        assert_eq!(mtt.position_prizes(), vec![500, 300, 200]);
+
+        // Fewer players, the unused share is redistributed and the odd
+        // chip goes to the first
+        let mut mtt = create_mtt_with_players(&[2], 6);
+        mtt.total_prize = 1001;
+        mtt.prize_rules = vec![50, 30, 20];
+        assert_eq!(mtt.position_prizes(), vec![626, 375]);
+
+        // More players, the ones out of the slots get nothing
+        let mut mtt = create_mtt_with_players(&[5], 6);
+        mtt.total_prize = 1000;
+        mtt.prize_rules = vec![50, 30, 20];
+        assert_eq!(mtt.position_prizes(), vec![500, 300, 200]);
+    }
+
+    #[test]
+    fn test_satellite_ends_when_seats_left() {
+        let mut mtt = create_mtt_with_players(&[3, 2], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.prize_mode = PrizeMode::Satellite(3);
+        mtt.total_prize = 3001;
+        let mut effect = Effect::default();
+
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(1, ChipsChange::Add(10000)), (4, ChipsChange::Sub(10000))]),
+        )
+        .unwrap();
+        mtt.apply_prizes(&mut effect).unwrap();
+        assert_eq!(mtt.stage, MttStage::Playing);
+
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(2, ChipsChange::Add(10000)), (5, ChipsChange::Sub(10000))]),
+        )
+        .unwrap();
+        mtt.apply_prizes(&mut effect).unwrap();
+
+        assert_eq!(mtt.stage, MttStage::Completed);
+        let winners: Vec<(u64, u64)> = mtt.winners.iter().map(|w| (w.player_id, w.prize)).collect();
+        assert_eq!(winners, vec![(1, 1001), (2, 1000), (3, 1000)]);
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![
+                (1, HoldemBridgeEvent::CloseTable),
+                (2, HoldemBridgeEvent::CloseTable)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_satellite_bubble_broken_by_chips() {
+        let mut mtt = create_mtt_with_players(&[4], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.prize_mode = PrizeMode::Satellite(3);
+        mtt.total_prize = 3000;
+        let mut effect = Effect::default();
+
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(3, ChipsChange::Add(2000)), (4, ChipsChange::Sub(2000))]),
+        )
+        .unwrap();
+
+        // Players 3 and 4 go all in and lose on the bubble
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([
+                (1, ChipsChange::Add(20000)),
+                (3, ChipsChange::Sub(12000)),
+                (4, ChipsChange::Sub(8000)),
+            ]),
+        )
+        .unwrap();
+        mtt.apply_prizes(&mut effect).unwrap();
+
+        assert_eq!(mtt.stage, MttStage::Completed);
+        let winners: Vec<(u64, u64)> = mtt.winners.iter().map(|w| (w.player_id, w.prize)).collect();
+        assert_eq!(winners, vec![(1, 1000), (2, 1000), (3, 1000)]);
+    }
+
+    #[test]
+    fn test_chop_by_icm_when_all_agree() {
+        let mut mtt = create_mtt_with_players(&[5], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.is_final_table = true;
+        mtt.prize_rules = vec![40, 25, 15, 12, 8];
+        mtt.total_prize = 10000;
+        let mut effect = Effect::default();
+
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([
+                (1, ChipsChange::Add(20000)),
+                (4, ChipsChange::Sub(10000)),
+                (5, ChipsChange::Sub(10000)),
+            ]),
+        )
+        .unwrap();
+
+        let agree_chop = |sender| Event::Custom {
+            sender,
+            raw: borsh::to_vec(&MttEvent::AgreeChop).unwrap(),
+        };
+
+        // An eliminated player can't agree
+        assert_eq!(
+            mtt.handle_event(&mut effect, agree_chop(4)),
+            Err(errors::error_chop_not_available())
+        );
+
+        mtt.handle_event(&mut effect, agree_chop(1)).unwrap();
+        mtt.handle_event(&mut effect, agree_chop(2)).unwrap();
+        assert_eq!(mtt.stage, MttStage::Playing);
+
+        mtt.handle_event(&mut effect, agree_chop(3)).unwrap();
+        assert_eq!(mtt.stage, MttStage::Completed);
+        let winners: Vec<(u64, u64)> = mtt.winners.iter().map(|w| (w.player_id, w.prize)).collect();
+        assert_eq!(
+            winners,
+            vec![(1, 3300), (2, 2350), (3, 2350), (4, 1200), (5, 800)]
+        );
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![(1, HoldemBridgeEvent::CloseTable)]
+        );
+
+        // Nothing to agree once the tournament is completed
+        assert_eq!(
+            mtt.handle_event(&mut effect, agree_chop(1)),
+            Err(errors::error_custom_event_not_allowed())
+        );
+    }
+
+    #[test]
+    fn test_guaranteed_prize_covers_overlay() {
+        let mut mtt = create_mtt_with_players(&[2], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.prize_rules = vec![70, 30];
+        mtt.rake = 100;
+        mtt.guaranteed_prize = 5000;
+        mtt.guarantee_fund = 5000;
+        mtt.collect_entry(1, 1000);
+        mtt.collect_entry(2, 1000);
+        assert_eq!(mtt.total_prize, 1800);
+        assert_eq!(mtt.total_rake, 200);
+        assert_eq!(mtt.prize_pool(), 5000);
+        assert_eq!(mtt.overlay(), 3200);
+        let mut effect = Effect::default();
+
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(1, ChipsChange::Add(10000)), (2, ChipsChange::Sub(10000))]),
+        )
+        .unwrap();
+        mtt.apply_prizes(&mut effect).unwrap();
+
+        assert_eq!(mtt.stage, MttStage::Completed);
+        let winners: Vec<(u64, u64)> = mtt.winners.iter().map(|w| (w.player_id, w.prize)).collect();
+        assert_eq!(winners, vec![(1, 3500), (2, 1500)]);
+        // The rake is from the entries only
+        assert_eq!(mtt.total_rake, 200);
+    }
+
+    #[test]
+    fn test_overlay_limited_to_guarantee_fund() {
+        let mut mtt = create_mtt_with_players(&[2], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.prize_rules = vec![70, 30];
+        mtt.guaranteed_prize = 5000;
+        mtt.guarantee_fund = 1000;
+        mtt.collect_entry(1, 1000);
+        mtt.collect_entry(2, 1000);
+        // Never settle more than the entries and the fund
+        assert_eq!(mtt.overlay(), 1000);
+        assert_eq!(mtt.prize_pool(), 3000);
+
+        // The entries beat the guarantee, the fund is not used
+        mtt.collect_entry(1, 4000);
+        assert_eq!(mtt.overlay(), 0);
+        assert_eq!(mtt.prize_pool(), 6000);
+    }
+
+    #[test]
+    fn test_addon_stacks_onto_chips() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.ticket = 100;
+        mtt.addon_time = 1_000_000;
+        mtt.addon_chips = 15000;
+        mtt.addon_price = 100;
+        let mut effect = Effect::default();
+
+        assert!(!mtt.is_addon(mtt.addon_time - 1, 1));
+        assert!(mtt.is_addon(mtt.addon_time, 1));
+        assert_eq!(
+            mtt.accept_addon(&mut effect, 1, 50),
+            Err(errors::error_addon_price_mismatch())
+        );
+        assert_eq!(mtt.get_rank(1).unwrap().chips, 10000);
+        mtt.accept_addon(&mut effect, 1, 100).unwrap();
+
+        let rank = mtt.get_rank(1).unwrap();
+        assert_eq!(rank.chips, 25000);
+        assert_eq!(rank.entries, 1);
+        assert_eq!(mtt.total_prize, 100);
+        assert_eq!(mtt.tables[&1].players[0].chips, 25000);
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![(
+                1,
+                HoldemBridgeEvent::AddChips {
+                    player_id: 1,
+                    chips: 15000
+                }
+            )]
+        );
+
+        // Only one add-on for each player
+        assert!(!mtt.is_addon(mtt.addon_time, 1));
+        assert!(mtt.is_addon(mtt.addon_time + ADDON_DURATION - 1, 2));
+        assert!(!mtt.is_addon(mtt.addon_time + ADDON_DURATION, 2));
+    }
+
+    #[test]
+    fn test_stats_of_empty_tournament() {
+        let mtt = Mtt::default();
+        assert_eq!(mtt.average_stack(), 0);
+        assert_eq!(mtt.chip_leader(), None);
+        assert_eq!(mtt.players_remaining(), 0);
+    }
+
+    #[test]
+    fn test_stats_exclude_busted_players() {
+        let mut mtt = create_mtt_with_players(&[3, 3], 6);
+        let mut effect = Effect::default();
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([
+                (1, ChipsChange::Sub(10000)),
+                (2, ChipsChange::Add(15000)),
+                (3, ChipsChange::Sub(5000)),
+            ]),
+        )
+        .unwrap();
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(4, ChipsChange::Add(10000)), (5, ChipsChange::Sub(10000))]),
+        )
+        .unwrap();
+
+        assert_eq!(mtt.players_remaining(), 4);
+        // (25000 + 5000 + 20000 + 10000) / 4
+        assert_eq!(mtt.average_stack(), 15000);
+        assert_eq!(mtt.chip_leader(), Some(2));
+    }
+
+    #[test]
+    fn test_chips_loss_exceeding_stack_marks_out() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        let mut effect = Effect::default();
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(1, ChipsChange::Add(10000)), (2, ChipsChange::Sub(12000))]),
+        )
+        .unwrap();
+
+        let rank = mtt.get_rank(2).unwrap();
+        assert_eq!(rank.chips, 0);
+        assert_eq!(rank.status, PlayerRankStatus::Out);
+        assert_eq!(mtt.table_assigns.get(&2), None);
+        assert_eq!(mtt.players_remaining(), 2);
+    }
+
+    #[test]
+    fn test_simultaneous_eliminations_ranked_by_chips() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.prize_rules = vec![50, 30, 20];
+        mtt.total_prize = 1000;
+        let mut effect = Effect::default();
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(2, ChipsChange::Sub(4000)), (3, ChipsChange::Add(4000))]),
+        )
+        .unwrap();
+
+        // Both bust in the same hand, player 3 with more chips
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([
+                (1, ChipsChange::Add(20000)),
+                (2, ChipsChange::Sub(6000)),
+                (3, ChipsChange::Sub(14000)),
+            ]),
+        )
+        .unwrap();
+        mtt.apply_prizes(&mut effect).unwrap();
+
+        let ids: Vec<u64> = mtt.ranks.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 3, 2]);
+        let winners: Vec<(u64, u64)> = mtt.winners.iter().map(|w| (w.player_id, w.prize)).collect();
+        assert_eq!(winners, vec![(1, 500), (3, 300), (2, 200)]);
+    }
+
+    #[test]
+    fn test_standings_follow_ranks() {
+        let mut mtt = create_mtt_with_players(&[3, 2], 6);
+        let mut effect = Effect::default();
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([
+                (1, ChipsChange::Sub(10000)),
+                (2, ChipsChange::Add(10000)),
+                (4, ChipsChange::Sub(5000)),
+            ]),
+        )
+        .unwrap();
+
+        let standings = mtt.standings().standings;
+        let ids: Vec<u64> = standings.iter().map(|s| s.player_id).collect();
+        let rank_ids: Vec<u64> = mtt.ranks.iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![2, 3, 5, 4, 1]);
+        assert_eq!(ids, rank_ids);
+        assert!(standings.windows(2).all(|w| w[0].chips >= w[1].chips));
+        assert_eq!(standings[3].table_id, Some(2));
+        assert_eq!(
+            standings[4],
+            MttStanding {
+                player_id: 1,
+                chips: 0,
+                status: PlayerRankStatus::Out,
+                position: 1,
+                table_id: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_per_player_rake_cap_across_reentries() {
+        let mut mtt = create_mtt_with_players(&[2], 6);
+        mtt.rake = 100;
+        mtt.per_player_rake_cap = 250;
+
+        // Player 1 enters five times, player 2 enters once
+        for _ in 0..5 {
+            mtt.collect_entry(1, 1000);
+        }
+        mtt.collect_entry(2, 1000);
+
+        assert_eq!(mtt.player_rakes.get(&1), Some(&250));
+        assert_eq!(mtt.player_rakes.get(&2), Some(&100));
+        assert_eq!(mtt.total_rake, 350);
+        assert_eq!(mtt.total_prize, 6000 - 350);
+
+        // Without a cap, every entry is raked
+        mtt.per_player_rake_cap = 0;
+        mtt.collect_entry(1, 1000);
+        assert_eq!(mtt.player_rakes.get(&1), Some(&350));
+        assert_eq!(mtt.total_prize, 7000 - 450);
+    }
+
+    #[test]
+    fn test_interpolate_antes_mid_level() {
+        let mut mtt = Mtt::default();
+        mtt.blind_info = BlindInfo {
+            blind_base: 10,
+            blind_interval: 60_000,
+            blind_rules: vec![
+                BlindRuleItem {
+                    sb_x: 5,
+                    bb_x: 10,
+                    ante_x: 1,
+                },
+                BlindRuleItem {
+                    sb_x: 10,
+                    bb_x: 20,
+                    ante_x: 3,
+                },
+            ],
+            interpolate_antes: false,
+            bb_ante: false,
+            breaks: vec![],
+            progression: BlindProgression::ByTime,
+        };
+        mtt.time_elapsed = 30_000;
+
+        // Constant ante per level by default
+        assert_eq!(mtt.calc_blinds().unwrap(), (50, 100, 10));
+
+        mtt.blind_info.interpolate_antes = true;
+        assert_eq!(mtt.calc_blinds().unwrap(), (50, 100, 20));
+
+        // No more stepping at the last level
+        mtt.time_elapsed = 90_000;
+        assert_eq!(mtt.calc_blinds().unwrap(), (100, 200, 30));
+    }
+
+    #[test]
+    fn test_blinds_level_constant_across_break() {
+        let mut mtt = Mtt::default();
+        mtt.blind_info.breaks = vec![BreakRule {
+            after_level: 1,
+            duration: 30_000,
+        }];
+        mtt.timestamp = 1_000_000;
+
+        mtt.time_elapsed = 59_000;
+        assert_eq!(mtt.calc_blinds().unwrap(), (50, 100, 0));
+        assert_eq!(mtt.calc_start_time(), 0);
+
+        // The break from 60s to 90s
+        for time_elapsed in [60_000, 75_000, 89_999, 90_000, 149_999] {
+            mtt.time_elapsed = time_elapsed;
+            assert_eq!(mtt.calc_blinds().unwrap(), (100, 200, 0));
+        }
+        mtt.time_elapsed = 75_000;
+        assert_eq!(mtt.calc_start_time(), 1_015_000);
+
+        mtt.time_elapsed = 150_000;
+        assert_eq!(mtt.calc_blinds().unwrap(), (150, 300, 0));
+        assert_eq!(mtt.calc_start_time(), 0);
+    }
+
+    #[test]
+    fn test_blinds_level_by_hands() {
+        let mut mtt = Mtt::default();
+        mtt.blind_info.progression = BlindProgression::ByHands(3);
+        // The time doesn't matter
+        mtt.time_elapsed = 600_000;
+        assert_eq!(mtt.calc_blinds().unwrap(), (50, 100, 0));
+
+        mtt.count_hand(1, 1);
+        mtt.count_hand(2, 1);
+        // A result of the same hand is counted once
+        mtt.count_hand(2, 1);
+        assert_eq!(mtt.hands_completed, 2);
+        assert_eq!(mtt.calc_blinds().unwrap(), (50, 100, 0));
+
+        mtt.count_hand(1, 2);
+        assert_eq!(mtt.current_level_index(), 1);
+        assert_eq!(mtt.calc_blinds().unwrap(), (100, 200, 0));
+        assert_eq!(mtt.next_level_info(0), (BlindRuleItem::new(150, 300), 0));
+    }
+
+    #[test]
+    fn test_final_table_redraw_keeps_survivors() {
+        let mut mtt = create_mtt_with_players(&[4], 6);
+        let mut effect = Effect::default();
+        mtt.stage = MttStage::Playing;
+        mtt.apply_chips_change(
+            &mut effect,
+            BTreeMap::from([(1, ChipsChange::Add(10000)), (3, ChipsChange::Sub(10000))]),
+        )
+        .unwrap();
+        mtt.tables
+            .get_mut(&1)
+            .unwrap()
+            .players
+            .retain(|p| p.id != 3);
+        let mut effect = Effect::default();
+
+        mtt.update_tables(&mut effect, 1).unwrap();
+
+        assert_eq!(
+            effect.list_bridge_events().unwrap(),
+            vec![(1, HoldemBridgeEvent::CloseTable)]
+        );
+        assert_eq!(mtt.tables.len(), 1);
+        let (table_id, table) = mtt.tables.first_key_value().unwrap();
+        assert_ne!(*table_id, 1);
+        let mut ids: Vec<u64> = table.players.iter().map(|p| p.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 4]);
+        for (i, p) in table.players.iter().enumerate() {
+            assert_eq!(p.table_position, i);
+            assert_eq!(p.chips, mtt.get_rank(p.id).unwrap().chips);
+            assert_eq!(mtt.table_assigns.get(&p.id), Some(table_id));
+        }
+        assert_eq!(mtt.table_assigns.len(), 3);
+
+        // Redraw only once
+        let table_id = *table_id;
+        let mut effect = Effect::default();
+        mtt.update_tables(&mut effect, table_id).unwrap();
+        assert_eq!(mtt.tables.len(), 1);
+        assert!(mtt.tables.contains_key(&table_id));
+    }
+
+    #[test]
+    fn test_add_player_keeps_free_position() {
+        let mut table = MttTableState {
+            players: vec![
+                MttTablePlayer::new(1, 10000, 0),
+                MttTablePlayer::new(2, 10000, 2),
+            ],
+            ..Default::default()
+        };
+
+        // The position at the previous table is free
+        let mut player = MttTablePlayer::new(3, 10000, 3);
+        table.add_player(&mut player);
+        assert_eq!(player.table_position, 3);
+
+        // Taken, the first free position instead
+        let mut player = MttTablePlayer::new(4, 10000, 2);
+        table.add_player(&mut player);
+        assert_eq!(player.table_position, 1);
+        assert_eq!(
+            table.players.last(),
+            Some(&MttTablePlayer::new(4, 10000, 1))
+        );
+    }
+
+    #[test]
+    fn test_draw_seats_is_reproducible() {
+        let order = draw_seats(42, 10);
+        assert_eq!(order, draw_seats(42, 10));
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..10).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_create_tables_with_random_seats_balanced() {
+        let mut mtt = Mtt {
+            table_size: 6,
+            ..Default::default()
+        };
+        for id in 1..=20 {
+            mtt.ranks
+                .push(PlayerRank::new(id, 10000, PlayerRankStatus::Alive, 0));
+        }
+        let mut effect = Effect::default();
+        mtt.create_tables(&mut effect, 42).unwrap();
+
+        assert_eq!(mtt.tables.len(), 4);
+        assert_eq!(mtt.table_assigns.len(), 20);
+        let sizes: Vec<usize> = mtt.tables.values().map(|t| t.players.len()).collect();
+        let min_size = sizes.iter().min().unwrap();
+        let max_size = sizes.iter().max().unwrap();
+        assert!(max_size - min_size <= 1);
+        for table in mtt.tables.values() {
+            for (i, p) in table.players.iter().enumerate() {
+                assert_eq!(p.table_position, i);
+                assert_eq!(mtt.table_assigns.get(&p.id), Some(&table.table_id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_game_start_draws_seats_from_revealed_randomness() {
+        let mut mtt = Mtt {
+            table_size: 6,
+            ..Default::default()
+        };
+        for id in 1..=8 {
+            mtt.ranks
+                .push(PlayerRank::new(id, 10000, PlayerRankStatus::Alive, 0));
+        }
+        let mut effect = Effect::default();
+        mtt.handle_event(&mut effect, Event::GameStart).unwrap();
+        assert_eq!(mtt.stage, MttStage::Init);
+        assert!(mtt.tables.is_empty());
+        assert_eq!(effect.init_random_states.len(), 1);
+        let random_id = mtt.seat_random_id.unwrap();
+
+        // No restart while the draw is pending
+        let mut effect = Effect::default();
+        mtt.handle_event(&mut effect, Event::Ready).unwrap();
+        assert!(!effect.start_game);
+
+        let mut effect = Effect::default();
+        mtt.handle_event(&mut effect, Event::RandomnessReady { random_id })
+            .unwrap();
+        assert_eq!(effect.reveals.len(), 1);
+        assert_eq!(
+            effect.reveals[0].indexes,
+            (0..SEAT_SEED_BYTES).collect::<Vec<_>>()
+        );
+
+        let mut effect = Effect::default();
+        effect.revealed.insert(
+            random_id,
+            (0..SEAT_SEED_BYTES)
+                .map(|i| (i, (i * 31).to_string()))
+                .collect(),
+        );
+        mtt.handle_event(
+            &mut effect,
+            Event::SecretsReady {
+                random_ids: vec![random_id],
+            },
+        )
+        .unwrap();
+        assert_eq!(mtt.stage, MttStage::Playing);
+        assert_eq!(mtt.tables.len(), 2);
+        assert_eq!(mtt.table_assigns.len(), 8);
+    }
+
+    #[test]
+    fn test_next_level_info_at_boundaries() {
+        let mut mtt = Mtt::default();
+        mtt.stage = MttStage::Playing;
+        mtt.timestamp = 1_000_000;
+        mtt.blind_info.blind_rules = vec![
+            BlindRuleItem::new(5, 10),
+            BlindRuleItem::new(10, 20),
+            BlindRuleItem::new(15, 30),
+        ];
+
+        assert_eq!(mtt.current_level_index(), 0);
+        assert_eq!(
+            mtt.next_level_info(1_000_000),
+            (BlindRuleItem::new(10, 20), 60_000)
+        );
+        assert_eq!(
+            mtt.next_level_info(1_059_999),
+            (BlindRuleItem::new(10, 20), 1)
+        );
+        assert_eq!(
+            mtt.next_level_info(1_060_000),
+            (BlindRuleItem::new(15, 30), 60_000)
+        );
+
+        mtt.time_elapsed = 60_000;
+        assert_eq!(mtt.current_level_index(), 1);
+        assert_eq!(mtt.calc_blinds().unwrap(), (100, 200, 0));
+
+        // Beyond the last level, the structure caps
+        mtt.time_elapsed = 600_000;
+        assert_eq!(mtt.current_level_index(), 2);
+        assert_eq!(mtt.calc_blinds().unwrap(), (150, 300, 0));
+        assert_eq!(
+            mtt.next_level_info(1_000_000),
+            (BlindRuleItem::new(15, 30), 0)
+        );
+    }
+
+    #[test]
+    fn test_next_level_info_includes_break() {
+        let mut mtt = Mtt::default();
+        mtt.stage = MttStage::Playing;
+        mtt.timestamp = 1_000_000;
+        mtt.blind_info.breaks = vec![BreakRule {
+            after_level: 1,
+            duration: 30_000,
+        }];
+
+        // In the break, 20s left
+        mtt.time_elapsed = 70_000;
+        assert_eq!(mtt.current_level_index(), 1);
+        assert_eq!(
+            mtt.next_level_info(1_000_000),
+            (BlindRuleItem::new(15, 30), 80_000)
+        );
+    }
+
     // Test sort ranks
 
     #[test]
@@ -1343,4 +3145,111 @@ mod tests {
         assert_eq!(mtt.ranks[3].id, 4);
         assert_eq!(mtt.ranks[4].id, 5);
     }
+    #[test]
+    fn test_rebuy_after_busting_reseats_at_sparse_table() {
+        let mut mtt = create_mtt_with_players(&[6, 6, 3], 6);
+        let mut effect = Effect::default();
+        mtt.stage = MttStage::Playing;
+        mtt.blind_info.blind_interval = 60_000;
+        mtt.blind_info.blind_base = 10;
+        mtt.blind_info.with_default_blind_rules();
+
+        // Player 2 busts at table 1
+        let mut chips_change = BTreeMap::new();
+        chips_change.insert(2, ChipsChange::Sub(10000));
+        mtt.apply_chips_change(&mut effect, chips_change).unwrap();
+        assert_eq!(
+            mtt.get_rank(2).map(|r| &r.status),
+            Some(&PlayerRankStatus::Out)
+        );
+        assert_eq!(mtt.table_assigns.get(&2), None);
+
+        let mut effect = Effect::default();
+        mtt.sit_in_player(&mut effect, 2).unwrap();
+
+        let rank = mtt.get_rank(2).unwrap();
+        assert_eq!(rank.status, PlayerRankStatus::Alive);
+        assert_eq!(rank.chips, 10000);
+        assert_eq!(mtt.alives, 15);
+        assert_eq!(mtt.table_assigns.get(&2), Some(&3));
+        assert_eq!(mtt.tables.get(&3).map(|t| t.players.len()), Some(4));
+        assert!(mtt.tables[&1].players.iter().all(|p| p.id != 2));
+        assert_eq!(mtt.ranks.iter().filter(|r| r.id == 2).count(), 1);
+    }
+
+    #[test]
+    fn test_reentry_rejected_over_max_entries() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.max_entries = 2;
+        let mut effect = Effect::default();
+        let bust = BTreeMap::from([(1, ChipsChange::Add(10000)), (2, ChipsChange::Sub(10000))]);
+
+        mtt.apply_chips_change(&mut effect, bust.clone()).unwrap();
+        assert_eq!(mtt.entry_rejection(2, 100), None);
+        mtt.accept_entry(&mut effect, 2, 100).unwrap();
+        assert_eq!(mtt.get_rank(2).map(|r| r.entries), Some(2));
+        assert_eq!(mtt.entry_rejection(2, 100), Some("Player Has Chips"));
+
+        // The third entry is over the limit
+        mtt.apply_chips_change(&mut effect, bust).unwrap();
+        assert_eq!(mtt.entry_rejection(2, 100), Some("Max Entries"));
+        assert_eq!(mtt.entry_rejection(9, 100), Some("Player Not In Game"));
+    }
+
+    #[test]
+    fn test_blinds_frozen_in_headsup() {
+        let mut mtt = create_mtt_with_players(&[2], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.freeze_blinds_headsup = true;
+        mtt.entry_close_time = 10_000;
+        mtt.update_alives();
+
+        // Late registration is still open, the clock keeps going
+        let mut effect = Effect::default();
+        effect.timestamp = 5_000;
+        mtt.handle_event(&mut effect, Event::Ready).unwrap();
+        assert_eq!(mtt.time_elapsed, 5_000);
+
+        let mut effect = Effect::default();
+        effect.timestamp = 20_000;
+        mtt.handle_event(&mut effect, Event::Ready).unwrap();
+        assert_eq!(mtt.time_elapsed, 5_000);
+        assert_eq!(mtt.timestamp, 20_000);
+
+        // Resume when the game is not heads-up anymore
+        mtt.ranks
+            .push(PlayerRank::new(3, 10000, PlayerRankStatus::Alive, 2));
+        mtt.update_alives();
+        let mut effect = Effect::default();
+        effect.timestamp = 21_000;
+        mtt.handle_event(&mut effect, Event::Ready).unwrap();
+        assert_eq!(mtt.time_elapsed, 6_000);
+    }
+
+    #[test]
+    fn test_latereg_with_avg_stack() {
+        let mut mtt = create_mtt_with_players(&[3], 6);
+        mtt.stage = MttStage::Playing;
+        mtt.latereg_avg_stack = true;
+        mtt.blind_info.blind_interval = 60_000;
+        mtt.blind_info.blind_base = 10;
+        mtt.blind_info.with_default_blind_rules();
+        mtt.ranks[0].chips = 20000;
+        mtt.ranks[1].chips = 15000;
+        mtt.ranks[2].chips = 10000;
+        mtt.ranks
+            .push(PlayerRank::new(4, 0, PlayerRankStatus::Out, 3));
+        let mut effect = Effect::default();
+
+        mtt.sit_in_player(&mut effect, 4).unwrap();
+
+        assert_eq!(mtt.get_rank(4).map(|r| r.chips), Some(15000));
+        assert_eq!(mtt.alives, 4);
+        let table = mtt.tables.get(&1).unwrap();
+        assert_eq!(
+            table.players.iter().find(|p| p.id == 4).map(|p| p.chips),
+            Some(15000)
+        );
+    }
 }
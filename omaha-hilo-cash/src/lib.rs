@@ -0,0 +1,24 @@
+//! Omaha Hi-Lo (8-or-better) cash table.  Each player is dealt four
+//! hole cards and makes the hands with exactly two of them.  A pot is
+//! split between the best high and the best qualifying low, or taken
+//! by the high when no low qualifies.
+use race_api::prelude::*;
+use race_holdem_base::essential::GameVariant;
+use race_holdem_base::game::Holdem;
+use race_proc_macro::game_handler;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+#[game_handler]
+pub struct OmahaHiLoVariant(Holdem);
+
+impl GameHandler for OmahaHiLoVariant {
+    fn init_state(init_account: InitAccount) -> Result<Self, HandleError> {
+        let mut holdem = Holdem::init_state(init_account)?;
+        holdem.variant = GameVariant::OmahaHiLo;
+        Ok(Self(holdem))
+    }
+
+    fn handle_event(&mut self, effect: &mut Effect, event: Event) -> Result<(), HandleError> {
+        self.0.handle_event(effect, event)
+    }
+}
@@ -0,0 +1,23 @@
+//! Short deck (6+) Hold'em cash table.  It plays with the 36 cards
+//! from 6 to A, where a flush beats a full house and A-6-7-8-9 is the
+//! lowest straight.
+use race_api::prelude::*;
+use race_holdem_base::essential::DeckSpec;
+use race_holdem_base::game::Holdem;
+use race_proc_macro::game_handler;
+
+#[derive(BorshSerialize, BorshDeserialize)]
+#[game_handler]
+pub struct ShortDeckCash(Holdem);
+
+impl GameHandler for ShortDeckCash {
+    fn init_state(init_account: InitAccount) -> Result<Self, HandleError> {
+        let mut holdem = Holdem::init_state(init_account)?;
+        holdem.deck_spec = DeckSpec::ShortDeck36;
+        Ok(Self(holdem))
+    }
+
+    fn handle_event(&mut self, effect: &mut Effect, event: Event) -> Result<(), HandleError> {
+        self.0.handle_event(effect, event)
+    }
+}